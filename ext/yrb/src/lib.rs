@@ -5,8 +5,14 @@ use crate::yawareness::{YAwareness, YAwarenessEvent};
 use crate::ydiff::YDiff;
 use crate::ydoc::YDoc;
 use crate::ymap::YMap;
+use crate::yorigin::YOrigin;
+use crate::yroom::YRoom;
+use crate::ysnapshot::YSnapshot;
+use crate::ysticky_index::YStickyIndex;
+use crate::ysync::YSyncMessage;
 use crate::ytext::YText;
 use crate::ytransaction::YTransaction;
+use crate::yundo_manager::YUndoManager;
 use crate::yxml_element::YXmlElement;
 use crate::yxml_fragment::YXmlFragment;
 use crate::yxml_text::YXmlText;
@@ -20,9 +26,18 @@ mod yattrs;
 mod yawareness;
 mod ydiff;
 mod ydoc;
+mod yerror;
+mod yevent;
 mod ymap;
+mod yorigin;
+mod yroom;
+mod ysnapshot;
+mod ysticky_index;
+mod ysync;
 mod ytext;
 mod ytransaction;
+mod yundo_manager;
+mod yupdate;
 mod yvalue;
 mod yxml_element;
 mod yxml_fragment;
@@ -32,6 +47,8 @@ mod yxml_text;
 fn init(ruby: &Ruby) -> Result<(), Error> {
     let module = ruby.define_module("Y").expect("cannot define ::Y module");
 
+    yerror::define_errors(ruby);
+
     let yarray = module
         .define_class("Array", ruby.class_object())
         .expect("cannot find class Y::Array");
@@ -57,6 +74,12 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
     yarray
         .define_private_method("yarray_observe", method!(YArray::yarray_observe, 1))
         .expect("cannot define private method: yarray_observe");
+    yarray
+        .define_private_method(
+            "yarray_observe_deep",
+            method!(YArray::yarray_observe_deep, 2),
+        )
+        .expect("cannot define private method: yarray_observe_deep");
     yarray
         .define_private_method("yarray_push_back", method!(YArray::yarray_push_back, 2))
         .expect("cannot define private method: yarray_push_back");
@@ -72,22 +95,52 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
             method!(YArray::yarray_remove_range, 3),
         )
         .expect("cannot define private method: yarray_remove_range");
+    yarray
+        .define_private_method(
+            "yarray_sticky_index",
+            method!(YArray::yarray_sticky_index, 3),
+        )
+        .expect("cannot define private method: yarray_sticky_index");
     yarray
         .define_private_method("yarray_to_a", method!(YArray::yarray_to_a, 1))
         .expect("cannot define private method: yarray_to_a");
     yarray
         .define_private_method("yarray_unobserve", method!(YArray::yarray_unobserve, 1))
         .expect("cannot define private method: yarray_unobserve");
+    yarray
+        .define_private_method(
+            "yarray_unobserve_deep",
+            method!(YArray::yarray_unobserve_deep, 1),
+        )
+        .expect("cannot define private method: yarray_unobserve_deep");
 
     let ydoc = module
         .define_class("Doc", ruby.class_object())
         .expect("cannot define class Y::Doc");
     ydoc.define_singleton_method("new", function!(YDoc::ydoc_new, -1))
         .expect("cannot define singleton method: ydoc_new");
+    ydoc.define_private_method("ydoc_auto_load", method!(YDoc::ydoc_auto_load, 0))
+        .expect("cannot define private method: ydoc_auto_load");
+    ydoc.define_private_method("ydoc_client_id", method!(YDoc::ydoc_client_id, 0))
+        .expect("cannot define private method: ydoc_client_id");
+    ydoc.define_private_method("ydoc_collection_id", method!(YDoc::ydoc_collection_id, 0))
+        .expect("cannot define private method: ydoc_collection_id");
     ydoc.define_private_method("ydoc_encode_diff_v1", method!(YDoc::ydoc_encode_diff_v1, 2))
         .expect("cannot define private method: ydoc_encode_diff_v1");
     ydoc.define_private_method("ydoc_encode_diff_v2", method!(YDoc::ydoc_encode_diff_v2, 2))
         .expect("cannot define private method: ydoc_encode_diff_v2");
+    ydoc.define_private_method(
+        "ydoc_encode_state_from_snapshot_v1",
+        method!(YDoc::ydoc_encode_state_from_snapshot_v1, 2),
+    )
+    .expect("cannot define private method: ydoc_encode_state_from_snapshot_v1");
+    ydoc.define_private_method(
+        "ydoc_encode_state_from_snapshot_v2",
+        method!(YDoc::ydoc_encode_state_from_snapshot_v2, 2),
+    )
+    .expect("cannot define private method: ydoc_encode_state_from_snapshot_v2");
+    ydoc.define_private_method("ydoc_guid", method!(YDoc::ydoc_guid, 0))
+        .expect("cannot define private method: ydoc_guid");
     ydoc.define_private_method(
         "ydoc_get_or_insert_array",
         method!(YDoc::ydoc_get_or_insert_array, 1),
@@ -118,11 +171,79 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
         method!(YDoc::ydoc_get_or_insert_xml_text, 1),
     )
     .expect("cannot define private method: ydoc_get_or_insert_xml_text");
-    ydoc.define_private_method("ydoc_transact", method!(YDoc::ydoc_transact, 0))
+    ydoc.define_private_method("ydoc_load", method!(YDoc::ydoc_load, 1))
+        .expect("cannot define private method: ydoc_load");
+    ydoc.define_private_method("ydoc_offset_kind", method!(YDoc::ydoc_offset_kind, 0))
+        .expect("cannot define private method: ydoc_offset_kind");
+    ydoc.define_private_method("ydoc_pending", method!(YDoc::ydoc_pending, 1))
+        .expect("cannot define private method: ydoc_pending");
+    ydoc.define_private_method(
+        "ydoc_read_transaction",
+        method!(YDoc::ydoc_read_transaction, 0),
+    )
+    .expect("cannot define private method: ydoc_read_transaction");
+    ydoc.define_private_method("ydoc_roots", method!(YDoc::ydoc_roots, 1))
+        .expect("cannot define private method: ydoc_roots");
+    ydoc.define_private_method("ydoc_should_load", method!(YDoc::ydoc_should_load, 0))
+        .expect("cannot define private method: ydoc_should_load");
+    ydoc.define_private_method("ydoc_skip_gc", method!(YDoc::ydoc_skip_gc, 0))
+        .expect("cannot define private method: ydoc_skip_gc");
+    ydoc.define_private_method("ydoc_stats", method!(YDoc::ydoc_stats, 1))
+        .expect("cannot define private method: ydoc_stats");
+    ydoc.define_private_method("ydoc_subdocs", method!(YDoc::ydoc_subdocs, 1))
+        .expect("cannot define private method: ydoc_subdocs");
+    ydoc.define_private_method("ydoc_to_h", method!(YDoc::ydoc_to_h, 1))
+        .expect("cannot define private method: ydoc_to_h");
+    ydoc.define_private_method("ydoc_transact", method!(YDoc::ydoc_transact, 2))
         .expect("cannot define private method: ydoc_transact");
 
-    ydoc.define_private_method("ydoc_observe_update", method!(YDoc::ydoc_observe_update, 1))
+    ydoc.define_private_method(
+        "ydoc_observe_after_transaction",
+        method!(YDoc::ydoc_observe_after_transaction, 2),
+    )
+    .expect("cannot define private method: ydoc_observe_after_transaction");
+    ydoc.define_private_method(
+        "ydoc_observe_transaction_cleanup",
+        method!(YDoc::ydoc_observe_transaction_cleanup, 2),
+    )
+    .expect("cannot define private method: ydoc_observe_transaction_cleanup");
+    ydoc.define_private_method("ydoc_observe_update", method!(YDoc::ydoc_observe_update, 2))
         .expect("cannot define private method: ydoc_observe_update");
+    ydoc.define_private_method(
+        "ydoc_observe_update_v2",
+        method!(YDoc::ydoc_observe_update_v2, 2),
+    )
+    .expect("cannot define private method: ydoc_observe_update_v2");
+    ydoc.define_private_method(
+        "ydoc_observe_subdocs",
+        method!(YDoc::ydoc_observe_subdocs, 1),
+    )
+    .expect("cannot define private method: ydoc_observe_subdocs");
+    ydoc.define_private_method(
+        "ydoc_unobserve_after_transaction",
+        method!(YDoc::ydoc_unobserve_after_transaction, 1),
+    )
+    .expect("cannot define private method: ydoc_unobserve_after_transaction");
+    ydoc.define_private_method(
+        "ydoc_unobserve_subdocs",
+        method!(YDoc::ydoc_unobserve_subdocs, 1),
+    )
+    .expect("cannot define private method: ydoc_unobserve_subdocs");
+    ydoc.define_private_method(
+        "ydoc_unobserve_transaction_cleanup",
+        method!(YDoc::ydoc_unobserve_transaction_cleanup, 1),
+    )
+    .expect("cannot define private method: ydoc_unobserve_transaction_cleanup");
+    ydoc.define_private_method(
+        "ydoc_unobserve_update",
+        method!(YDoc::ydoc_unobserve_update, 1),
+    )
+    .expect("cannot define private method: ydoc_unobserve_update");
+    ydoc.define_private_method(
+        "ydoc_unobserve_update_v2",
+        method!(YDoc::ydoc_unobserve_update_v2, 1),
+    )
+    .expect("cannot define private method: ydoc_unobserve_update_v2");
 
     let ymap = module
         .define_class("Map", ruby.class_object())
@@ -140,6 +261,8 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
         .expect("cannot define private method: ymap_insert");
     ymap.define_private_method("ymap_observe", method!(YMap::ymap_observe, 1))
         .expect("cannot define private method: ymap_observe");
+    ymap.define_private_method("ymap_observe_deep", method!(YMap::ymap_observe_deep, 2))
+        .expect("cannot define private method: ymap_observe_deep");
     ymap.define_private_method("ymap_remove", method!(YMap::ymap_remove, 2))
         .expect("cannot define private method: ymap_remove");
     ymap.define_private_method("ymap_size", method!(YMap::ymap_size, 1))
@@ -148,6 +271,95 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
         .expect("cannot define private method: ymap_to_h");
     ymap.define_private_method("ymap_unobserve", method!(YMap::ymap_unobserve, 1))
         .expect("cannot define private method: ymap_unobserve");
+    ymap.define_private_method("ymap_unobserve_deep", method!(YMap::ymap_unobserve_deep, 1))
+        .expect("cannot define private method: ymap_unobserve_deep");
+
+    let yorigin = module
+        .define_class("Origin", ruby.class_object())
+        .expect("cannot define class Y::Origin");
+
+    yorigin
+        .define_private_method("yorigin_local", method!(YOrigin::yorigin_local, 0))
+        .expect("cannot define private method: yorigin_local");
+    yorigin
+        .define_private_method("yorigin_value", method!(YOrigin::yorigin_value, 0))
+        .expect("cannot define private method: yorigin_value");
+
+    let ysnapshot = module
+        .define_class("Snapshot", ruby.class_object())
+        .expect("cannot define class Y::Snapshot");
+
+    ysnapshot
+        .define_singleton_method(
+            "ysnapshot_decode",
+            function!(YSnapshot::ysnapshot_decode, 1),
+        )
+        .expect("cannot define singleton method: ysnapshot_decode");
+    ysnapshot
+        .define_singleton_method(
+            "ysnapshot_decode_v2",
+            function!(YSnapshot::ysnapshot_decode_v2, 1),
+        )
+        .expect("cannot define singleton method: ysnapshot_decode_v2");
+    ysnapshot
+        .define_private_method("ysnapshot_encode", method!(YSnapshot::ysnapshot_encode, 0))
+        .expect("cannot define private method: ysnapshot_encode");
+    ysnapshot
+        .define_private_method(
+            "ysnapshot_encode_v2",
+            method!(YSnapshot::ysnapshot_encode_v2, 0),
+        )
+        .expect("cannot define private method: ysnapshot_encode_v2");
+    ysnapshot
+        .define_private_method("ysnapshot_equals", method!(YSnapshot::ysnapshot_equals, 1))
+        .expect("cannot define private method: ysnapshot_equals");
+
+    let ysticky_index = module
+        .define_class("StickyIndex", ruby.class_object())
+        .expect("cannot define class Y::StickyIndex");
+
+    ysticky_index
+        .define_singleton_method(
+            "ysticky_index_decode",
+            function!(YStickyIndex::ysticky_index_decode, 1),
+        )
+        .expect("cannot define singleton method: ysticky_index_decode");
+    ysticky_index
+        .define_singleton_method(
+            "ysticky_index_from_h",
+            function!(YStickyIndex::ysticky_index_from_h, 1),
+        )
+        .expect("cannot define singleton method: ysticky_index_from_h");
+    ysticky_index
+        .define_private_method(
+            "ysticky_index_assoc",
+            method!(YStickyIndex::ysticky_index_assoc, 0),
+        )
+        .expect("cannot define private method: ysticky_index_assoc");
+    ysticky_index
+        .define_private_method(
+            "ysticky_index_encode",
+            method!(YStickyIndex::ysticky_index_encode, 0),
+        )
+        .expect("cannot define private method: ysticky_index_encode");
+    ysticky_index
+        .define_private_method(
+            "ysticky_index_equals",
+            method!(YStickyIndex::ysticky_index_equals, 1),
+        )
+        .expect("cannot define private method: ysticky_index_equals");
+    ysticky_index
+        .define_private_method(
+            "ysticky_index_offset",
+            method!(YStickyIndex::ysticky_index_offset, 1),
+        )
+        .expect("cannot define private method: ysticky_index_offset");
+    ysticky_index
+        .define_private_method(
+            "ysticky_index_to_h",
+            method!(YStickyIndex::ysticky_index_to_h, 0),
+        )
+        .expect("cannot define private method: ysticky_index_to_h");
 
     let ytransaction = module
         .define_class("Transaction", ruby.class_object())
@@ -210,6 +422,18 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
             method!(YTransaction::ytransaction_get_xml_text, 1),
         )
         .expect("cannot define private method: ytransaction_get_xml_text");
+    ytransaction
+        .define_private_method(
+            "ytransaction_read_only",
+            method!(YTransaction::ytransaction_read_only, 0),
+        )
+        .expect("cannot define private method: ytransaction_read_only");
+    ytransaction
+        .define_private_method(
+            "ytransaction_snapshot",
+            method!(YTransaction::ytransaction_snapshot, 0),
+        )
+        .expect("cannot define private method: ytransaction_snapshot");
     ytransaction
         .define_private_method(
             "ytransaction_state_vector",
@@ -222,6 +446,18 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
             method!(YTransaction::ytransaction_state_vector_v2, 0),
         )
         .expect("cannot define private method: ytransaction_state_vector_v2");
+    ytransaction
+        .define_private_method(
+            "ytransaction_update_summary",
+            method!(YTransaction::ytransaction_update_summary, 1),
+        )
+        .expect("cannot define private method: ytransaction_update_summary");
+    ytransaction
+        .define_private_method(
+            "ytransaction_update_summary_v2",
+            method!(YTransaction::ytransaction_update_summary_v2, 1),
+        )
+        .expect("cannot define private method: ytransaction_update_summary_v2");
 
     let ytext = module
         .define_class("Text", ruby.class_object())
@@ -257,18 +493,131 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
     ytext
         .define_private_method("ytext_observe", method!(YText::ytext_observe, 1))
         .expect("cannot define private method: ytext_observe");
+    ytext
+        .define_private_method("ytext_observe_deep", method!(YText::ytext_observe_deep, 2))
+        .expect("cannot define private method: ytext_observe_deep");
     ytext
         .define_private_method("ytext_push", method!(YText::ytext_push, 2))
         .expect("cannot define private method: ytext_push");
     ytext
         .define_private_method("ytext_remove_range", method!(YText::ytext_remove_range, 3))
         .expect("cannot define private method: ytext_remove_range");
+    ytext
+        .define_private_method("ytext_sticky_index", method!(YText::ytext_sticky_index, 3))
+        .expect("cannot define private method: ytext_sticky_index");
     ytext
         .define_private_method("ytext_to_s", method!(YText::ytext_to_s, 1))
         .expect("cannot define private method: ytext_to_s");
     ytext
         .define_private_method("ytext_unobserve", method!(YText::ytext_unobserve, 1))
         .expect("cannot define private method: ytext_unobserve");
+    ytext
+        .define_private_method(
+            "ytext_unobserve_deep",
+            method!(YText::ytext_unobserve_deep, 1),
+        )
+        .expect("cannot define private method: ytext_unobserve_deep");
+
+    let yundo_manager = module
+        .define_class("UndoManager", ruby.class_object())
+        .expect("cannot define class Y::UndoManager");
+
+    yundo_manager
+        .define_singleton_method(
+            "yundo_manager_new",
+            function!(YUndoManager::yundo_manager_new, 4),
+        )
+        .expect("cannot define singleton method: yundo_manager_new");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_can_redo",
+            method!(YUndoManager::yundo_manager_can_redo, 0),
+        )
+        .expect("cannot define private method: yundo_manager_can_redo");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_can_undo",
+            method!(YUndoManager::yundo_manager_can_undo, 0),
+        )
+        .expect("cannot define private method: yundo_manager_can_undo");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_clear",
+            method!(YUndoManager::yundo_manager_clear, 0),
+        )
+        .expect("cannot define private method: yundo_manager_clear");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_exclude_origin",
+            method!(YUndoManager::yundo_manager_exclude_origin, 1),
+        )
+        .expect("cannot define private method: yundo_manager_exclude_origin");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_expand_scope",
+            method!(YUndoManager::yundo_manager_expand_scope, 1),
+        )
+        .expect("cannot define private method: yundo_manager_expand_scope");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_include_origin",
+            method!(YUndoManager::yundo_manager_include_origin, 1),
+        )
+        .expect("cannot define private method: yundo_manager_include_origin");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_observe_item_added",
+            method!(YUndoManager::yundo_manager_observe_item_added, 1),
+        )
+        .expect("cannot define private method: yundo_manager_observe_item_added");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_observe_item_popped",
+            method!(YUndoManager::yundo_manager_observe_item_popped, 1),
+        )
+        .expect("cannot define private method: yundo_manager_observe_item_popped");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_observe_item_updated",
+            method!(YUndoManager::yundo_manager_observe_item_updated, 1),
+        )
+        .expect("cannot define private method: yundo_manager_observe_item_updated");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_redo",
+            method!(YUndoManager::yundo_manager_redo, 0),
+        )
+        .expect("cannot define private method: yundo_manager_redo");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_stop_capturing",
+            method!(YUndoManager::yundo_manager_stop_capturing, 0),
+        )
+        .expect("cannot define private method: yundo_manager_stop_capturing");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_undo",
+            method!(YUndoManager::yundo_manager_undo, 0),
+        )
+        .expect("cannot define private method: yundo_manager_undo");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_unobserve_item_added",
+            method!(YUndoManager::yundo_manager_unobserve_item_added, 1),
+        )
+        .expect("cannot define private method: yundo_manager_unobserve_item_added");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_unobserve_item_popped",
+            method!(YUndoManager::yundo_manager_unobserve_item_popped, 1),
+        )
+        .expect("cannot define private method: yundo_manager_unobserve_item_popped");
+    yundo_manager
+        .define_private_method(
+            "yundo_manager_unobserve_item_updated",
+            method!(YUndoManager::yundo_manager_unobserve_item_updated, 1),
+        )
+        .expect("cannot define private method: yundo_manager_unobserve_item_updated");
 
     let yxml_element = module
         .define_class("XMLElement", ruby.class_object())
@@ -334,6 +683,12 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
             method!(YXmlElement::yxml_element_observe, 1),
         )
         .expect("cannot define private method: yxml_element_observe");
+    yxml_element
+        .define_private_method(
+            "yxml_element_observe_deep",
+            method!(YXmlElement::yxml_element_observe_deep, 2),
+        )
+        .expect("cannot define private method: yxml_element_observe_deep");
     yxml_element
         .define_private_method(
             "yxml_element_parent",
@@ -412,6 +767,12 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
             method!(YXmlElement::yxml_element_unobserve, 1),
         )
         .expect("cannot define private method: yxml_element_unobserve");
+    yxml_element
+        .define_private_method(
+            "yxml_element_unobserve_deep",
+            method!(YXmlElement::yxml_element_unobserve_deep, 1),
+        )
+        .expect("cannot define private method: yxml_element_unobserve_deep");
 
     let yxml_fragment = module
         .define_class("XMLFragment", ruby.class_object())
@@ -441,6 +802,12 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
             method!(YXmlFragment::yxml_fragment_len, 1),
         )
         .expect("cannot define private method: yxml_fragment_len");
+    yxml_fragment
+        .define_private_method(
+            "yxml_fragment_observe_deep",
+            method!(YXmlFragment::yxml_fragment_observe_deep, 2),
+        )
+        .expect("cannot define private method: yxml_fragment_observe_deep");
     yxml_fragment
         .define_private_method(
             "yxml_fragment_parent",
@@ -477,6 +844,12 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
             method!(YXmlFragment::yxml_fragment_to_s, 1),
         )
         .expect("cannot define private method: yxml_fragment_to_s");
+    yxml_fragment
+        .define_private_method(
+            "yxml_fragment_unobserve_deep",
+            method!(YXmlFragment::yxml_fragment_unobserve_deep, 1),
+        )
+        .expect("cannot define private method: yxml_fragment_unobserve_deep");
 
     let yxml_text = module
         .define_class("XMLText", ruby.class_object())
@@ -533,6 +906,12 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
             method!(YXmlText::yxml_text_next_sibling, 1),
         )
         .expect("cannot define private method: yxml_text_next_sibling");
+    yxml_text
+        .define_private_method(
+            "yxml_text_observe_deep",
+            method!(YXmlText::yxml_text_observe_deep, 2),
+        )
+        .expect("cannot define private method: yxml_text_observe_deep");
     yxml_text
         .define_private_method("yxml_text_parent", method!(YXmlText::yxml_text_parent, 0))
         .expect("cannot define private method: yxml_text_parent");
@@ -551,15 +930,27 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
             method!(YXmlText::yxml_text_remove_range, 3),
         )
         .expect("cannot define private method: yxml_text_remove_range");
+    yxml_text
+        .define_private_method(
+            "yxml_text_sticky_index",
+            method!(YXmlText::yxml_text_sticky_index, 3),
+        )
+        .expect("cannot define private method: yxml_text_sticky_index");
     yxml_text
         .define_private_method("yxml_text_to_s", method!(YXmlText::yxml_text_to_s, 1))
         .expect("cannot define private method: yxml_text_to_s");
+    yxml_text
+        .define_private_method(
+            "yxml_text_unobserve_deep",
+            method!(YXmlText::yxml_text_unobserve_deep, 1),
+        )
+        .expect("cannot define private method: yxml_text_unobserve_deep");
 
     let yawareness = module
         .define_class("Awareness", ruby.class_object())
         .expect("cannot define class Y::Awareness");
     yawareness
-        .define_singleton_method("new", function!(YAwareness::yawareness_new, 0))
+        .define_singleton_method("yawareness_new", function!(YAwareness::yawareness_new, 1))
         .expect("cannot define singleton method: yawareness_new");
     yawareness
         .define_private_method(
@@ -591,6 +982,9 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
             method!(YAwareness::yawareness_local_state, 0),
         )
         .expect("cannot define private method: yawareness_local_state");
+    yawareness
+        .define_private_method("yawareness_meta", method!(YAwareness::yawareness_meta, 1))
+        .expect("cannot define private method: yawareness_meta");
     yawareness
         .define_private_method(
             "yawareness_on_update",
@@ -609,6 +1003,12 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
             method!(YAwareness::yawareness_set_local_state, 1),
         )
         .expect("cannot define private method: yawareness_set_local_state");
+    yawareness
+        .define_private_method(
+            "yawareness_unobserve",
+            method!(YAwareness::yawareness_unobserve, 1),
+        )
+        .expect("cannot define private method: yawareness_unobserve");
     yawareness
         .define_private_method(
             "yawareness_update",
@@ -634,6 +1034,65 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
     yawareness_event
         .define_method("removed", method!(YAwarenessEvent::removed, 0))
         .expect("cannot define private method: removed");
+    yawareness_event
+        .define_private_method(
+            "yawareness_event_update",
+            method!(YAwarenessEvent::yawareness_event_update, 0),
+        )
+        .expect("cannot define private method: yawareness_event_update");
+
+    module
+        .define_class("AwarenessSubscription", ruby.class_object())
+        .expect("cannot define class Y::AwarenessSubscription");
+
+    let yroom = module
+        .define_class("Room", ruby.class_object())
+        .expect("cannot define class Y::Room");
+    yroom
+        .define_singleton_method("yroom_new", function!(YRoom::yroom_new, 0))
+        .expect("cannot define singleton method: yroom_new");
+    yroom
+        .define_private_method(
+            "yroom_broadcast_awareness",
+            method!(YRoom::yroom_broadcast_awareness, 1),
+        )
+        .expect("cannot define private method: yroom_broadcast_awareness");
+    yroom
+        .define_private_method(
+            "yroom_broadcast_update",
+            method!(YRoom::yroom_broadcast_update, 2),
+        )
+        .expect("cannot define private method: yroom_broadcast_update");
+    yroom
+        .define_private_method("yroom_connect", method!(YRoom::yroom_connect, 3))
+        .expect("cannot define private method: yroom_connect");
+    yroom
+        .define_private_method("yroom_connected", method!(YRoom::yroom_connected, 1))
+        .expect("cannot define private method: yroom_connected");
+    yroom
+        .define_private_method("yroom_connections", method!(YRoom::yroom_connections, 0))
+        .expect("cannot define private method: yroom_connections");
+    yroom
+        .define_private_method("yroom_disconnect", method!(YRoom::yroom_disconnect, 1))
+        .expect("cannot define private method: yroom_disconnect");
+    yroom
+        .define_private_method("yroom_protocol", method!(YRoom::yroom_protocol, 1))
+        .expect("cannot define private method: yroom_protocol");
+    yroom
+        .define_private_method("yroom_send", method!(YRoom::yroom_send, 2))
+        .expect("cannot define private method: yroom_send");
+    yroom
+        .define_private_method(
+            "yroom_set_receiving",
+            method!(YRoom::yroom_set_receiving, 1),
+        )
+        .expect("cannot define private method: yroom_set_receiving");
+    yroom
+        .define_private_method(
+            "yroom_take_stale_clients",
+            method!(YRoom::yroom_take_stale_clients, 0),
+        )
+        .expect("cannot define private method: yroom_take_stale_clients");
 
     let ydiff = module
         .define_class("Diff", ruby.class_object())
@@ -645,5 +1104,154 @@ fn init(ruby: &Ruby) -> Result<(), Error> {
         .define_private_method("ydiff_attrs", method!(YDiff::ydiff_attrs, 0))
         .expect("cannot define private method: attrs");
 
+    let yupdate = module
+        .define_module("Update")
+        .expect("cannot define module Y::Update");
+    yupdate
+        .define_singleton_method(
+            "yupdate_compact_v1",
+            function!(yupdate::yupdate_compact_v1, 1),
+        )
+        .expect("cannot define singleton method: yupdate_compact_v1");
+    yupdate
+        .define_singleton_method(
+            "yupdate_compact_v2",
+            function!(yupdate::yupdate_compact_v2, 1),
+        )
+        .expect("cannot define singleton method: yupdate_compact_v2");
+    yupdate
+        .define_singleton_method(
+            "yupdate_decode_v1",
+            function!(yupdate::yupdate_decode_v1, 1),
+        )
+        .expect("cannot define singleton method: yupdate_decode_v1");
+    yupdate
+        .define_singleton_method(
+            "yupdate_decode_v2",
+            function!(yupdate::yupdate_decode_v2, 1),
+        )
+        .expect("cannot define singleton method: yupdate_decode_v2");
+    yupdate
+        .define_singleton_method("yupdate_diff_v1", function!(yupdate::yupdate_diff_v1, 2))
+        .expect("cannot define singleton method: yupdate_diff_v1");
+    yupdate
+        .define_singleton_method("yupdate_diff_v2", function!(yupdate::yupdate_diff_v2, 2))
+        .expect("cannot define singleton method: yupdate_diff_v2");
+    yupdate
+        .define_singleton_method("yupdate_merge_v1", function!(yupdate::yupdate_merge_v1, 1))
+        .expect("cannot define singleton method: yupdate_merge_v1");
+    yupdate
+        .define_singleton_method("yupdate_merge_v2", function!(yupdate::yupdate_merge_v2, 1))
+        .expect("cannot define singleton method: yupdate_merge_v2");
+    yupdate
+        .define_singleton_method(
+            "yupdate_state_vector_v1",
+            function!(yupdate::yupdate_state_vector_v1, 1),
+        )
+        .expect("cannot define singleton method: yupdate_state_vector_v1");
+    yupdate
+        .define_singleton_method(
+            "yupdate_state_vector_v2",
+            function!(yupdate::yupdate_state_vector_v2, 1),
+        )
+        .expect("cannot define singleton method: yupdate_state_vector_v2");
+    yupdate
+        .define_singleton_method("yupdate_v1_to_v2", function!(yupdate::yupdate_v1_to_v2, 1))
+        .expect("cannot define singleton method: yupdate_v1_to_v2");
+    yupdate
+        .define_singleton_method("yupdate_v2_to_v1", function!(yupdate::yupdate_v2_to_v1, 1))
+        .expect("cannot define singleton method: yupdate_v2_to_v1");
+
+    let ysync = module
+        .define_module("Sync")
+        .expect("cannot define module Y::Sync");
+    let ysync_message = ysync
+        .define_class("Message", ruby.class_object())
+        .expect("cannot define class Y::Sync::Message");
+    ysync_message
+        .define_singleton_method(
+            "ysync_message_auth",
+            function!(YSyncMessage::ysync_message_auth, 1),
+        )
+        .expect("cannot define singleton method: ysync_message_auth");
+    ysync_message
+        .define_singleton_method(
+            "ysync_message_awareness",
+            function!(YSyncMessage::ysync_message_awareness, 1),
+        )
+        .expect("cannot define singleton method: ysync_message_awareness");
+    ysync_message
+        .define_singleton_method(
+            "ysync_message_custom",
+            function!(YSyncMessage::ysync_message_custom, 2),
+        )
+        .expect("cannot define singleton method: ysync_message_custom");
+    ysync_message
+        .define_singleton_method(
+            "ysync_message_decode",
+            function!(YSyncMessage::ysync_message_decode, 1),
+        )
+        .expect("cannot define singleton method: ysync_message_decode");
+    ysync_message
+        .define_singleton_method(
+            "ysync_message_query_awareness",
+            function!(YSyncMessage::ysync_message_query_awareness, 0),
+        )
+        .expect("cannot define singleton method: ysync_message_query_awareness");
+    ysync_message
+        .define_singleton_method(
+            "ysync_message_sync_step1",
+            function!(YSyncMessage::ysync_message_sync_step1, 1),
+        )
+        .expect("cannot define singleton method: ysync_message_sync_step1");
+    ysync_message
+        .define_singleton_method(
+            "ysync_message_sync_step2",
+            function!(YSyncMessage::ysync_message_sync_step2, 1),
+        )
+        .expect("cannot define singleton method: ysync_message_sync_step2");
+    ysync_message
+        .define_singleton_method(
+            "ysync_message_update",
+            function!(YSyncMessage::ysync_message_update, 1),
+        )
+        .expect("cannot define singleton method: ysync_message_update");
+    ysync_message
+        .define_private_method(
+            "ysync_message_encode",
+            method!(YSyncMessage::ysync_message_encode, 0),
+        )
+        .expect("cannot define private method: ysync_message_encode");
+    ysync_message
+        .define_private_method(
+            "ysync_message_equals",
+            method!(YSyncMessage::ysync_message_equals, 1),
+        )
+        .expect("cannot define private method: ysync_message_equals");
+    ysync_message
+        .define_private_method(
+            "ysync_message_payload",
+            method!(YSyncMessage::ysync_message_payload, 0),
+        )
+        .expect("cannot define private method: ysync_message_payload");
+    ysync_message
+        .define_private_method(
+            "ysync_message_reason",
+            method!(YSyncMessage::ysync_message_reason, 0),
+        )
+        .expect("cannot define private method: ysync_message_reason");
+    ysync_message
+        .define_private_method(
+            "ysync_message_tag",
+            method!(YSyncMessage::ysync_message_tag, 0),
+        )
+        .expect("cannot define private method: ysync_message_tag");
+    ysync_message
+        .define_private_method(
+            "ysync_message_type",
+            method!(YSyncMessage::ysync_message_type, 0),
+        )
+        .expect("cannot define private method: ysync_message_type");
+
     Ok(())
 }
@@ -1,50 +1,193 @@
+use crate::ydoc::RootKind;
+use crate::yerror::type_error;
 use crate::yvalue::YValue;
 use magnus::r_hash::ForEach::Continue;
-use magnus::{Error, RHash, RString, Ruby, Symbol, Value};
+use magnus::{Error, Integer, IntoValue, RHash, RString, Ruby, Symbol, TryConvert, Value};
+use rb_sys::rb_thread_call_without_gvl2;
+use std::ffi::c_void;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::Arc;
+use std::{ptr, thread};
 use yrs::types::{Attrs, Value as YrsValue};
-use yrs::{Any, Array, Map, TransactionMut};
+use yrs::{Any, Array, Map, Origin, ReadTxn, TextRef, XmlFragmentRef};
+
+// origins are tagged with the Ruby type they were created from, so that we
+// can hand the very same value back to Ruby when reading them
+const ORIGIN_STRING: u8 = b's';
+const ORIGIN_SYMBOL: u8 = b'y';
+const ORIGIN_INTEGER: u8 = b'i';
+// transactions applying updates received from other peers are marked as
+// remote by prefixing their (optional) origin
+const ORIGIN_REMOTE: u8 = b'r';
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub(crate) struct TypeConversionError;
 
+// Encoded updates, state vectors etc. are accepted as binary strings as well
+// as arrays of bytes. Strings are copied at once, arrays are converted from
+// one Ruby object per byte.
+pub(crate) struct EncodedBytes(pub(crate) Vec<u8>);
+
+impl TryConvert for EncodedBytes {
+    fn try_convert(value: Value) -> Result<Self, Error> {
+        match RString::from_value(value) {
+            // SAFETY: the slice is copied before any other Ruby code runs
+            Some(string) => Ok(EncodedBytes(unsafe { string.as_slice() }.to_vec())),
+            None => Vec::<u8>::try_convert(value).map(EncodedBytes),
+        }
+    }
+}
+
+pub(crate) fn encoded_to_value(bytes: &[u8], binary: bool) -> Value {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    if binary {
+        ruby.str_from_slice(bytes).into_value_with(&ruby)
+    } else {
+        ruby.ary_from_iter(bytes.iter().copied())
+            .into_value_with(&ruby)
+    }
+}
+
+// Releasing the GVL lets other Ruby threads run while we are busy, but getting
+// it back can take a while when they are busy as well. Only work on inputs of
+// at least this many bytes (or clock units) is done without holding the GVL.
+pub(crate) const WITHOUT_GVL_THRESHOLD: usize = 64 * 1024;
+
+// Runs `func` without holding the GVL, if the amount of work reaches the
+// threshold. `func` must not create, read or modify any Ruby object, and must
+// not borrow or release the RefCells of our wrappers, as only the GVL protects
+// their borrow flags from other threads.
+pub(crate) fn without_gvl<F, R>(work: usize, func: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    struct Call<F, R> {
+        func: Option<F>,
+        result: Option<thread::Result<R>>,
+    }
+
+    unsafe extern "C" fn trampoline<F: FnOnce() -> R, R>(data: *mut c_void) -> *mut c_void {
+        let call = &mut *(data as *mut Call<F, R>);
+        if let Some(func) = call.func.take() {
+            // unwinding through the C frames of Ruby is undefined behavior
+            call.result = Some(panic::catch_unwind(AssertUnwindSafe(func)));
+        }
+        ptr::null_mut()
+    }
+
+    if work < WITHOUT_GVL_THRESHOLD {
+        return func();
+    }
+
+    let mut call = Call {
+        func: Some(func),
+        result: None,
+    };
+    // without an unblocking function, the call can not be interrupted. Ruby
+    // returns without calling it if an interrupt is pending, it then runs
+    // while holding the GVL instead.
+    unsafe {
+        rb_thread_call_without_gvl2(
+            Some(trampoline::<F, R>),
+            &mut call as *mut Call<F, R> as *mut c_void,
+            None,
+            ptr::null_mut(),
+        );
+    }
+    match (call.result, call.func) {
+        (Some(Ok(result)), _) => result,
+        (Some(Err(payload)), _) => panic::resume_unwind(payload),
+        (None, Some(func)) => func(),
+        (None, None) => unreachable!("function has neither been called nor kept"),
+    }
+}
+
 pub(crate) fn indifferent_hash_key(key: Value) -> Option<String> {
     RString::from_value(key)
-        .map(|v| v.to_string().unwrap())
-        .or_else(|| Symbol::from_value(key).map(|v| v.name().unwrap().to_string()))
+        .and_then(|v| v.to_string().ok())
+        .or_else(|| Symbol::from_value(key).and_then(|v| v.name().ok().map(String::from)))
+}
+
+pub(crate) fn origin_from_value(value: Value) -> Result<Origin, Error> {
+    let mut bytes: Vec<u8> = Vec::new();
+
+    if let Some(symbol) = Symbol::from_value(value) {
+        bytes.push(ORIGIN_SYMBOL);
+        bytes.extend_from_slice(symbol.name()?.as_bytes());
+    } else if let Some(string) = RString::from_value(value) {
+        bytes.push(ORIGIN_STRING);
+        bytes.extend_from_slice(string.to_string()?.as_bytes());
+    } else if let Some(integer) = Integer::from_value(value) {
+        bytes.push(ORIGIN_INTEGER);
+        bytes.extend_from_slice(&integer.to_i64()?.to_be_bytes());
+    } else {
+        return Err(type_error(
+            "origin must be of type String, Symbol or Integer",
+        ));
+    }
+
+    Ok(Origin::from(bytes.as_slice()))
+}
+
+pub(crate) fn remote_origin_from_value(value: Option<Value>) -> Result<Origin, Error> {
+    let mut bytes: Vec<u8> = vec![ORIGIN_REMOTE];
+    if let Some(value) = value {
+        bytes.extend_from_slice(origin_from_value(value)?.as_ref());
+    }
+
+    Ok(Origin::from(bytes.as_slice()))
+}
+
+pub(crate) fn origin_is_local(origin: Option<&Origin>) -> bool {
+    origin.and_then(|o| o.as_ref().first()) != Some(&ORIGIN_REMOTE)
+}
+
+pub(crate) fn origin_to_value(origin: &Origin) -> Value {
+    origin_bytes_to_value(origin.as_ref())
+}
+
+fn origin_bytes_to_value(bytes: &[u8]) -> Value {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    match bytes.split_first() {
+        Some((&ORIGIN_REMOTE, rest)) => origin_bytes_to_value(rest),
+        Some((&ORIGIN_STRING, rest)) => std::str::from_utf8(rest)
+            .map(|s| ruby.str_new(s).into_value_with(&ruby))
+            .unwrap_or_else(|_| ruby.qnil().into_value_with(&ruby)),
+        Some((&ORIGIN_SYMBOL, rest)) => std::str::from_utf8(rest)
+            .map(|s| ruby.to_symbol(s).into_value_with(&ruby))
+            .unwrap_or_else(|_| ruby.qnil().into_value_with(&ruby)),
+        Some((&ORIGIN_INTEGER, rest)) => <[u8; 8]>::try_from(rest)
+            .map(|b| i64::from_be_bytes(b).into_value_with(&ruby))
+            .unwrap_or_else(|_| ruby.qnil().into_value_with(&ruby)),
+        // origins not created by us, e.g. the ones used by an UndoManager
+        _ => ruby.qnil().into_value_with(&ruby),
+    }
 }
 
 pub(crate) fn map_rhash_to_attrs(hash: RHash) -> Result<Attrs, Error> {
     let mut a: Attrs = Default::default();
 
-    let result = hash.foreach(|key: Value, value: Value| {
+    hash.foreach(|key: Value, value: Value| {
         let k = Arc::from(key.to_string());
-        let v = Any::from(YValue::from(value));
+        let v = Any::try_from(YValue::from(value))?;
 
         a.insert(k, v);
 
         Ok(Continue)
-    });
-
-    if result.is_err() {
-        let ruby = Ruby::get().unwrap();
-        return Err(Error::new(
-            ruby.exception_runtime_error(),
-            "could not map hash to attrs",
-        ));
-    }
+    })?;
 
     Ok(a)
 }
 
-pub(crate) fn convert_yvalue_to_ruby_value(value: YrsValue, tx: &TransactionMut) -> YValue {
+pub(crate) fn convert_yvalue_to_ruby_value<T: ReadTxn>(value: YrsValue, tx: &T) -> YValue {
     let ruby = unsafe { Ruby::get_unchecked() };
     match value {
         YrsValue::Any(val) => YValue::from(val),
         YrsValue::YText(text) => YValue::from(text),
         YrsValue::YXmlElement(el) => YValue::from(el),
         YrsValue::YXmlText(text) => YValue::from(text),
+        YrsValue::YDoc(doc) => YValue::from(doc),
         YrsValue::YArray(val) => {
             let arr = ruby.ary_new();
             for item in val.iter(tx) {
@@ -63,6 +206,16 @@ pub(crate) fn convert_yvalue_to_ruby_value(value: YrsValue, tx: &TransactionMut)
             }
             YValue::from(hash)
         }
-        v => panic!("cannot map given yrs values to yvalue: {:?}", v),
+        YrsValue::YXmlFragment(fragment) => YValue::from(fragment),
+        // shared types received from other peers stay undefined until they
+        // are fetched with one of the typed getters, empty ones have no
+        // content to convert
+        YrsValue::UndefinedRef(branch) => match RootKind::infer(branch, tx) {
+            RootKind::Map => convert_yvalue_to_ruby_value(YrsValue::YMap(branch.into()), tx),
+            RootKind::Array => convert_yvalue_to_ruby_value(YrsValue::YArray(branch.into()), tx),
+            RootKind::Text => YValue::from(TextRef::from(branch)),
+            RootKind::XmlFragment => YValue::from(XmlFragmentRef::from(branch)),
+            _ => YValue::from(ruby.qnil()),
+        },
     }
 }
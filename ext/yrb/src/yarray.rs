@@ -1,12 +1,17 @@
 use crate::utils::convert_yvalue_to_ruby_value;
+use crate::ydoc::YDoc;
+use crate::yerror::{check_index, check_range, index_error};
+use crate::yevent::observe_deep;
+use crate::yorigin::{call_observer, keep_observer_error};
+use crate::ysticky_index::{sticky_index_at, YStickyIndex};
 use crate::ytransaction::YTransaction;
 use crate::yvalue::YValue;
 use magnus::block::Proc;
 use magnus::value::Qnil;
-use magnus::{Error, IntoValue, RArray, Ruby, Value};
+use magnus::{Error, IntoValue, RArray, Ruby, Symbol, TryConvert, Value};
 use std::cell::RefCell;
 use yrs::types::Change;
-use yrs::{Any, Array, ArrayRef, Observable};
+use yrs::{Any, Array, ArrayRef, DeepObservable, Doc, Observable};
 
 #[magnus::wrap(class = "Y::Array")]
 pub(crate) struct YArray(pub(crate) RefCell<ArrayRef>);
@@ -16,60 +21,99 @@ unsafe impl Send for YArray {}
 
 impl YArray {
     pub(crate) fn yarray_each(&self, transaction: &YTransaction, block: Proc) -> Result<(), Error> {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
         let arr = self.0.borrow();
-        arr.iter(tx).for_each(|val| {
+        for val in arr.iter(tx) {
             let yvalue = *convert_yvalue_to_ruby_value(val, tx).0.borrow();
             let args = (yvalue,);
             let _ = block.call::<(Value,), Qnil>(args);
-        });
+        }
 
         Ok(())
     }
 
-    pub(crate) fn yarray_get(&self, transaction: &YTransaction, index: u32) -> Value {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+    pub(crate) fn yarray_get(
+        &self,
+        transaction: &YTransaction,
+        index: u32,
+    ) -> Result<Value, Error> {
+        let tx = &transaction.read_transaction()?;
 
         let arr = self.0.borrow();
-        let v = arr.get(tx, index).unwrap();
-        *convert_yvalue_to_ruby_value(v, tx).0.borrow()
+        let v = arr.get(tx, index).ok_or_else(|| {
+            index_error(format!(
+                "index {} out of bounds for length {}",
+                index,
+                arr.len(tx)
+            ))
+        })?;
+        Ok(*convert_yvalue_to_ruby_value(v, tx).0.borrow())
     }
-    pub(crate) fn yarray_insert(&self, transaction: &YTransaction, index: u32, value: Value) {
-        let yvalue = YValue::from(value);
-        let avalue = Any::from(yvalue);
-
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    pub(crate) fn yarray_insert(
+        &self,
+        transaction: &YTransaction,
+        index: u32,
+        value: Value,
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
         let arr = self.0.borrow_mut();
-        arr.insert(tx, index, avalue);
+        check_index(index, arr.len(tx))?;
+        if let Ok(doc) = <&YDoc>::try_convert(value) {
+            arr.insert(tx, index, doc.subdoc()?);
+        } else {
+            let yvalue = YValue::from(value);
+            let avalue = Any::try_from(yvalue)?;
+            arr.insert(tx, index, avalue);
+        }
+
+        Ok(())
     }
     pub(crate) fn yarray_insert_range(
         &self,
         transaction: &YTransaction,
         index: u32,
         values: RArray,
-    ) {
+    ) -> Result<(), Error> {
         let arr = self.0.borrow_mut();
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
-        let add_values: Vec<Any> = values
-            .into_iter()
-            .map(|value| YValue::from(value).into())
-            .collect();
+        check_index(index, arr.len(tx))?;
+        // yrs only inserts ranges of plain values, subdocuments are inserted
+        // one by one in between. All values are converted first, so that the
+        // array is not modified if any of them can not be stored.
+        let mut runs: Vec<(Vec<Any>, Option<Doc>)> = vec![(Vec::new(), None)];
+        for value in values.into_iter() {
+            if let Ok(doc) = <&YDoc>::try_convert(value) {
+                runs.push((Vec::new(), Some(doc.subdoc()?)));
+            } else if let Some((run, _)) = runs.last_mut() {
+                run.push(Any::try_from(YValue::from(value))?);
+            }
+        }
 
-        arr.insert_range(tx, index, add_values)
+        let mut index = index;
+        for (run, doc) in runs {
+            if let Some(doc) = doc {
+                arr.insert(tx, index, doc);
+                index += 1;
+            }
+            let len = run.len() as u32;
+            if len > 0 {
+                arr.insert_range(tx, index, run);
+                index += len;
+            }
+        }
+
+        Ok(())
     }
-    pub(crate) fn yarray_length(&self, transaction: &YTransaction) -> u32 {
+    pub(crate) fn yarray_length(&self, transaction: &YTransaction) -> Result<u32, Error> {
         let arr = self.0.borrow();
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
-        arr.len(tx)
+        Ok(arr.len(tx))
     }
     pub(crate) fn yarray_observe(&self, block: Proc) -> Result<u32, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
@@ -79,111 +123,156 @@ impl YArray {
 
         // let mut error: Option<Error> = None;
 
-        let subscription_id =
-            self.0
-                .borrow_mut()
-                .observe(move |transaction, array_event| {
-                    let ruby = unsafe { Ruby::get_unchecked() };
-                    let delta = array_event.delta(transaction);
-                    // let mut changes = RArray::with_capacity(delta.len());
-                    let (changes, errors): (Vec<_>, Vec<_>) = delta
-                        .iter()
-                        .map(|change| {
-                            let payload = ruby.hash_new();
-                            let result =
-                                match change {
-                                    Change::Added(v) => {
-                                        let values = ruby.ary_new();
-                                        for val in v.iter() {
-                                            let value: Value = YValue::from(val.clone()).into();
-                                            values.push(value).expect("cannot push value to array");
-                                        }
-                                        payload.aset(change_added, values)
-                                    }
-                                    Change::Retain(position) => payload
-                                        .aset(change_retain, (*position).into_value_with(&ruby)),
-                                    Change::Removed(position) => payload
-                                        .aset(change_removed, (*position).into_value_with(&ruby)),
-                                };
-
-                            match result {
-                                Ok(()) => Ok(payload),
-                                Err(e) => Err(e),
+        let subscription_id = self
+            .0
+            .borrow_mut()
+            .observe(move |transaction, array_event| {
+                let ruby = unsafe { Ruby::get_unchecked() };
+                let delta = array_event.delta(transaction);
+                // let mut changes = RArray::with_capacity(delta.len());
+                let (changes, errors): (Vec<_>, Vec<_>) = delta
+                    .iter()
+                    .map(|change| {
+                        let payload = ruby.hash_new();
+                        let result = match change {
+                            Change::Added(v) => {
+                                let values = ruby.ary_new();
+                                for val in v.iter() {
+                                    let value: Value =
+                                        convert_yvalue_to_ruby_value(val.clone(), transaction)
+                                            .into();
+                                    values.push(value).expect("cannot push value to array");
+                                }
+                                payload.aset(change_added, values)
                             }
-                        })
-                        .partition(Result::is_ok);
-
-                    if errors.is_empty() {
-                        let args_changes = ruby.ary_new();
-                        for change in changes.iter() {
-                            let c = *change.as_ref().unwrap();
-                            args_changes
-                                .push(c)
-                                .expect("cannot push change event to args");
+                            Change::Retain(position) => {
+                                payload.aset(change_retain, (*position).into_value_with(&ruby))
+                            }
+                            Change::Removed(position) => {
+                                payload.aset(change_removed, (*position).into_value_with(&ruby))
+                            }
+                        };
+
+                        match result {
+                            Ok(()) => Ok(payload),
+                            Err(e) => Err(e),
                         }
+                    })
+                    .partition(Result::is_ok);
 
-                        let args = (args_changes,);
-                        let _ = block.call::<(RArray,), Qnil>(args);
-                        // todo: make sure we respect the result and bubble up the
-                        //  error so that we can return as part of the Result
+                if errors.is_empty() {
+                    let args_changes = ruby.ary_new();
+                    for change in changes.iter() {
+                        let c = *change.as_ref().unwrap();
+                        args_changes
+                            .push(c)
+                            .expect("cannot push change event to args");
                     }
 
-                    // todo: make sure we respect errors and let the method fail by
-                    //  by returning a Result containing an Error
-                })
-                .into();
+                    keep_observer_error(call_observer(block, args_changes, transaction));
+                } else if let Some(error) = errors.into_iter().next() {
+                    keep_observer_error(error);
+                }
+            })
+            .into();
 
         Ok(subscription_id)
     }
-    pub(crate) fn yarray_push_back(&self, transaction: &YTransaction, value: Value) {
-        let yvalue = YValue::from(value);
-        let avalue = Any::from(yvalue);
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
-
-        self.0.borrow_mut().push_back(tx, avalue);
+    pub(crate) fn yarray_observe_deep(&self, block: Proc, document: Value) -> u32 {
+        observe_deep(&mut *self.0.borrow_mut(), block, document)
     }
-    pub(crate) fn yarray_push_front(&self, transaction: &YTransaction, value: Value) {
-        let yvalue = YValue::from(value);
-        let avalue = Any::from(yvalue);
+    pub(crate) fn yarray_push_back(
+        &self,
+        transaction: &YTransaction,
+        value: Value,
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+        if let Ok(doc) = <&YDoc>::try_convert(value) {
+            self.0.borrow_mut().push_back(tx, doc.subdoc()?);
+        } else {
+            let yvalue = YValue::from(value);
+            let avalue = Any::try_from(yvalue)?;
+            self.0.borrow_mut().push_back(tx, avalue);
+        }
+
+        Ok(())
+    }
+    pub(crate) fn yarray_push_front(
+        &self,
+        transaction: &YTransaction,
+        value: Value,
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
         let arr = self.0.borrow_mut();
-        arr.push_front(tx, avalue);
+        if let Ok(doc) = <&YDoc>::try_convert(value) {
+            arr.push_front(tx, doc.subdoc()?);
+        } else {
+            let yvalue = YValue::from(value);
+            let avalue = Any::try_from(yvalue)?;
+            arr.push_front(tx, avalue);
+        }
+
+        Ok(())
     }
-    pub(crate) fn yarray_remove(&self, transaction: &YTransaction, index: u32) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    pub(crate) fn yarray_remove(
+        &self,
+        transaction: &YTransaction,
+        index: u32,
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
         let arr = self.0.borrow_mut();
-        arr.remove(tx, index)
+        check_range(index, 1, arr.len(tx))?;
+        arr.remove(tx, index);
+
+        Ok(())
     }
-    pub(crate) fn yarray_remove_range(&self, transaction: &YTransaction, index: u32, len: u32) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    pub(crate) fn yarray_remove_range(
+        &self,
+        transaction: &YTransaction,
+        index: u32,
+        len: u32,
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
         let arr = self.0.borrow_mut();
-        arr.remove_range(tx, index, len)
+        check_range(index, len, arr.len(tx))?;
+        arr.remove_range(tx, index, len);
+
+        Ok(())
     }
-    pub(crate) fn yarray_to_a(&self, transaction: &YTransaction) -> RArray {
+    pub(crate) fn yarray_sticky_index(
+        &self,
+        transaction: &YTransaction,
+        index: u32,
+        assoc: Symbol,
+    ) -> Result<Option<YStickyIndex>, Error> {
+        sticky_index_at(&*self.0.borrow(), transaction, index, assoc)
+    }
+    pub(crate) fn yarray_to_a(&self, transaction: &YTransaction) -> Result<RArray, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
         let arr = self.0.borrow();
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
         let r_arr = ruby.ary_new();
         for item in arr.iter(tx) {
-            let r_val = YValue::from(item);
-            let r_val = *r_val.0.borrow();
-            r_arr.push(r_val).expect("cannot push item event to array");
+            let r_val = *convert_yvalue_to_ruby_value(item, tx).0.borrow();
+            r_arr.push(r_val)?;
         }
-        r_arr
+        Ok(r_arr)
     }
     pub(crate) fn yarray_unobserve(&self, subscription_id: u32) {
         self.0.borrow_mut().unobserve(subscription_id);
     }
+    pub(crate) fn yarray_unobserve_deep(&self, subscription_id: u32) {
+        self.0.borrow_mut().unobserve_deep(subscription_id);
+    }
 }
 
 impl From<ArrayRef> for YArray {
@@ -1,6 +1,6 @@
 use crate::yvalue::YValue;
 use magnus::r_hash::ForEach::Continue;
-use magnus::{RHash, Value};
+use magnus::{Error, RHash, Value};
 use std::cell::RefCell;
 use std::sync::Arc;
 use yrs::types::Attrs;
@@ -19,21 +19,21 @@ impl From<Attrs> for YAttrs {
     }
 }
 
-impl From<RHash> for YAttrs {
-    fn from(value: RHash) -> Self {
+impl TryFrom<RHash> for YAttrs {
+    type Error = Error;
+
+    fn try_from(value: RHash) -> Result<Self, Self::Error> {
         let mut attrs = Attrs::new();
 
-        value
-            .foreach(|key: Value, value: Value| {
-                let k = key.to_string();
-                let yvalue = YValue::from(value);
-                let avalue = Any::from(yvalue);
-                attrs.insert(Arc::from(k), avalue);
+        value.foreach(|key: Value, value: Value| {
+            let k = key.to_string();
+            let yvalue = YValue::from(value);
+            let avalue = Any::try_from(yvalue)?;
+            attrs.insert(Arc::from(k), avalue);
 
-                Ok(Continue)
-            })
-            .expect("cannot iterate attributes hash");
+            Ok(Continue)
+        })?;
 
-        YAttrs(RefCell::from(attrs))
+        Ok(YAttrs(RefCell::from(attrs)))
     }
 }
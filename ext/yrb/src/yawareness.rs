@@ -1,12 +1,15 @@
-use magnus::{block::Proc, Error, Ruby, Value};
-use std::borrow::Borrow;
+use crate::utils::EncodedBytes;
+use crate::ydoc::YDoc;
+use crate::yerror::{decode_error, error};
+use crate::yorigin::{keep_observer_error, raise_observer_error};
+use magnus::{block::Proc, Error, RHash, RString, Ruby, Value};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use y_sync::awareness::{Awareness, AwarenessUpdate, Event, UpdateSubscription};
 use yrs::block::ClientID;
-use yrs::updates::decoder::Decode;
+use yrs::encoding::read::Read;
+use yrs::updates::decoder::{Decode, DecoderV1};
 use yrs::updates::encoder::Encode;
-use yrs::{Doc, OffsetKind, Options};
 
 #[magnus::wrap(class = "Y::Awareness")]
 pub(crate) struct YAwareness(pub(crate) RefCell<Awareness>);
@@ -15,36 +18,29 @@ pub(crate) struct YAwareness(pub(crate) RefCell<Awareness>);
 unsafe impl Send for YAwareness {}
 
 impl YAwareness {
-    pub(crate) fn yawareness_new() -> Self {
-        let mut options = Options {
-            offset_kind: OffsetKind::Utf16,
-            ..Default::default()
-        };
-        options.offset_kind = OffsetKind::Utf16;
-
-        let doc = Doc::with_options(options);
+    // Documents are reference counted, so the awareness shares the document
+    // (and its client id) with the given Y::Doc.
+    pub(crate) fn yawareness_new(doc: &YDoc) -> Self {
+        let awareness = Awareness::new(doc.0.borrow().clone());
 
-        let awareness = Awareness::new(doc);
-
-        Self(RefCell::new(awareness))
+        Self::from(awareness)
     }
 
-    pub(crate) fn yawareness_apply_update(&self, update: Vec<u8>) -> Result<(), Error> {
-        let ruby = Ruby::get().unwrap();
-        AwarenessUpdate::decode_v1(update.as_slice())
-            .map_err(|_error| Error::new(ruby.exception_runtime_error(), "cannot decode update"))
+    pub(crate) fn yawareness_apply_update(&self, update: EncodedBytes) -> Result<(), Error> {
+        AwarenessUpdate::decode_v1(update.0.as_slice())
+            .map_err(|error| decode_error("cannot decode awareness update", error))
             .and_then(|value| {
-                self.0.borrow_mut().apply_update(value).map_err(|_error| {
-                    Error::new(
-                        ruby.exception_runtime_error(),
-                        "cannot apply awareness update",
-                    )
-                })
-            })
+                self.0
+                    .borrow_mut()
+                    .apply_update(value)
+                    .map_err(|err| error(format!("cannot apply awareness update: {}", err)))
+            })?;
+        raise_observer_error()
     }
 
-    pub(crate) fn yawareness_clean_local_state(&self) {
+    pub(crate) fn yawareness_clean_local_state(&self) -> Result<(), Error> {
         self.0.borrow_mut().clean_local_state();
+        raise_observer_error()
     }
 
     pub(crate) fn yawareness_client_id(&self) -> ClientID {
@@ -59,13 +55,23 @@ impl YAwareness {
         self.0.borrow().local_state().map(|value| value.to_string())
     }
 
+    pub(crate) fn yawareness_meta(&self, client_id: ClientID) -> Result<Option<RHash>, Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        let clock = match client_clock(&self.0.borrow(), client_id) {
+            Some(clock) => clock,
+            None => return Ok(None),
+        };
+
+        let meta = ruby.hash_new();
+        meta.aset(ruby.to_symbol("clock"), clock)?;
+        Ok(Some(meta))
+    }
+
     pub(crate) fn yawareness_on_update(&self, block: Proc) -> YAwarenessSubscription {
-        let subscription = self.0.borrow_mut().on_update(move |_awareness, event| {
-            let awareness_event = YAwarenessEvent::from(event);
+        let subscription = self.0.borrow_mut().on_update(move |awareness, event| {
+            let awareness_event = YAwarenessEvent::new(awareness, event);
             let args = (awareness_event,);
-            block
-                .call::<(YAwarenessEvent,), Value>(args)
-                .expect("cannot call block: on_update");
+            keep_observer_error(block.call::<(YAwarenessEvent,), Value>(args));
         });
 
         // we need to make sure the event handler "survives" and is not being
@@ -73,42 +79,43 @@ impl YAwareness {
         YAwarenessSubscription::from(subscription)
     }
 
-    pub(crate) fn yawareness_remove_state(&self, client_id: ClientID) {
-        self.0.borrow_mut().remove_state(client_id)
+    pub(crate) fn yawareness_unobserve(&self, subscription: &YAwarenessSubscription) {
+        subscription.0.borrow_mut().take();
+    }
+
+    pub(crate) fn yawareness_remove_state(&self, client_id: ClientID) -> Result<(), Error> {
+        self.0.borrow_mut().remove_state(client_id);
+        raise_observer_error()
     }
 
-    pub(crate) fn yawareness_set_local_state(&self, json: String) {
-        self.0.borrow_mut().set_local_state(json)
+    pub(crate) fn yawareness_set_local_state(&self, json: String) -> Result<(), Error> {
+        self.0.borrow_mut().set_local_state(json);
+        raise_observer_error()
     }
 
-    pub(crate) fn yawareness_update(&self) -> Result<Vec<u8>, Error> {
+    pub(crate) fn yawareness_update(&self) -> Result<RString, Error> {
         let ruby = Ruby::get().unwrap();
         self.0
             .borrow_mut()
             .update()
-            .map(|update| update.encode_v1())
-            .map_err(|_error| {
-                Error::new(
-                    ruby.exception_runtime_error(),
-                    "cannot create update for current state",
-                )
-            })
+            .map(|update| ruby.str_from_slice(&update.encode_v1()))
+            .map_err(|err| error(format!("cannot create update for current state: {}", err)))
     }
 
     pub(crate) fn yawareness_update_with_clients(
         &self,
         clients: Vec<ClientID>,
-    ) -> Result<Vec<u8>, Error> {
+    ) -> Result<RString, Error> {
         let ruby = Ruby::get().unwrap();
         self.0
             .borrow_mut()
             .update_with_clients(clients)
-            .map(|update| update.encode_v1())
-            .map_err(|_error| {
-                Error::new(
-                    ruby.exception_runtime_error(),
-                    "cannot create update for current state and given clients",
-                )
+            .map(|update| ruby.str_from_slice(&update.encode_v1()))
+            .map_err(|err| {
+                error(format!(
+                    "cannot create update for current state and given clients: {}",
+                    err
+                ))
             })
     }
 }
@@ -119,40 +126,65 @@ impl From<Awareness> for YAwareness {
     }
 }
 
+// y-sync keeps the clock of every client private, it is read from an update
+// of the client instead, which consists of the number of clients (1), the
+// client id, its clock and its state.
+fn client_clock(awareness: &Awareness, client_id: ClientID) -> Option<u32> {
+    let update = awareness.update_with_clients([client_id]).ok()?.encode_v1();
+    let mut decoder = DecoderV1::from(update.as_slice());
+    let _clients: u32 = decoder.read_var().ok()?;
+    let _client_id: ClientID = decoder.read_var().ok()?;
+    decoder.read_var().ok()
+}
+
 #[magnus::wrap(class = "Y::AwarenessEvent")]
-pub(crate) struct YAwarenessEvent(Event);
+pub(crate) struct YAwarenessEvent(Event, Option<Vec<u8>>);
 
 /// SAFETY: This is safe because we only access this data when the GVL is held.
 unsafe impl Send for YAwarenessEvent {}
 
 impl YAwarenessEvent {
+    // The update of the changed clients is encoded right away, as observers
+    // are called while the awareness is borrowed and can not encode it later.
+    pub(crate) fn new(awareness: &Awareness, event: &Event) -> Self {
+        let clients = [event.added(), event.updated(), event.removed()].concat();
+        let update = awareness
+            .update_with_clients(clients)
+            .map(|update| update.encode_v1())
+            .ok();
+
+        Self(event.clone(), update)
+    }
+
     pub(crate) fn added(&self) -> Vec<ClientID> {
-        self.0.borrow().added().to_vec()
+        self.0.added().to_vec()
     }
     pub(crate) fn updated(&self) -> Vec<ClientID> {
-        self.0.borrow().updated().to_vec()
+        self.0.updated().to_vec()
     }
     pub(crate) fn removed(&self) -> Vec<ClientID> {
-        self.0.borrow().removed().to_vec()
+        self.0.removed().to_vec()
+    }
+    pub(crate) fn yawareness_event_update(&self) -> Option<RString> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        self.update_bytes()
+            .map(|update| ruby.str_from_slice(update))
     }
-}
 
-impl From<&Event> for YAwarenessEvent {
-    fn from(value: &Event) -> Self {
-        Self(value.clone())
+    pub(crate) fn update_bytes(&self) -> Option<&[u8]> {
+        self.1.as_deref()
     }
 }
 
-#[allow(dead_code)]
-#[magnus::wrap(class = "Y::AwarenessEvent")]
-pub(crate) struct YAwarenessSubscription(UpdateSubscription);
+// The observer is removed when the subscription is dropped, either by
+// detaching it or once Ruby collects the subscription.
+#[magnus::wrap(class = "Y::AwarenessSubscription")]
+pub(crate) struct YAwarenessSubscription(RefCell<Option<UpdateSubscription>>);
 
 unsafe impl Send for YAwarenessSubscription {}
 
-impl YAwarenessSubscription {}
-
 impl From<UpdateSubscription> for YAwarenessSubscription {
     fn from(v: UpdateSubscription) -> Self {
-        YAwarenessSubscription(v)
+        YAwarenessSubscription(RefCell::from(Some(v)))
     }
 }
@@ -1,117 +1,736 @@
+use crate::utils::{
+    encoded_to_value, origin_from_value, remote_origin_from_value, without_gvl, EncodedBytes,
+};
 use crate::yarray::YArray;
+use crate::yerror::{decode_error, error, transaction_error, type_error};
 use crate::ymap::YMap;
+use crate::yorigin::{call_observer, keep_observer_error, raise_observer_error};
+use crate::ysnapshot::YSnapshot;
 use crate::ytext::YText;
+use crate::yupdate::{client_stats, PendingUpdates};
+use crate::yvalue::YValue;
 use crate::yxml_element::YXmlElement;
 use crate::yxml_fragment::YXmlFragment;
 use crate::yxml_text::YXmlText;
 use crate::YTransaction;
 use magnus::block::Proc;
-use magnus::{Error, Integer, RArray, Ruby, Value};
-use std::borrow::Borrow;
-use std::cell::RefCell;
+use magnus::scan_args::{get_kwargs, scan_args};
+use magnus::{Error, Integer, IntoValue, RArray, RHash, RString, Ruby, Symbol, Value};
+use std::cell::{Ref, RefCell};
+use std::rc::Rc;
+use yrs::block::Item;
+use yrs::types::text::YChange;
+use yrs::types::{BranchPtr, ToJson, Value as YrsValue};
 use yrs::updates::decoder::Decode;
-use yrs::updates::encoder::{Encoder, EncoderV2};
-use yrs::{Doc, OffsetKind, Options, ReadTxn, StateVector, SubscriptionId, Transact};
+use yrs::updates::encoder::{Encode, Encoder, EncoderV1, EncoderV2};
+use yrs::{
+    Any, Array, ArrayRef, Doc, GetString, Map, MapRef, OffsetKind, Options, ReadTxn, StateVector,
+    SubdocsEventIter, SubscriptionId, Text, TextRef, Transact, TransactionMut, XmlFragment,
+    XmlFragmentRef,
+};
 
 #[magnus::wrap(class = "Y::Doc")]
-pub(crate) struct YDoc(pub(crate) RefCell<Doc>);
+pub(crate) struct YDoc(
+    pub(crate) RefCell<Doc>,
+    pub(crate) Rc<RefCell<PendingUpdates>>,
+);
 
 unsafe impl Send for YDoc {}
 
 impl YDoc {
-    pub(crate) fn ydoc_new(client_id: &[Value]) -> Self {
+    pub(crate) fn ydoc_new(args: &[Value]) -> Result<Self, Error> {
+        let ruby = Ruby::get().unwrap();
+        let args = scan_args::<(), (Option<Value>,), (), (), RHash, ()>(args)?;
+        let (client_id,) = args.optional;
+        let kwargs = get_kwargs::<
+            _,
+            (),
+            (
+                Option<String>,
+                Option<String>,
+                Option<bool>,
+                Option<bool>,
+                Option<bool>,
+                Option<bool>,
+                Option<Symbol>,
+            ),
+            (),
+        >(
+            args.keywords,
+            &[],
+            &[
+                "guid",
+                "collection_id",
+                "gc",
+                "skip_gc",
+                "auto_load",
+                "should_load",
+                "offset_kind",
+            ],
+        )?;
+        let (guid, collection_id, gc, skip_gc, auto_load, should_load, offset_kind) =
+            kwargs.optional;
+
         let mut options = Options::default();
-        if client_id.len() == 1 {
-            let value = client_id.first().unwrap();
-            options.client_id = Integer::from_value(*value).unwrap().to_u64().unwrap();
+        if let Some(value) = client_id {
+            options.client_id = Integer::from_value(value)
+                .and_then(|id| id.to_u64().ok())
+                .ok_or_else(|| type_error("client id must be an unsigned 64-bit integer"))?;
+        }
+        if let Some(guid) = guid {
+            options.guid = guid.into();
         }
-        options.offset_kind = OffsetKind::Utf16;
+        options.collection_id = collection_id;
+        options.skip_gc = match (gc, skip_gc) {
+            (Some(gc), Some(skip_gc)) if gc == skip_gc => {
+                return Err(Error::new(
+                    ruby.exception_arg_error(),
+                    "gc and skip_gc contradict each other, provide only one of them",
+                ))
+            }
+            (Some(gc), _) => !gc,
+            (None, skip_gc) => skip_gc.unwrap_or(false),
+        };
+        options.auto_load = auto_load.unwrap_or(false);
+        options.should_load = should_load.unwrap_or(true);
+        options.offset_kind = match offset_kind {
+            Some(offset_kind) => offset_kind_from_symbol(offset_kind)?,
+            None => OffsetKind::Utf16,
+        };
 
         let doc = Doc::with_options(options);
-        Self(RefCell::new(doc))
+        Ok(Self(RefCell::new(doc), Rc::default()))
+    }
+
+    pub(crate) fn ydoc_auto_load(&self) -> bool {
+        self.0.borrow().options().auto_load
+    }
+
+    pub(crate) fn ydoc_client_id(&self) -> u64 {
+        self.0.borrow().client_id()
+    }
+
+    pub(crate) fn ydoc_collection_id(&self) -> Option<String> {
+        self.0.borrow().options().collection_id.clone()
     }
 
     pub(crate) fn ydoc_encode_diff_v1(
         &self,
         transaction: &YTransaction,
-        state_vector: Vec<u8>,
-    ) -> Result<Vec<u8>, Error> {
+        state_vector: EncodedBytes,
+    ) -> Result<RString, Error> {
         let ruby = Ruby::get().unwrap();
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+        let tx = &transaction.exclusive_transaction()?;
+        let sv = StateVector::decode_v1(state_vector.0.as_slice())
+            .map_err(|error| decode_error("cannot decode state vector", error))?;
 
-        StateVector::decode_v1(state_vector.borrow())
-            .map(|sv| tx.encode_diff_v1(&sv))
-            .map_err(|_e| Error::new(ruby.exception_runtime_error(), "cannot encode diff"))
+        let diff = without_gvl(missing_clocks(tx, &sv), || tx.encode_diff_v1(&sv));
+        Ok(ruby.str_from_slice(&diff))
     }
 
     pub(crate) fn ydoc_encode_diff_v2(
         &self,
         transaction: &YTransaction,
-        state_vector: Vec<u8>,
-    ) -> Result<Vec<u8>, Error> {
+        state_vector: EncodedBytes,
+    ) -> Result<RString, Error> {
+        let ruby = Ruby::get().unwrap();
+        let tx = &transaction.exclusive_transaction()?;
+        let sv = StateVector::decode_v2(state_vector.0.as_slice())
+            .map_err(|error| decode_error("cannot decode state vector", error))?;
+
+        let diff = without_gvl(missing_clocks(tx, &sv), || {
+            let mut encoder = EncoderV2::new();
+            tx.encode_diff(&sv, &mut encoder);
+            encoder.to_vec()
+        });
+        Ok(ruby.str_from_slice(&diff))
+    }
+
+    pub(crate) fn ydoc_encode_state_from_snapshot_v1(
+        &self,
+        transaction: &YTransaction,
+        snapshot: &YSnapshot,
+    ) -> Result<RString, Error> {
+        let ruby = Ruby::get().unwrap();
+        let tx = &transaction.exclusive_transaction()?;
+        let work = missing_clocks(tx, &StateVector::default());
+
+        without_gvl(work, || {
+            let mut encoder = EncoderV1::new();
+            tx.encode_state_from_snapshot(&snapshot.0, &mut encoder)
+                .map(|_| encoder.to_vec())
+        })
+        .map(|update| ruby.str_from_slice(&update))
+        .map_err(|e| error(format!("cannot encode state from snapshot: {}", e)))
+    }
+
+    pub(crate) fn ydoc_encode_state_from_snapshot_v2(
+        &self,
+        transaction: &YTransaction,
+        snapshot: &YSnapshot,
+    ) -> Result<RString, Error> {
         let ruby = Ruby::get().unwrap();
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
-        let mut encoder = EncoderV2::new();
+        let tx = &transaction.exclusive_transaction()?;
+        let work = missing_clocks(tx, &StateVector::default());
 
-        StateVector::decode_v2(state_vector.borrow())
-            .map(|sv| tx.encode_diff(&sv, &mut encoder))
-            .map(|_| encoder.to_vec())
-            .map_err(|_e| Error::new(ruby.exception_runtime_error(), "cannot encode diff"))
+        without_gvl(work, || {
+            let mut encoder = EncoderV2::new();
+            tx.encode_state_from_snapshot(&snapshot.0, &mut encoder)
+                .map(|_| encoder.to_vec())
+        })
+        .map(|update| ruby.str_from_slice(&update))
+        .map_err(|e| error(format!("cannot encode state from snapshot: {}", e)))
     }
 
-    pub(crate) fn ydoc_get_or_insert_array(&self, name: String) -> YArray {
-        let array_ref = self.0.borrow().get_or_insert_array(name.as_str());
-        YArray::from(array_ref)
+    pub(crate) fn ydoc_guid(&self) -> String {
+        self.0.borrow().guid().to_string()
     }
 
-    pub(crate) fn ydoc_get_or_insert_map(&self, name: String) -> YMap {
-        let map_ref = self.0.borrow().get_or_insert_map(name.as_str());
-        YMap::from(map_ref)
+    pub(crate) fn ydoc_offset_kind(&self) -> Symbol {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        match self.0.borrow().options().offset_kind {
+            OffsetKind::Bytes => ruby.to_symbol("bytes"),
+            OffsetKind::Utf16 => ruby.to_symbol("utf16"),
+        }
+    }
+
+    pub(crate) fn ydoc_should_load(&self) -> bool {
+        self.0.borrow().options().should_load
     }
 
-    pub(crate) fn ydoc_get_or_insert_text(&self, name: String) -> YText {
-        let text_ref = self.0.borrow().get_or_insert_text(name.as_str());
-        YText::from(text_ref)
+    pub(crate) fn ydoc_skip_gc(&self) -> bool {
+        self.0.borrow().options().skip_gc
     }
 
-    pub(crate) fn ydoc_get_or_insert_xml_element(&self, name: String) -> YXmlElement {
-        let xml_element_ref = self.0.borrow_mut().get_or_insert_xml_element(name.as_str());
-        YXmlElement::from(xml_element_ref) // ::into() maps to YXmlFragment instead of YXmlElement :-(
+    pub(crate) fn ydoc_get_or_insert_array(&self, name: String) -> Result<YArray, Error> {
+        let doc = self.root_access()?;
+        Ok(YArray::from(doc.get_or_insert_array(name.as_str())))
     }
 
-    pub(crate) fn ydoc_get_or_insert_xml_fragment(&self, name: String) -> YXmlFragment {
-        let xml_fragment_ref = self.0.borrow().get_or_insert_xml_fragment(name.as_str());
-        YXmlFragment::from(xml_fragment_ref)
+    pub(crate) fn ydoc_get_or_insert_map(&self, name: String) -> Result<YMap, Error> {
+        let doc = self.root_access()?;
+        Ok(YMap::from(doc.get_or_insert_map(name.as_str())))
+    }
+
+    pub(crate) fn ydoc_get_or_insert_text(&self, name: String) -> Result<YText, Error> {
+        let doc = self.root_access()?;
+        Ok(YText::from(doc.get_or_insert_text(name.as_str())))
+    }
+
+    pub(crate) fn ydoc_get_or_insert_xml_element(
+        &self,
+        name: String,
+    ) -> Result<YXmlElement, Error> {
+        let doc = self.root_access()?;
+        let xml_element_ref = doc.get_or_insert_xml_element(name.as_str());
+        Ok(YXmlElement::from(xml_element_ref)) // ::into() maps to YXmlFragment instead of YXmlElement :-(
+    }
+
+    pub(crate) fn ydoc_get_or_insert_xml_fragment(
+        &self,
+        name: String,
+    ) -> Result<YXmlFragment, Error> {
+        let doc = self.root_access()?;
+        Ok(YXmlFragment::from(
+            doc.get_or_insert_xml_fragment(name.as_str()),
+        ))
     }
 
-    pub(crate) fn ydoc_get_or_insert_xml_text(&self, name: String) -> YXmlText {
-        let xml_text_ref = self.0.borrow().get_or_insert_xml_text(name.as_str());
-        YXmlText::from(xml_text_ref)
+    pub(crate) fn ydoc_get_or_insert_xml_text(&self, name: String) -> Result<YXmlText, Error> {
+        let doc = self.root_access()?;
+        Ok(YXmlText::from(doc.get_or_insert_xml_text(name.as_str())))
     }
 
-    pub(crate) fn ydoc_transact(&self) -> YTransaction {
+    pub(crate) fn ydoc_load(&self, transaction: Option<&YTransaction>) -> Result<(), Error> {
         let doc = self.0.borrow();
-        let transaction = doc.transact_mut();
-        YTransaction::from(transaction)
+
+        match transaction {
+            Some(transaction) => {
+                let mut tx = transaction.transaction_mut()?;
+                let tx = &mut *tx;
+                doc.load(tx);
+            }
+            None => {
+                let parent = doc
+                    .parent_doc()
+                    .ok_or_else(|| error("document is not a subdocument"))?;
+                let mut tx = parent
+                    .try_transact_mut()
+                    .map_err(|err| transaction_error(err.to_string()))?;
+                doc.load(&mut tx);
+                drop(tx);
+                raise_observer_error()?;
+            }
+        }
+
+        Ok(())
     }
 
-    pub(crate) fn ydoc_observe_update(&self, block: Proc) -> Result<SubscriptionId, Error> {
-        let ruby = Ruby::get().unwrap();
+    pub(crate) fn ydoc_pending(&self, transaction: &YTransaction) -> Result<Option<RHash>, Error> {
+        self.1
+            .try_borrow()
+            .map_err(|_| transaction_error("pending updates are in use, e.g. by another thread"))?
+            .describe(&transaction.read_transaction()?)
+    }
+
+    pub(crate) fn ydoc_read_transaction(&self) -> Result<YTransaction, Error> {
+        self.0
+            .borrow()
+            .try_transact()
+            .map(YTransaction::from)
+            .map_err(|err| transaction_error(err.to_string()))
+    }
+
+    pub(crate) fn ydoc_roots(&self, transaction: &YTransaction) -> Result<RHash, Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        let tx = &transaction.read_transaction()?;
+
+        let roots = ruby.hash_new();
+        for (name, value) in tx.root_refs() {
+            roots.aset(name, ruby.to_symbol(RootKind::of(&value, tx).name()))?;
+        }
+        Ok(roots)
+    }
+
+    pub(crate) fn ydoc_stats(&self, transaction: &YTransaction) -> Result<RHash, Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        let tx = &transaction.exclusive_transaction()?;
+
+        let work = missing_clocks(tx, &StateVector::default());
+        let (state_v1, state_v2, delete_set, client_stats) = without_gvl(work, || {
+            let state_v1 = tx.encode_state_as_update_v1(&StateVector::default());
+            let state_v2 = tx.encode_state_as_update_v2(&StateVector::default());
+            let delete_set = tx.snapshot().delete_set;
+            let client_stats = client_stats(&state_v1, &delete_set);
+            (state_v1, state_v2, delete_set, client_stats)
+        });
+        let client_stats =
+            client_stats.map_err(|error| decode_error("cannot read document state", error))?;
+
+        let clients = ruby.hash_new();
+        let (mut blocks, mut deleted, mut gc) = (0, 0, 0);
+        for stats in client_stats {
+            let client = ruby.hash_new();
+            client.aset(ruby.to_symbol("clock"), stats.clock)?;
+            client.aset(ruby.to_symbol("blocks"), stats.blocks)?;
+            client.aset(ruby.to_symbol("deleted"), stats.deleted)?;
+            client.aset(ruby.to_symbol("gc"), stats.gc)?;
+            clients.aset(stats.client, client)?;
+
+            blocks += stats.blocks;
+            deleted += stats.deleted;
+            gc += stats.gc;
+        }
+
+        let delete_set_ranges = ruby.hash_new();
+        for (client, ranges) in delete_set.iter() {
+            delete_set_ranges.aset(*client, ranges.iter().count())?;
+        }
+
+        let roots = ruby.hash_new();
+        for (name, value) in tx.root_refs() {
+            roots.aset(name, root_len(&value, tx))?;
+        }
+
+        // every block takes a slot in the block list of its client, items are
+        // allocated separately and own their content, which is approximated by
+        // the size of the encoded state
+        let heap_bytes = blocks * 2 * std::mem::size_of::<usize>()
+            + (blocks - gc) * std::mem::size_of::<Item>()
+            + state_v1.len();
+
+        let stats = ruby.hash_new();
+        stats.aset(ruby.to_symbol("clients"), clients)?;
+        stats.aset(ruby.to_symbol("blocks"), blocks)?;
+        stats.aset(ruby.to_symbol("deleted"), deleted)?;
+        stats.aset(ruby.to_symbol("gc"), gc)?;
+        stats.aset(ruby.to_symbol("delete_set"), delete_set_ranges)?;
+        stats.aset(ruby.to_symbol("roots"), roots)?;
+        stats.aset(ruby.to_symbol("size_v1"), state_v1.len())?;
+        stats.aset(ruby.to_symbol("size_v2"), state_v2.len())?;
+        stats.aset(ruby.to_symbol("heap_bytes"), heap_bytes)?;
+        Ok(stats)
+    }
+
+    pub(crate) fn ydoc_subdocs(&self, transaction: &YTransaction) -> Result<RArray, Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        let tx = &transaction.read_transaction()?;
+
+        let subdocs = ruby.ary_new();
+        for doc in tx.subdocs() {
+            subdocs
+                .push(YDoc::from(doc.clone()))
+                .expect("cannot push subdocument to array");
+        }
+        Ok(subdocs)
+    }
+
+    pub(crate) fn ydoc_to_h(&self, transaction: &YTransaction) -> Result<RHash, Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        let tx = &transaction.read_transaction()?;
+
+        let hash = ruby.hash_new();
+        for (name, value) in tx.root_refs() {
+            let json: Value = YValue::from(root_to_json(&value, tx)).into();
+            hash.aset(name, json)?;
+        }
+        Ok(hash)
+    }
+
+    pub(crate) fn ydoc_transact(
+        &self,
+        origin: Option<Value>,
+        local: bool,
+    ) -> Result<YTransaction, Error> {
+        let doc = self.0.borrow();
+        let transaction = match (origin, local) {
+            (Some(origin), true) => doc.try_transact_mut_with(origin_from_value(origin)?),
+            (None, true) => doc.try_transact_mut(),
+            (origin, false) => doc.try_transact_mut_with(remote_origin_from_value(origin)?),
+        };
+        transaction
+            .map(|transaction| YTransaction::from(transaction).with_pending(self.1.clone()))
+            .map_err(|err| transaction_error(err.to_string()))
+    }
+
+    pub(crate) fn ydoc_observe_after_transaction(
+        &self,
+        block: Proc,
+        binary: bool,
+    ) -> Result<SubscriptionId, Error> {
+        self.0
+            .borrow()
+            .observe_after_transaction(move |transaction| {
+                if is_empty_transaction(transaction) {
+                    return;
+                }
+                keep_observer_error(
+                    transaction_to_hash(transaction, binary)
+                        .and_then(|payload| call_observer(block, payload, transaction)),
+                );
+            })
+            .map(|v| v.into())
+            .map_err(|err| transaction_error(err.to_string()))
+    }
+
+    pub(crate) fn ydoc_observe_transaction_cleanup(
+        &self,
+        block: Proc,
+        binary: bool,
+    ) -> Result<SubscriptionId, Error> {
+        self.0
+            .borrow()
+            .observe_transaction_cleanup(move |transaction, _event| {
+                if is_empty_transaction(transaction) {
+                    return;
+                }
+                keep_observer_error(
+                    transaction_to_hash(transaction, binary)
+                        .and_then(|payload| call_observer(block, payload, transaction)),
+                );
+            })
+            .map(|v| v.into())
+            .map_err(|err| transaction_error(err.to_string()))
+    }
+
+    pub(crate) fn ydoc_observe_update(
+        &self,
+        block: Proc,
+        binary: bool,
+    ) -> Result<SubscriptionId, Error> {
         self.0
             .borrow()
-            .observe_update_v1(move |_tx, update_event| {
+            .observe_update_v1(move |transaction, update_event| {
+                let update = encoded_to_value(&update_event.update, binary);
+
+                keep_observer_error(call_observer(block, update, transaction));
+            })
+            .map(|v| v.into())
+            .map_err(|err| transaction_error(err.to_string()))
+    }
+
+    pub(crate) fn ydoc_observe_update_v2(
+        &self,
+        block: Proc,
+        binary: bool,
+    ) -> Result<SubscriptionId, Error> {
+        self.0
+            .borrow()
+            .observe_update_v2(move |transaction, update_event| {
+                let update = encoded_to_value(&update_event.update, binary);
+
+                keep_observer_error(call_observer(block, update, transaction));
+            })
+            .map(|v| v.into())
+            .map_err(|err| transaction_error(err.to_string()))
+    }
+
+    pub(crate) fn ydoc_observe_subdocs(&self, block: Proc) -> Result<SubscriptionId, Error> {
+        self.0
+            .borrow()
+            .observe_subdocs(move |_tx, subdocs_event| {
                 let ruby = unsafe { Ruby::get_unchecked() };
-                let update = update_event.update.to_vec();
-                let update = ruby.ary_from_vec(update);
+                let payload = ruby.hash_new();
+                payload
+                    .aset(
+                        ruby.to_symbol("added"),
+                        subdocs_to_array(subdocs_event.added()),
+                    )
+                    .expect("cannot add subdocs event added");
+                payload
+                    .aset(
+                        ruby.to_symbol("removed"),
+                        subdocs_to_array(subdocs_event.removed()),
+                    )
+                    .expect("cannot add subdocs event removed");
+                payload
+                    .aset(
+                        ruby.to_symbol("loaded"),
+                        subdocs_to_array(subdocs_event.loaded()),
+                    )
+                    .expect("cannot add subdocs event loaded");
 
-                let args: (RArray,) = (update,);
-                block
-                    .call::<(RArray,), Value>(args)
-                    .expect("cannot call update block");
+                keep_observer_error(block.call::<(RHash,), Value>((payload,)));
             })
             .map(|v| v.into())
-            .map_err(|err| Error::new(ruby.exception_runtime_error(), err.to_string()))
+            .map_err(|err| transaction_error(err.to_string()))
+    }
+
+    pub(crate) fn ydoc_unobserve_after_transaction(&self, subscription_id: SubscriptionId) {
+        self.0.borrow().unobserve_after_transaction(subscription_id)
+    }
+
+    pub(crate) fn ydoc_unobserve_subdocs(&self, subscription_id: SubscriptionId) {
+        self.0.borrow().unobserve_subdocs(subscription_id)
+    }
+
+    pub(crate) fn ydoc_unobserve_transaction_cleanup(&self, subscription_id: SubscriptionId) {
+        self.0
+            .borrow()
+            .unobserve_transaction_cleanup(subscription_id)
+    }
+
+    pub(crate) fn ydoc_unobserve_update(&self, subscription_id: SubscriptionId) {
+        self.0.borrow().unobserve_update_v1(subscription_id)
+    }
+
+    pub(crate) fn ydoc_unobserve_update_v2(&self, subscription_id: SubscriptionId) {
+        self.0.borrow().unobserve_update_v2(subscription_id)
+    }
+
+    // a document can only be integrated as a subdocument once, yrs panics
+    // when we try to insert it a second time
+    pub(crate) fn subdoc(&self) -> Result<Doc, Error> {
+        let doc = self.0.borrow();
+        if doc.parent_doc().is_some() {
+            return Err(error("document is already used as a subdocument"));
+        }
+        Ok(doc.clone())
+    }
+
+    // yrs panics when a root type is requested while the store of the document
+    // is borrowed, e.g. by a transaction another thread applies an update in.
+    // The probing transaction has no changes and is committed right away.
+    fn root_access(&self) -> Result<Ref<'_, Doc>, Error> {
+        let doc = self.0.borrow();
+        doc.try_transact_mut().map(drop).map_err(|_| {
+            transaction_error("cannot get a root type while the document is in a transaction")
+        })?;
+        Ok(doc)
+    }
+}
+
+impl From<Doc> for YDoc {
+    fn from(doc: Doc) -> Self {
+        YDoc(RefCell::new(doc), Rc::default())
+    }
+}
+
+// yrs does not support codepoint (UTF-32) offsets, :utf32 is rejected
+// explicitly instead of silently falling back to another offset kind
+fn offset_kind_from_symbol(offset_kind: Symbol) -> Result<OffsetKind, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    match offset_kind.name()?.as_ref() {
+        "bytes" => Ok(OffsetKind::Bytes),
+        "utf16" => Ok(OffsetKind::Utf16),
+        "utf32" => Err(Error::new(
+            ruby.exception_arg_error(),
+            "offset kind :utf32 is not supported, use :bytes or :utf16",
+        )),
+        other => Err(Error::new(
+            ruby.exception_arg_error(),
+            format!(
+                "unknown offset kind: {}, must be one of :bytes or :utf16",
+                other
+            ),
+        )),
     }
 }
+
+fn subdocs_to_array(subdocs: SubdocsEventIter) -> RArray {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let array = ruby.ary_new_capa(subdocs.len());
+    for doc in subdocs {
+        array
+            .push(YDoc::from(doc.clone()).into_value_with(&ruby))
+            .expect("cannot push subdocument to array");
+    }
+    array
+}
+
+fn branch_ptr(value: &YrsValue) -> Option<BranchPtr> {
+    match value {
+        YrsValue::YText(v) => Some(BranchPtr::from(v.as_ref())),
+        YrsValue::YArray(v) => Some(BranchPtr::from(v.as_ref())),
+        YrsValue::YMap(v) => Some(BranchPtr::from(v.as_ref())),
+        YrsValue::YXmlElement(v) => Some(BranchPtr::from(v.as_ref())),
+        YrsValue::YXmlFragment(v) => Some(BranchPtr::from(v.as_ref())),
+        YrsValue::YXmlText(v) => Some(BranchPtr::from(v.as_ref())),
+        _ => None,
+    }
+}
+
+// the work to encode a diff grows with the number of clock units (roughly
+// characters or elements) the other peer is missing
+fn missing_clocks<T: ReadTxn>(tx: &T, remote: &StateVector) -> usize {
+    tx.state_vector()
+        .iter()
+        .map(|(client, clock)| clock.saturating_sub(remote.get(client)) as usize)
+        .sum()
+}
+
+// number of entries in maps, otherwise the length of the sequence
+fn root_len<T: ReadTxn>(value: &YrsValue, tx: &T) -> u32 {
+    match value {
+        YrsValue::YArray(array) => array.len(tx),
+        YrsValue::YMap(map) => map.len(tx),
+        YrsValue::YText(text) => text.len(tx),
+        YrsValue::YXmlElement(element) => element.len(tx),
+        YrsValue::YXmlFragment(fragment) => fragment.len(tx),
+        YrsValue::YXmlText(text) => text.len(tx),
+        YrsValue::UndefinedRef(branch) => match RootKind::infer(*branch, tx) {
+            RootKind::Map => MapRef::from(*branch).len(tx),
+            _ => branch.content_len(),
+        },
+        _ => 0,
+    }
+}
+
+pub(crate) enum RootKind {
+    Array,
+    Map,
+    Text,
+    XmlElement,
+    XmlFragment,
+    XmlText,
+    Undefined,
+}
+
+impl RootKind {
+    fn of<T: ReadTxn>(value: &YrsValue, tx: &T) -> Self {
+        match value {
+            YrsValue::YArray(_) => RootKind::Array,
+            YrsValue::YMap(_) => RootKind::Map,
+            YrsValue::YText(_) => RootKind::Text,
+            YrsValue::YXmlElement(_) => RootKind::XmlElement,
+            YrsValue::YXmlFragment(_) => RootKind::XmlFragment,
+            YrsValue::YXmlText(_) => RootKind::XmlText,
+            YrsValue::UndefinedRef(branch) => RootKind::infer(*branch, tx),
+            _ => RootKind::Undefined,
+        }
+    }
+
+    // root types loaded from an update stay undefined until they are fetched
+    // with one of the typed getters, so we guess their kind from the content
+    pub(crate) fn infer<T: ReadTxn>(branch: BranchPtr, tx: &T) -> Self {
+        if MapRef::from(branch).len(tx) > 0 {
+            return RootKind::Map;
+        }
+        if branch.content_len() == 0 {
+            return RootKind::Undefined;
+        }
+
+        let diff = TextRef::from(branch).diff(tx, YChange::identity);
+        if diff
+            .iter()
+            .any(|d| matches!(d.insert, YrsValue::Any(Any::String(_))))
+        {
+            RootKind::Text
+        } else if diff
+            .iter()
+            .any(|d| matches!(d.insert, YrsValue::YXmlElement(_) | YrsValue::YXmlText(_)))
+        {
+            RootKind::XmlFragment
+        } else {
+            RootKind::Array
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            RootKind::Array => "array",
+            RootKind::Map => "map",
+            RootKind::Text => "text",
+            RootKind::XmlElement => "xml_element",
+            RootKind::XmlFragment => "xml_fragment",
+            RootKind::XmlText => "xml_text",
+            RootKind::Undefined => "undefined",
+        }
+    }
+}
+
+fn root_to_json<T: ReadTxn>(value: &YrsValue, tx: &T) -> Any {
+    match value {
+        YrsValue::UndefinedRef(branch) => match RootKind::infer(*branch, tx) {
+            RootKind::Map => MapRef::from(*branch).to_json(tx),
+            RootKind::Text => Any::from(TextRef::from(*branch).get_string(tx)),
+            RootKind::XmlFragment => Any::from(XmlFragmentRef::from(*branch).get_string(tx)),
+            RootKind::Array => ArrayRef::from(*branch).to_json(tx),
+            _ => Any::Null,
+        },
+        value => value.to_json(tx),
+    }
+}
+
+// Reading a document opens (and commits) a transaction as well, transaction
+// listeners are only called for transactions that changed something
+fn is_empty_transaction(transaction: &TransactionMut) -> bool {
+    transaction.before_state() == transaction.after_state() && transaction.delete_set().is_empty()
+}
+
+// changed parent types include nested types and all of their ancestors, we
+// only report the names of the root types
+fn transaction_to_hash(transaction: &TransactionMut, binary: bool) -> Result<RHash, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+
+    let delete_set = ruby.hash_new();
+    for (client, ranges) in transaction.delete_set().iter() {
+        let clock_ranges = ruby.ary_new();
+        for range in ranges.iter() {
+            clock_ranges.push(ruby.range_new(range.start, range.end, true)?)?;
+        }
+        delete_set.aset(*client, clock_ranges)?;
+    }
+
+    let changed_parent_types = transaction.changed_parent_types();
+    let changed = ruby.ary_new();
+    for (name, value) in transaction.root_refs() {
+        if branch_ptr(&value).is_some_and(|ptr| changed_parent_types.contains(&ptr)) {
+            changed.push(name)?;
+        }
+    }
+
+    let payload = ruby.hash_new();
+    payload.aset(
+        ruby.to_symbol("before_state"),
+        encoded_to_value(&transaction.before_state().encode_v1(), binary),
+    )?;
+    payload.aset(
+        ruby.to_symbol("after_state"),
+        encoded_to_value(&transaction.after_state().encode_v1(), binary),
+    )?;
+    payload.aset(ruby.to_symbol("delete_set"), delete_set)?;
+    payload.aset(ruby.to_symbol("changed"), changed)?;
+
+    Ok(payload)
+}
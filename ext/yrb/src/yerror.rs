@@ -0,0 +1,89 @@
+use magnus::value::Lazy;
+use magnus::{Error, ExceptionClass, Module, Ruby};
+use std::borrow::Cow;
+use std::fmt::Display;
+
+// The exception classes are defined when the extension is loaded, and
+// reopened (with the same superclass) by lib/y/error.rb to document them.
+static ERROR: Lazy<ExceptionClass> = Lazy::new(|ruby| {
+    ruby.define_module("Y")
+        .and_then(|module| module.define_error("Error", ruby.exception_standard_error()))
+        .expect("cannot define class: Y::Error")
+});
+
+static DECODE_ERROR: Lazy<ExceptionClass> = Lazy::new(|ruby| define_error(ruby, "DecodeError"));
+
+static INDEX_ERROR: Lazy<ExceptionClass> = Lazy::new(|ruby| define_error(ruby, "IndexError"));
+
+static TRANSACTION_ERROR: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "TransactionError"));
+
+static TYPE_ERROR: Lazy<ExceptionClass> = Lazy::new(|ruby| define_error(ruby, "TypeError"));
+
+fn define_error(ruby: &Ruby, name: &str) -> ExceptionClass {
+    ruby.define_module("Y")
+        .and_then(|module| module.define_error(name, ruby.get_inner(&ERROR)))
+        .unwrap_or_else(|_| panic!("cannot define class: Y::{}", name))
+}
+
+pub(crate) fn define_errors(ruby: &Ruby) {
+    Lazy::force(&ERROR, ruby);
+    Lazy::force(&DECODE_ERROR, ruby);
+    Lazy::force(&INDEX_ERROR, ruby);
+    Lazy::force(&TRANSACTION_ERROR, ruby);
+    Lazy::force(&TYPE_ERROR, ruby);
+}
+
+// Y::Error, for failures that do not fit any of the more specific classes
+pub(crate) fn error(message: impl Into<Cow<'static, str>>) -> Error {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    Error::new(ruby.get_inner(&ERROR), message)
+}
+
+// Y::DecodeError, carries the details of the error reported by yrs
+pub(crate) fn decode_error(message: &str, error: impl Display) -> Error {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    Error::new(
+        ruby.get_inner(&DECODE_ERROR),
+        format!("{}: {}", message, error),
+    )
+}
+
+pub(crate) fn index_error(message: impl Into<Cow<'static, str>>) -> Error {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    Error::new(ruby.get_inner(&INDEX_ERROR), message)
+}
+
+pub(crate) fn transaction_error(message: impl Into<Cow<'static, str>>) -> Error {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    Error::new(ruby.get_inner(&TRANSACTION_ERROR), message)
+}
+
+pub(crate) fn type_error(message: impl Into<Cow<'static, str>>) -> Error {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    Error::new(ruby.get_inner(&TYPE_ERROR), message)
+}
+
+// yrs panics when inserting at an index beyond the end of a sequence
+pub(crate) fn check_index(index: u32, len: u32) -> Result<(), Error> {
+    if index > len {
+        return Err(index_error(format!(
+            "index {} out of bounds for length {}",
+            index, len
+        )));
+    }
+    Ok(())
+}
+
+// yrs panics when removing a range that exceeds the end of a sequence
+pub(crate) fn check_range(index: u32, length: u32, len: u32) -> Result<(), Error> {
+    if u64::from(index) + u64::from(length) > u64::from(len) {
+        return Err(index_error(format!(
+            "range {}...{} out of bounds for length {}",
+            index,
+            u64::from(index) + u64::from(length),
+            len
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use crate::utils::convert_yvalue_to_ruby_value;
+use crate::yarray::YArray;
+use crate::ymap::YMap;
+use crate::yorigin::{call_observer, keep_observer_error};
+use crate::ytext::YText;
+use crate::yvalue::YValue;
+use crate::yxml_element::YXmlElement;
+use crate::yxml_fragment::YXmlFragment;
+use crate::yxml_text::YXmlText;
+use magnus::block::Proc;
+use magnus::value::ReprValue;
+use magnus::{Error, IntoValue, RArray, Ruby, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use yrs::types::{Change, Delta, EntryChange, Event, Events, Path, PathSegment};
+use yrs::{DeepObservable, TransactionMut, XmlNode};
+
+// Deep observers receive all events of a transaction at once, each event
+// describes the changes of a single (nested) shared type. Event targets are
+// created natively and get associated with the document of the observed type.
+pub(crate) fn observe_deep<T: DeepObservable>(shared: &mut T, block: Proc, document: Value) -> u32 {
+    shared
+        .observe_deep(move |transaction, events| {
+            keep_observer_error(
+                events_to_array(transaction, events, document)
+                    .and_then(|payload| call_observer(block, payload, transaction)),
+            );
+        })
+        .into()
+}
+
+fn events_to_array(
+    transaction: &TransactionMut,
+    events: &Events,
+    document: Value,
+) -> Result<RArray, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let target = ruby.to_symbol("target");
+    let path = ruby.to_symbol("path");
+    let delta = ruby.to_symbol("delta");
+    let keys = ruby.to_symbol("keys");
+
+    let array = ruby.ary_new();
+    for event in events.iter() {
+        let payload = ruby.hash_new();
+        payload.aset(path, path_to_array(event.path())?)?;
+
+        let value = match event {
+            Event::Text(e) => {
+                payload.aset(
+                    delta,
+                    text_delta_to_array(transaction, e.delta(transaction))?,
+                )?;
+                YText::from(e.target().clone()).into_value_with(&ruby)
+            }
+            Event::Array(e) => {
+                payload.aset(delta, changes_to_array(transaction, e.delta(transaction))?)?;
+                YArray::from(e.target().clone()).into_value_with(&ruby)
+            }
+            Event::Map(e) => {
+                payload.aset(keys, keys_to_array(transaction, e.keys(transaction))?)?;
+                YMap::from(e.target().clone()).into_value_with(&ruby)
+            }
+            Event::XmlFragment(e) => {
+                payload.aset(delta, changes_to_array(transaction, e.delta(transaction))?)?;
+                payload.aset(keys, keys_to_array(transaction, e.keys(transaction))?)?;
+                xml_node_to_value(e.target().clone())
+            }
+            Event::XmlText(e) => {
+                payload.aset(
+                    delta,
+                    text_delta_to_array(transaction, e.delta(transaction))?,
+                )?;
+                payload.aset(keys, keys_to_array(transaction, e.keys(transaction))?)?;
+                YXmlText::from(e.target().clone()).into_value_with(&ruby)
+            }
+        };
+        value.funcall::<_, _, Value>("document=", (document,))?;
+        payload.aset(target, value)?;
+
+        array.push(payload)?;
+    }
+
+    Ok(array)
+}
+
+fn path_to_array(path: Path) -> Result<RArray, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let array = ruby.ary_new_capa(path.len());
+    for segment in path {
+        match segment {
+            PathSegment::Key(key) => array.push(key.to_string())?,
+            PathSegment::Index(index) => array.push(index)?,
+        }
+    }
+    Ok(array)
+}
+
+fn text_delta_to_array(transaction: &TransactionMut, delta: &[Delta]) -> Result<RArray, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let array = ruby.ary_new_capa(delta.len());
+    for change in delta {
+        let payload = ruby.hash_new();
+        let attrs = match change {
+            Delta::Inserted(value, attrs) => {
+                let value = convert_yvalue_to_ruby_value(value.clone(), transaction);
+                payload.aset(ruby.to_symbol("insert"), value.0.into_inner())?;
+                attrs
+            }
+            Delta::Retain(length, attrs) => {
+                payload.aset(ruby.to_symbol("retain"), *length)?;
+                attrs
+            }
+            Delta::Deleted(length) => {
+                payload.aset(ruby.to_symbol("delete"), *length)?;
+                &None
+            }
+        };
+        if let Some(attrs) = attrs {
+            let attributes = ruby.hash_new();
+            for (key, value) in attrs.iter() {
+                let value: Value = YValue::from(value.clone()).into();
+                attributes.aset(key.to_string(), value)?;
+            }
+            payload.aset(ruby.to_symbol("attributes"), attributes)?;
+        }
+        array.push(payload)?;
+    }
+    Ok(array)
+}
+
+fn changes_to_array(transaction: &TransactionMut, changes: &[Change]) -> Result<RArray, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let array = ruby.ary_new_capa(changes.len());
+    for change in changes {
+        let payload = ruby.hash_new();
+        match change {
+            Change::Added(values) => {
+                let added = ruby.ary_new_capa(values.len());
+                for value in values {
+                    let value = convert_yvalue_to_ruby_value(value.clone(), transaction);
+                    added.push(value.0.into_inner())?;
+                }
+                payload.aset(ruby.to_symbol("added"), added)?;
+            }
+            Change::Retain(position) => payload.aset(ruby.to_symbol("retain"), *position)?,
+            Change::Removed(position) => payload.aset(ruby.to_symbol("removed"), *position)?,
+        }
+        array.push(payload)?;
+    }
+    Ok(array)
+}
+
+fn keys_to_array(
+    transaction: &TransactionMut,
+    keys: &HashMap<Arc<str>, EntryChange>,
+) -> Result<RArray, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let array = ruby.ary_new_capa(keys.len());
+    for (key, change) in keys {
+        let key = ruby.to_symbol(key);
+        let entry = ruby.hash_new();
+        let payload = ruby.hash_new();
+        match change {
+            EntryChange::Inserted(value) => {
+                let value = convert_yvalue_to_ruby_value(value.clone(), transaction);
+                entry.aset(key, value.0.into_inner())?;
+                payload.aset(ruby.to_symbol("inserted"), entry)?;
+            }
+            EntryChange::Updated(old, new) => {
+                let old = convert_yvalue_to_ruby_value(old.clone(), transaction);
+                let new = convert_yvalue_to_ruby_value(new.clone(), transaction);
+                let values = ruby.ary_from_vec(vec![old.0.into_inner(), new.0.into_inner()]);
+                entry.aset(key, values)?;
+                payload.aset(ruby.to_symbol("updated"), entry)?;
+            }
+            EntryChange::Removed(value) => {
+                let value = convert_yvalue_to_ruby_value(value.clone(), transaction);
+                entry.aset(key, value.0.into_inner())?;
+                payload.aset(ruby.to_symbol("removed"), entry)?;
+            }
+        }
+        array.push(payload)?;
+    }
+    Ok(array)
+}
+
+fn xml_node_to_value(node: XmlNode) -> Value {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    match node {
+        XmlNode::Element(element) => YXmlElement::from(element).into_value_with(&ruby),
+        XmlNode::Fragment(fragment) => YXmlFragment::from(fragment).into_value_with(&ruby),
+        XmlNode::Text(text) => YXmlText::from(text).into_value_with(&ruby),
+    }
+}
@@ -1,11 +1,15 @@
 use crate::utils::{convert_yvalue_to_ruby_value, indifferent_hash_key};
+use crate::ydoc::YDoc;
+use crate::yerror::type_error;
+use crate::yevent::observe_deep;
+use crate::yorigin::{call_observer, keep_observer_error};
 use crate::yvalue::YValue;
 use crate::YTransaction;
 use magnus::block::Proc;
-use magnus::{Error, RArray, RHash, Ruby, Value};
+use magnus::{Error, RHash, Ruby, TryConvert, Value};
 use std::cell::RefCell;
 use yrs::types::{EntryChange, Value as YrsValue};
-use yrs::{Any, Map, MapRef, Observable};
+use yrs::{Any, DeepObservable, Map, MapRef, Observable};
 
 #[magnus::wrap(class = "Y::Map")]
 pub(crate) struct YMap(pub(crate) RefCell<MapRef>);
@@ -14,41 +18,49 @@ pub(crate) struct YMap(pub(crate) RefCell<MapRef>);
 unsafe impl Send for YMap {}
 
 impl YMap {
-    pub(crate) fn ymap_clear(&self, transaction: &YTransaction) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    pub(crate) fn ymap_clear(&self, transaction: &YTransaction) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
-        self.0.borrow_mut().clear(tx)
+        self.0.borrow_mut().clear(tx);
+
+        Ok(())
     }
-    pub(crate) fn ymap_contains(&self, transaction: &YTransaction, key: Value) -> bool {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+    pub(crate) fn ymap_contains(
+        &self,
+        transaction: &YTransaction,
+        key: Value,
+    ) -> Result<bool, Error> {
+        let tx = &transaction.read_transaction()?;
 
         match indifferent_hash_key(key) {
-            None => false,
-            Some(k) => self.0.borrow().contains_key(tx, k.as_str()),
+            None => Ok(false),
+            Some(k) => Ok(self.0.borrow().contains_key(tx, k.as_str())),
         }
     }
 
-    pub(crate) fn ymap_each(&self, transaction: &YTransaction, proc: Proc) {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
-        self.0.borrow().iter(tx).for_each(|(key, val)| {
+    pub(crate) fn ymap_each(&self, transaction: &YTransaction, proc: Proc) -> Result<(), Error> {
+        let tx = &transaction.read_transaction()?;
+        for (key, val) in self.0.borrow().iter(tx) {
             let k = key.to_string();
             let v = *convert_yvalue_to_ruby_value(val, tx).0.borrow();
-            proc.call::<(String, Value), Value>((k, v))
-                .expect("cannot iterate map");
-        })
+            proc.call::<(String, Value), Value>((k, v))?;
+        }
+
+        Ok(())
     }
 
-    pub(crate) fn ymap_get(&self, transaction: &YTransaction, key: Value) -> Option<Value> {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+    pub(crate) fn ymap_get(
+        &self,
+        transaction: &YTransaction,
+        key: Value,
+    ) -> Result<Option<Value>, Error> {
+        let tx = &transaction.read_transaction()?;
 
-        indifferent_hash_key(key)
+        Ok(indifferent_hash_key(key)
             .map(|k| self.0.borrow().get(tx, k.as_str()))
             .map(|v| v.unwrap_or(YrsValue::Any(Any::Undefined)))
-            .map(|v| *convert_yvalue_to_ruby_value(v, tx).0.borrow())
+            .map(|v| *convert_yvalue_to_ruby_value(v, tx).0.borrow()))
     }
     pub(crate) fn ymap_insert(
         &self,
@@ -56,18 +68,20 @@ impl YMap {
         key: Value,
         value: Value,
     ) -> Result<(), Error> {
-        let ruby = Ruby::get().unwrap();
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
         match indifferent_hash_key(key) {
-            None => Err(Error::new(
-                ruby.exception_runtime_error(),
+            None => Err(type_error(
                 "invalid key type, make sure it is either of type Symbol or String",
             )),
             Some(k) => {
-                let v = Any::from(YValue::from(value));
-                self.0.borrow_mut().insert(tx, k, v);
+                if let Ok(doc) = <&YDoc>::try_convert(value) {
+                    self.0.borrow_mut().insert(tx, k, doc.subdoc()?);
+                } else {
+                    let v = Any::try_from(YValue::from(value))?;
+                    self.0.borrow_mut().insert(tx, k, v);
+                }
 
                 Ok(())
             }
@@ -89,8 +103,13 @@ impl YMap {
                     match change {
                         EntryChange::Inserted(v) => {
                             let h = ruby.hash_new();
-                            h.aset(ruby.to_symbol(key), *YValue::from(v.clone()).0.borrow())
-                                .expect("cannot add change::inserted");
+                            h.aset(
+                                ruby.to_symbol(key),
+                                *convert_yvalue_to_ruby_value(v.clone(), transaction)
+                                    .0
+                                    .borrow(),
+                            )
+                            .expect("cannot add change::inserted");
 
                             let payload = ruby.hash_new();
                             payload
@@ -102,10 +121,18 @@ impl YMap {
                         EntryChange::Updated(old, new) => {
                             let values = ruby.ary_new_capa(2);
                             values
-                                .push(*YValue::from(old.clone()).0.borrow())
+                                .push(
+                                    *convert_yvalue_to_ruby_value(old.clone(), transaction)
+                                        .0
+                                        .borrow(),
+                                )
                                 .expect("cannot push change::updated");
                             values
-                                .push(*YValue::from(new.clone()).0.borrow())
+                                .push(
+                                    *convert_yvalue_to_ruby_value(new.clone(), transaction)
+                                        .0
+                                        .borrow(),
+                                )
                                 .expect("cannot push change::updated");
 
                             let h = ruby.hash_new();
@@ -121,8 +148,13 @@ impl YMap {
                         }
                         EntryChange::Removed(v) => {
                             let h = ruby.hash_new();
-                            h.aset(ruby.to_symbol(key), *YValue::from(v.clone()).0.borrow())
-                                .expect("cannot push change::removed");
+                            h.aset(
+                                ruby.to_symbol(key),
+                                *convert_yvalue_to_ruby_value(v.clone(), transaction)
+                                    .0
+                                    .borrow(),
+                            )
+                            .expect("cannot push change::removed");
 
                             let payload = ruby.hash_new();
                             payload
@@ -134,43 +166,48 @@ impl YMap {
                     }
                 }
 
-                block
-                    .call::<(RArray,), Value>((changes,))
-                    .expect("cannot call block");
+                keep_observer_error(call_observer(block, changes, transaction));
             })
             .into()
     }
-    pub(crate) fn ymap_remove(&self, transaction: &YTransaction, key: Value) -> Option<Value> {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    pub(crate) fn ymap_observe_deep(&self, block: Proc, document: Value) -> u32 {
+        observe_deep(&mut *self.0.borrow_mut(), block, document)
+    }
+    pub(crate) fn ymap_remove(
+        &self,
+        transaction: &YTransaction,
+        key: Value,
+    ) -> Result<Option<Value>, Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
-        indifferent_hash_key(key)
+        Ok(indifferent_hash_key(key)
             .map(|k| self.0.borrow().remove(tx, k.as_str()))
             .map(|v| v.unwrap_or(YrsValue::Any(Any::Undefined)))
-            .map(|v| *YValue::from(v).0.borrow())
+            .map(|v| *convert_yvalue_to_ruby_value(v, tx).0.borrow()))
     }
-    pub(crate) fn ymap_size(&self, transaction: &YTransaction) -> u32 {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+    pub(crate) fn ymap_size(&self, transaction: &YTransaction) -> Result<u32, Error> {
+        let tx = &transaction.read_transaction()?;
 
-        self.0.borrow().len(tx)
+        Ok(self.0.borrow().len(tx))
     }
-    pub(crate) fn ymap_to_h(&self, transaction: &YTransaction) -> RHash {
+    pub(crate) fn ymap_to_h(&self, transaction: &YTransaction) -> Result<RHash, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
         let hash = ruby.hash_new();
         for (k, v) in self.0.borrow().iter(tx) {
-            let value = *YValue::from(v).0.borrow();
-            hash.aset(k.to_string(), value)
-                .expect("cannot insert into hash");
+            let value = *convert_yvalue_to_ruby_value(v, tx).0.borrow();
+            hash.aset(k.to_string(), value)?;
         }
-        hash
+        Ok(hash)
     }
     pub(crate) fn ymap_unobserve(&self, subscription_id: u32) {
         self.0.borrow_mut().unobserve(subscription_id);
     }
+    pub(crate) fn ymap_unobserve_deep(&self, subscription_id: u32) {
+        self.0.borrow_mut().unobserve_deep(subscription_id);
+    }
 }
 
 impl From<MapRef> for YMap {
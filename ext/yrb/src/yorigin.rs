@@ -0,0 +1,82 @@
+use crate::utils::{origin_is_local, origin_to_value};
+use magnus::block::Proc;
+use magnus::value::BoxValue;
+use magnus::{Error, IntoValue, Ruby, Value};
+use std::cell::RefCell;
+use yrs::{Origin, TransactionMut};
+
+#[magnus::wrap(class = "Y::Origin")]
+pub(crate) struct YOrigin(pub(crate) Option<Origin>);
+
+/// SAFETY: This is safe because we only access this data when the GVL is held.
+unsafe impl Send for YOrigin {}
+
+impl YOrigin {
+    pub(crate) fn yorigin_local(&self) -> bool {
+        origin_is_local(self.0.as_ref())
+    }
+
+    pub(crate) fn yorigin_value(&self) -> Option<Value> {
+        self.0.as_ref().map(origin_to_value)
+    }
+}
+
+impl From<&TransactionMut<'_>> for YOrigin {
+    fn from(transaction: &TransactionMut<'_>) -> Self {
+        YOrigin(transaction.origin().cloned())
+    }
+}
+
+// Observers receive the origin of the transaction as second argument, but
+// only if they accept it. This keeps single argument lambdas working.
+pub(crate) fn call_observer<T: IntoValue>(
+    block: Proc,
+    payload: T,
+    transaction: &TransactionMut,
+) -> Result<Value, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let payload = payload.into_value_with(&ruby);
+
+    if (0..2).contains(&block.arity()) {
+        block.call::<(Value,), Value>((payload,))
+    } else {
+        let origin = YOrigin::from(transaction).into_value_with(&ruby);
+        block.call::<(Value, Value), Value>((payload, origin))
+    }
+}
+
+// yrs calls observers while it commits a transaction (or changes an awareness)
+// and has no way to pass their errors on. The first error is kept until yrs
+// returned, the method that caused the commit raises it then. Observers called
+// after a failing one are still called.
+thread_local! {
+    static OBSERVER_ERROR: RefCell<Option<ObserverError>> = const { RefCell::new(None) };
+}
+
+struct ObserverError {
+    error: Error,
+    // no Ruby object references the exception meanwhile
+    _value: Option<BoxValue<Value>>,
+}
+
+pub(crate) fn keep_observer_error<T>(result: Result<T, Error>) {
+    if let Err(error) = result {
+        OBSERVER_ERROR.with(|kept| {
+            let mut kept = kept.borrow_mut();
+            if kept.is_none() {
+                let value = error.value().map(BoxValue::new);
+                *kept = Some(ObserverError {
+                    error,
+                    _value: value,
+                });
+            }
+        });
+    }
+}
+
+pub(crate) fn raise_observer_error() -> Result<(), Error> {
+    match OBSERVER_ERROR.with(|kept| kept.borrow_mut().take()) {
+        Some(kept) => Err(kept.error),
+        None => Ok(()),
+    }
+}
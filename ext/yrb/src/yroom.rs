@@ -0,0 +1,201 @@
+use crate::utils::EncodedBytes;
+use crate::yawareness::YAwarenessEvent;
+use magnus::gc::Marker;
+use magnus::value::ReprValue;
+use magnus::{
+    DataTypeFunctions, Error, RArray, RHash, RString, Ruby, TryConvert, TypedData, Value,
+};
+use std::cell::{Cell, RefCell};
+use y_sync::sync::{Message, SyncMessage, MSG_AWARENESS};
+use yrs::block::ClientID;
+use yrs::updates::encoder::{Encode, Encoder, EncoderV1};
+
+// Every connection is stored as an array of its sender, its protocol and the
+// IDs of the awareness clients it has introduced. Keeping them in Ruby objects
+// lets a single mark of the hash keep all of them alive.
+const SENDER: isize = 0;
+const PROTOCOL: isize = 1;
+const CLIENTS: isize = 2;
+
+#[derive(TypedData)]
+#[magnus(class = "Y::Room", free_immediately, mark)]
+pub(crate) struct YRoom {
+    connections: RHash,
+    receiving: Cell<Option<Value>>,
+    notifying: Cell<bool>,
+    stale_clients: RefCell<Vec<ClientID>>,
+}
+
+/// SAFETY: This is safe because we only access this data when the GVL is held.
+unsafe impl Send for YRoom {}
+
+impl DataTypeFunctions for YRoom {
+    fn mark(&self, marker: &Marker) {
+        marker.mark(self.connections);
+        if let Some(id) = self.receiving.get() {
+            marker.mark(id);
+        }
+    }
+}
+
+impl YRoom {
+    pub(crate) fn yroom_new() -> Self {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        Self {
+            connections: ruby.hash_new(),
+            receiving: Cell::new(None),
+            notifying: Cell::new(false),
+            stale_clients: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn yroom_connect(
+        &self,
+        id: Value,
+        sender: Value,
+        protocol: Value,
+    ) -> Result<(), Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        if self.yroom_connected(id) {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                format!("already connected: {}", id.inspect()),
+            ));
+        }
+
+        let connection = ruby.ary_from_vec(vec![sender, protocol, ruby.ary_new().as_value()]);
+        self.connections.aset(id, connection)
+    }
+
+    pub(crate) fn yroom_connected(&self, id: Value) -> bool {
+        self.connections.get(id).is_some()
+    }
+
+    pub(crate) fn yroom_connections(&self) -> Result<RArray, Error> {
+        self.connections.funcall("keys", ())
+    }
+
+    // The clients of a disconnected peer are removed from the awareness once
+    // the room is done with the current message, see yroom_take_stale_clients
+    pub(crate) fn yroom_disconnect(&self, id: Value) -> Result<(), Error> {
+        let connection: Option<RArray> = self.connections.delete(id)?;
+        if let Some(connection) = connection {
+            let clients: Vec<ClientID> = connection.entry::<RArray>(CLIENTS)?.to_vec()?;
+            self.stale_clients.borrow_mut().extend(clients);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn yroom_protocol(&self, id: Value) -> Result<Value, Error> {
+        self.connection(id)?.entry(PROTOCOL)
+    }
+
+    pub(crate) fn yroom_send(&self, id: Value, message: RString) -> Result<(), Error> {
+        self.connection(id)?
+            .entry::<Value>(SENDER)?
+            .funcall::<_, _, Value>("call", (message,))
+            .map(|_| ())
+    }
+
+    pub(crate) fn yroom_set_receiving(&self, id: Option<Value>) {
+        self.receiving.set(id);
+    }
+
+    // Awareness states can not be removed while the awareness notifies its
+    // observers, so they are handed out once the room is done with a message
+    pub(crate) fn yroom_take_stale_clients(&self) -> Vec<ClientID> {
+        if self.notifying.get() || self.receiving.get().is_some() {
+            return Vec::new();
+        }
+        self.stale_clients.take()
+    }
+
+    pub(crate) fn yroom_broadcast_update(
+        &self,
+        update: EncodedBytes,
+        origin: Option<Value>,
+    ) -> Result<(), Error> {
+        let message = Message::Sync(SyncMessage::Update(update.0)).encode_v1();
+        self.broadcast(&message, origin)
+    }
+
+    pub(crate) fn yroom_broadcast_awareness(&self, event: &YAwarenessEvent) -> Result<(), Error> {
+        let receiving = self.receiving.get();
+        if let Some(id) = receiving {
+            self.track_clients(id, event)?;
+        }
+
+        let update = match event.update_bytes() {
+            Some(update) => update,
+            None => return Ok(()),
+        };
+        // the same framing as Message::Awareness, which would have to decode
+        // the update of the event first
+        let mut encoder = EncoderV1::new();
+        encoder.write_var(MSG_AWARENESS);
+        encoder.write_buf(update);
+        let message = encoder.to_vec();
+
+        let notifying = self.notifying.replace(true);
+        let result = self.broadcast(&message, receiving);
+        self.notifying.set(notifying);
+        result
+    }
+
+    fn connection(&self, id: Value) -> Result<RArray, Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        match self.connections.get(id) {
+            Some(connection) => RArray::try_convert(connection),
+            None => Err(Error::new(
+                ruby.exception_arg_error(),
+                format!("not connected: {}", id.inspect()),
+            )),
+        }
+    }
+
+    fn track_clients(&self, id: Value, event: &YAwarenessEvent) -> Result<(), Error> {
+        let connection = match self.connections.get(id) {
+            Some(connection) => RArray::try_convert(connection)?,
+            None => return Ok(()),
+        };
+        let clients: RArray = connection.entry(CLIENTS)?;
+        for client in event.added().into_iter().chain(event.updated()) {
+            if !clients.includes(client) {
+                clients.push(client)?;
+            }
+        }
+        for client in event.removed() {
+            clients.funcall::<_, _, Value>("delete", (client,))?;
+        }
+        Ok(())
+    }
+
+    // The message is encoded once and the very same (frozen) string is sent to
+    // all connections. Connections are read from a snapshot, as a sender that
+    // fails is disconnected while iterating.
+    fn broadcast(&self, message: &[u8], except: Option<Value>) -> Result<(), Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        let message = ruby.str_from_slice(message);
+        message.freeze();
+
+        let connections: RArray = self.connections.funcall("to_a", ())?;
+        for index in 0..connections.len() {
+            let (id, connection): (Value, RArray) = connections.entry(index as isize)?;
+            if let Some(except) = except {
+                if id.eql(except)? {
+                    continue;
+                }
+            }
+
+            let sender: Value = connection.entry(SENDER)?;
+            match sender.funcall::<_, _, Value>("call", (message,)) {
+                Ok(_) => {}
+                Err(error) if error.is_kind_of(ruby.exception_standard_error()) => {
+                    self.yroom_disconnect(id)?;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+}
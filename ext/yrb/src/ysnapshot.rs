@@ -0,0 +1,44 @@
+use crate::utils::EncodedBytes;
+use crate::yerror::decode_error;
+use magnus::Error;
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::Snapshot;
+
+#[magnus::wrap(class = "Y::Snapshot")]
+pub(crate) struct YSnapshot(pub(crate) Snapshot);
+
+/// SAFETY: This is safe because we only access this data when the GVL is held.
+unsafe impl Send for YSnapshot {}
+
+impl YSnapshot {
+    pub(crate) fn ysnapshot_decode(encoded: EncodedBytes) -> Result<Self, Error> {
+        Snapshot::decode_v1(encoded.0.as_slice())
+            .map(YSnapshot::from)
+            .map_err(|error| decode_error("cannot decode snapshot", error))
+    }
+
+    pub(crate) fn ysnapshot_decode_v2(encoded: EncodedBytes) -> Result<Self, Error> {
+        Snapshot::decode_v2(encoded.0.as_slice())
+            .map(YSnapshot::from)
+            .map_err(|error| decode_error("cannot decode snapshot", error))
+    }
+
+    pub(crate) fn ysnapshot_encode(&self) -> Vec<u8> {
+        self.0.encode_v1()
+    }
+
+    pub(crate) fn ysnapshot_encode_v2(&self) -> Vec<u8> {
+        self.0.encode_v2()
+    }
+
+    pub(crate) fn ysnapshot_equals(&self, other: &YSnapshot) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl From<Snapshot> for YSnapshot {
+    fn from(value: Snapshot) -> Self {
+        YSnapshot(value)
+    }
+}
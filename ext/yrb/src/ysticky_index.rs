@@ -0,0 +1,157 @@
+use crate::utils::EncodedBytes;
+use crate::yerror::decode_error;
+use crate::YTransaction;
+use magnus::value::ReprValue;
+use magnus::{Error, RHash, Ruby, Symbol, TryConvert};
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{Assoc, IndexScope, IndexedSequence, StickyIndex, ID};
+
+#[magnus::wrap(class = "Y::StickyIndex")]
+pub(crate) struct YStickyIndex(pub(crate) StickyIndex);
+
+/// SAFETY: This is safe because we only access this data when the GVL is held.
+unsafe impl Send for YStickyIndex {}
+
+impl YStickyIndex {
+    pub(crate) fn ysticky_index_decode(encoded: EncodedBytes) -> Result<Self, Error> {
+        StickyIndex::decode_v1(encoded.0.as_slice())
+            .map(YStickyIndex::from)
+            .map_err(|error| decode_error("cannot decode sticky index", error))
+    }
+
+    // The hash representation is compatible with the JSON representation of
+    // relative positions in Yjs: {"type", "tname", "item", "assoc"}
+    pub(crate) fn ysticky_index_from_h(hash: RHash) -> Result<Self, Error> {
+        let ruby = Ruby::get().unwrap();
+
+        let scope = if let Some(item) = fetch::<RHash>(hash, "item")? {
+            IndexScope::Relative(id_from_hash(item)?)
+        } else if let Some(tname) = fetch::<String>(hash, "tname")? {
+            IndexScope::Root(tname.into())
+        } else if let Some(parent) = fetch::<RHash>(hash, "type")? {
+            IndexScope::Nested(id_from_hash(parent)?)
+        } else {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                "sticky index requires one of: item, tname, type",
+            ));
+        };
+        let assoc = match fetch::<i64>(hash, "assoc")? {
+            Some(assoc) if assoc < 0 => Assoc::Before,
+            _ => Assoc::After,
+        };
+
+        Ok(YStickyIndex(StickyIndex::new(scope, assoc)))
+    }
+
+    pub(crate) fn ysticky_index_assoc(&self) -> Symbol {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        match self.0.assoc {
+            Assoc::After => ruby.to_symbol("after"),
+            Assoc::Before => ruby.to_symbol("before"),
+        }
+    }
+
+    pub(crate) fn ysticky_index_encode(&self) -> Vec<u8> {
+        self.0.encode_v1()
+    }
+
+    pub(crate) fn ysticky_index_equals(&self, other: &YStickyIndex) -> bool {
+        self.0 == other.0
+    }
+
+    pub(crate) fn ysticky_index_offset(
+        &self,
+        transaction: &YTransaction,
+    ) -> Result<Option<u32>, Error> {
+        let tx = &transaction.read_transaction()?;
+
+        Ok(self.0.get_offset(tx).map(|offset| offset.index))
+    }
+
+    pub(crate) fn ysticky_index_to_h(&self) -> Result<RHash, Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        let hash = ruby.hash_new();
+
+        match self.0.scope() {
+            IndexScope::Relative(id) => hash.aset("item", id_to_hash(id)?)?,
+            IndexScope::Root(tname) => hash.aset("tname", tname.to_string())?,
+            IndexScope::Nested(id) => hash.aset("type", id_to_hash(id)?)?,
+        }
+        hash.aset("assoc", self.0.assoc as i64)?;
+
+        Ok(hash)
+    }
+}
+
+impl From<StickyIndex> for YStickyIndex {
+    fn from(value: StickyIndex) -> Self {
+        YStickyIndex(value)
+    }
+}
+
+// yrs does not create indexes associated with the position right after the
+// end of a sequence, we stick to the shared type itself in that case, which
+// always resolves to the end of the sequence (same as Yjs does)
+pub(crate) fn sticky_index_at<T: IndexedSequence>(
+    shared: &T,
+    transaction: &YTransaction,
+    index: u32,
+    assoc: Symbol,
+) -> Result<Option<YStickyIndex>, Error> {
+    let mut tx = transaction.transaction_mut()?;
+    let tx = &mut *tx;
+
+    let assoc = assoc_from_symbol(assoc)?;
+    let sticky_index = shared.sticky_index(tx, index, assoc).or_else(|| {
+        (assoc == Assoc::After && index == shared.as_ref().content_len())
+            .then(|| StickyIndex::from_type(tx, shared, assoc))
+    });
+
+    Ok(sticky_index.map(YStickyIndex::from))
+}
+
+fn assoc_from_symbol(assoc: Symbol) -> Result<Assoc, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    match assoc.name()?.as_ref() {
+        "after" => Ok(Assoc::After),
+        "before" => Ok(Assoc::Before),
+        other => Err(Error::new(
+            ruby.exception_arg_error(),
+            format!("assoc must be :before or :after, got: {}", other),
+        )),
+    }
+}
+
+// keys are looked up as strings first (parsed JSON) and as symbols second
+fn fetch<T: TryConvert>(hash: RHash, key: &str) -> Result<Option<T>, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    hash.get(key)
+        .or_else(|| hash.get(ruby.to_symbol(key)))
+        .filter(|value| !value.is_nil())
+        .map(T::try_convert)
+        .transpose()
+}
+
+fn id_from_hash(hash: RHash) -> Result<ID, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let client = fetch::<u64>(hash, "client")?;
+    let clock = fetch::<u32>(hash, "clock")?;
+
+    match (client, clock) {
+        (Some(client), Some(clock)) => Ok(ID::new(client, clock)),
+        _ => Err(Error::new(
+            ruby.exception_arg_error(),
+            "id requires client and clock",
+        )),
+    }
+}
+
+fn id_to_hash(id: &ID) -> Result<RHash, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let hash = ruby.hash_new();
+    hash.aset("client", id.client)?;
+    hash.aset("clock", id.clock)?;
+    Ok(hash)
+}
@@ -0,0 +1,129 @@
+use crate::utils::EncodedBytes;
+use crate::yerror::decode_error;
+use magnus::{Error, RString, Ruby, Symbol};
+use y_sync::awareness::AwarenessUpdate;
+use y_sync::sync::{Message, SyncMessage, MSG_AUTH, MSG_AWARENESS, MSG_QUERY_AWARENESS, MSG_SYNC};
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::StateVector;
+
+// Tags of custom messages are written as a single byte, but read as a
+// variable length integer, so only tags below 128 survive a round trip.
+const MAX_CUSTOM_TAG: u8 = 127;
+
+#[magnus::wrap(class = "Y::Sync::Message")]
+pub(crate) struct YSyncMessage(pub(crate) Message);
+
+/// SAFETY: This is safe because we only access this data when the GVL is held.
+unsafe impl Send for YSyncMessage {}
+
+impl YSyncMessage {
+    pub(crate) fn ysync_message_auth(reason: Option<String>) -> Self {
+        YSyncMessage(Message::Auth(reason))
+    }
+
+    pub(crate) fn ysync_message_awareness(update: EncodedBytes) -> Result<Self, Error> {
+        AwarenessUpdate::decode_v1(update.0.as_slice())
+            .map(|update| YSyncMessage(Message::Awareness(update)))
+            .map_err(|error| decode_error("cannot decode awareness update", error))
+    }
+
+    pub(crate) fn ysync_message_custom(tag: u8, data: EncodedBytes) -> Result<Self, Error> {
+        if tag <= MSG_QUERY_AWARENESS || tag > MAX_CUSTOM_TAG {
+            let ruby = unsafe { Ruby::get_unchecked() };
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                format!(
+                    "custom message tag must be between {} and {}",
+                    MSG_QUERY_AWARENESS + 1,
+                    MAX_CUSTOM_TAG
+                ),
+            ));
+        }
+        Ok(YSyncMessage(Message::Custom(tag, data.0)))
+    }
+
+    pub(crate) fn ysync_message_decode(encoded: EncodedBytes) -> Result<Self, Error> {
+        Message::decode_v1(encoded.0.as_slice())
+            .map(YSyncMessage::from)
+            .map_err(|error| decode_error("cannot decode sync message", error))
+    }
+
+    pub(crate) fn ysync_message_query_awareness() -> Self {
+        YSyncMessage(Message::AwarenessQuery)
+    }
+
+    pub(crate) fn ysync_message_sync_step1(state_vector: EncodedBytes) -> Result<Self, Error> {
+        StateVector::decode_v1(state_vector.0.as_slice())
+            .map(|sv| YSyncMessage(Message::Sync(SyncMessage::SyncStep1(sv))))
+            .map_err(|error| decode_error("cannot decode state vector", error))
+    }
+
+    pub(crate) fn ysync_message_sync_step2(update: EncodedBytes) -> Self {
+        YSyncMessage(Message::Sync(SyncMessage::SyncStep2(update.0)))
+    }
+
+    pub(crate) fn ysync_message_update(update: EncodedBytes) -> Self {
+        YSyncMessage(Message::Sync(SyncMessage::Update(update.0)))
+    }
+
+    pub(crate) fn ysync_message_encode(&self) -> RString {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        ruby.str_from_slice(&self.0.encode_v1())
+    }
+
+    pub(crate) fn ysync_message_equals(&self, other: &YSyncMessage) -> bool {
+        self.0 == other.0
+    }
+
+    // The content of the message without the framing, e.g. the update of an
+    // update message. Auth and awareness query messages have none.
+    pub(crate) fn ysync_message_payload(&self) -> Option<RString> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        match &self.0 {
+            Message::Sync(SyncMessage::SyncStep1(sv)) => Some(ruby.str_from_slice(&sv.encode_v1())),
+            Message::Sync(SyncMessage::SyncStep2(update)) => Some(ruby.str_from_slice(update)),
+            Message::Sync(SyncMessage::Update(update)) => Some(ruby.str_from_slice(update)),
+            Message::Awareness(update) => Some(ruby.str_from_slice(&update.encode_v1())),
+            Message::Custom(_, data) => Some(ruby.str_from_slice(data)),
+            Message::Auth(_) | Message::AwarenessQuery => None,
+        }
+    }
+
+    pub(crate) fn ysync_message_reason(&self) -> Option<String> {
+        match &self.0 {
+            Message::Auth(reason) => reason.clone(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn ysync_message_tag(&self) -> u8 {
+        match &self.0 {
+            Message::Sync(_) => MSG_SYNC,
+            Message::Awareness(_) => MSG_AWARENESS,
+            Message::Auth(_) => MSG_AUTH,
+            Message::AwarenessQuery => MSG_QUERY_AWARENESS,
+            Message::Custom(tag, _) => *tag,
+        }
+    }
+
+    pub(crate) fn ysync_message_type(&self) -> Symbol {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        let name = match &self.0 {
+            Message::Sync(SyncMessage::SyncStep1(_)) => "sync_step1",
+            Message::Sync(SyncMessage::SyncStep2(_)) => "sync_step2",
+            Message::Sync(SyncMessage::Update(_)) => "update",
+            Message::Awareness(_) => "awareness",
+            Message::Auth(_) => "auth",
+            Message::AwarenessQuery => "query_awareness",
+            Message::Custom(_, _) => "custom",
+        };
+        ruby.to_symbol(name)
+    }
+}
+
+impl From<Message> for YSyncMessage {
+    fn from(value: Message) -> Self {
+        YSyncMessage(value)
+    }
+}
@@ -1,15 +1,19 @@
+use crate::utils::convert_yvalue_to_ruby_value;
 use crate::yattrs::YAttrs;
 use crate::ydiff::YDiff;
+use crate::yerror::{check_index, check_range};
+use crate::yevent::observe_deep;
+use crate::yorigin::{call_observer, keep_observer_error};
+use crate::ysticky_index::{sticky_index_at, YStickyIndex};
 use crate::yvalue::YValue;
 use crate::YTransaction;
 use magnus::block::Proc;
-use magnus::value::Qnil;
-use magnus::RArray;
 pub(crate) use magnus::{Error, IntoValue, RHash, Ruby, Value};
+use magnus::{RArray, Symbol};
 use std::cell::RefCell;
 use yrs::types::text::YChange;
 use yrs::types::Delta;
-use yrs::{Any, GetString, Observable, Text, TextRef};
+use yrs::{Any, DeepObservable, GetString, Observable, Text, TextRef};
 
 #[magnus::wrap(class = "Y::Text")]
 pub(crate) struct YText(pub(crate) RefCell<TextRef>);
@@ -18,14 +22,13 @@ pub(crate) struct YText(pub(crate) RefCell<TextRef>);
 unsafe impl Send for YText {}
 
 impl YText {
-    pub(crate) fn ytext_diff(&self, transaction: &YTransaction) -> RArray {
+    pub(crate) fn ytext_diff(&self, transaction: &YTransaction) -> Result<RArray, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
         let array = ruby.ary_new();
         for diff in self.0.borrow().diff(tx, YChange::identity).iter() {
-            let yvalue = YValue::from(diff.insert.clone());
+            let yvalue = convert_yvalue_to_ruby_value(diff.insert.clone(), tx);
             let insert = yvalue.0.into_inner();
             let attributes = diff.attributes.as_ref().map_or_else(
                 || None,
@@ -47,7 +50,7 @@ impl YText {
                 .push(ydiff.into_value_with(&ruby))
                 .expect("cannot push diff to array");
         }
-        array
+        Ok(array)
     }
     pub(crate) fn ytext_format(
         &self,
@@ -55,35 +58,49 @@ impl YText {
         index: u32,
         length: u32,
         attrs: RHash,
-    ) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_range(index, length, self.0.borrow().len(tx))?;
 
-        let a = YAttrs::from(attrs);
+        let a = YAttrs::try_from(attrs)?;
 
         self.0
             .borrow_mut()
-            .format(tx, index, length, a.0.into_inner())
+            .format(tx, index, length, a.0.into_inner());
+
+        Ok(())
     }
-    pub(crate) fn ytext_insert(&self, transaction: &YTransaction, index: u32, chunk: String) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    pub(crate) fn ytext_insert(
+        &self,
+        transaction: &YTransaction,
+        index: u32,
+        chunk: String,
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_index(index, self.0.borrow().len(tx))?;
+
+        self.0.borrow_mut().insert(tx, index, chunk.as_str());
 
-        self.0.borrow_mut().insert(tx, index, chunk.as_str())
+        Ok(())
     }
     pub(crate) fn ytext_insert_embed(
         &self,
         transaction: &YTransaction,
         index: u32,
         content: Value,
-    ) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_index(index, self.0.borrow().len(tx))?;
 
         let yvalue = YValue::from(content);
-        let avalue = Any::from(yvalue);
+        let avalue = Any::try_from(yvalue)?;
 
         self.0.borrow_mut().insert_embed(tx, index, avalue);
+
+        Ok(())
     }
     pub(crate) fn ytext_insert_embed_with_attributes(
         &self,
@@ -91,18 +108,21 @@ impl YText {
         index: u32,
         embed: Value,
         attrs: RHash,
-    ) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_index(index, self.0.borrow().len(tx))?;
 
         let yvalue = YValue::from(embed);
-        let avalue = Any::from(yvalue);
+        let avalue = Any::try_from(yvalue)?;
 
-        let a = YAttrs::from(attrs);
+        let a = YAttrs::try_from(attrs)?;
 
         self.0
             .borrow_mut()
             .insert_embed_with_attributes(tx, index, avalue, a.0.into_inner());
+
+        Ok(())
     }
     pub(crate) fn ytext_insert_with_attributes(
         &self,
@@ -110,21 +130,23 @@ impl YText {
         index: u32,
         chunk: String,
         attrs: RHash,
-    ) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_index(index, self.0.borrow().len(tx))?;
 
-        let a = YAttrs::from(attrs);
+        let a = YAttrs::try_from(attrs)?;
 
         self.0
             .borrow_mut()
-            .insert_with_attributes(tx, index, chunk.as_str(), a.0.into_inner())
+            .insert_with_attributes(tx, index, chunk.as_str(), a.0.into_inner());
+
+        Ok(())
     }
-    pub(crate) fn ytext_length(&self, transaction: &YTransaction) -> u32 {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+    pub(crate) fn ytext_length(&self, transaction: &YTransaction) -> Result<u32, Error> {
+        let tx = &transaction.read_transaction()?;
 
-        self.0.borrow().len(tx)
+        Ok(self.0.borrow().len(tx))
     }
     pub(crate) fn ytext_observe(&self, block: Proc) -> Result<u32, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
@@ -140,11 +162,11 @@ impl YText {
             .observe(move |transaction, text_event| {
                 let ruby = unsafe { Ruby::get_unchecked() };
                 let delta = text_event.delta(transaction);
-                let (_, errors): (Vec<_>, Vec<_>) = delta
+                delta
                     .iter()
                     .map(|change| match change {
                         Delta::Inserted(value, attrs) => {
-                            let yvalue = YValue::from(value.clone());
+                            let yvalue = convert_yvalue_to_ruby_value(value.clone(), transaction);
                             let payload = ruby.hash_new();
                             payload
                                 .aset(delta_insert, yvalue.0.into_inner())
@@ -162,7 +184,7 @@ impl YText {
                                     None => None,
                                 })
                                 .map(|attrs_hash| attrs_hash.map(|v| payload.aset(attributes, v)))
-                                .map(|_| block.call::<(RHash,), Qnil>((payload,)))
+                                .map(|_| call_observer(block, payload, transaction))
                         }
                         Delta::Retain(index, attrs) => {
                             let payload = ruby.hash_new();
@@ -185,7 +207,7 @@ impl YText {
                                     None => None,
                                 })
                                 .map(|attrs_hash| attrs_hash.map(|v| payload.aset(attributes, v)))
-                                .map(|_| block.call::<(RHash,), Qnil>((payload,)))
+                                .map(|_| call_observer(block, payload, transaction))
                         }
                         Delta::Deleted(index) => {
                             let payload = ruby.hash_new();
@@ -194,41 +216,63 @@ impl YText {
 
                             payload
                                 .aset(delta_delete, yvalue.0.into_inner())
-                                .map(|()| block.call::<(RHash,), Qnil>((payload,)))
+                                .map(|()| call_observer(block, payload, transaction))
                         }
                     })
-                    .partition(Result::is_ok);
-
-                if !errors.is_empty() {
-                    // todo: make sure we respect errors and let the method fail by
-                    //  by returning a Result containing an Error
-                }
+                    .for_each(|result| keep_observer_error(result.and_then(|called| called)));
             })
             .into();
 
         Ok(subscription_id)
     }
-    pub(crate) fn ytext_push(&self, transaction: &YTransaction, chunk: String) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    pub(crate) fn ytext_observe_deep(&self, block: Proc, document: Value) -> u32 {
+        observe_deep(&mut *self.0.borrow_mut(), block, document)
+    }
+    pub(crate) fn ytext_push(
+        &self,
+        transaction: &YTransaction,
+        chunk: String,
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+
+        self.0.borrow_mut().push(tx, chunk.as_str());
 
-        self.0.borrow_mut().push(tx, chunk.as_str())
+        Ok(())
     }
-    pub(crate) fn ytext_remove_range(&self, transaction: &YTransaction, start: u32, length: u32) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    pub(crate) fn ytext_remove_range(
+        &self,
+        transaction: &YTransaction,
+        start: u32,
+        length: u32,
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_range(start, length, self.0.borrow().len(tx))?;
 
-        self.0.borrow_mut().remove_range(tx, start, length)
+        self.0.borrow_mut().remove_range(tx, start, length);
+
+        Ok(())
+    }
+    pub(crate) fn ytext_sticky_index(
+        &self,
+        transaction: &YTransaction,
+        index: u32,
+        assoc: Symbol,
+    ) -> Result<Option<YStickyIndex>, Error> {
+        sticky_index_at(&*self.0.borrow(), transaction, index, assoc)
     }
-    pub(crate) fn ytext_to_s(&self, transaction: &YTransaction) -> String {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    pub(crate) fn ytext_to_s(&self, transaction: &YTransaction) -> Result<String, Error> {
+        let tx = &transaction.read_transaction()?;
 
-        self.0.borrow().get_string(tx)
+        Ok(self.0.borrow().get_string(tx))
     }
     pub(crate) fn ytext_unobserve(&self, subscription_id: u32) {
         self.0.borrow_mut().unobserve(subscription_id);
     }
+    pub(crate) fn ytext_unobserve_deep(&self, subscription_id: u32) {
+        self.0.borrow_mut().unobserve_deep(subscription_id);
+    }
 }
 
 impl From<TextRef> for YText {
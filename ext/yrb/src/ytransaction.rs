@@ -1,129 +1,291 @@
+use crate::utils::{without_gvl, EncodedBytes};
 use crate::yarray::YArray;
+use crate::yerror::{decode_error, transaction_error};
 use crate::ymap::YMap;
+use crate::yorigin::raise_observer_error;
+use crate::ysnapshot::YSnapshot;
 use crate::ytext::YText;
+use crate::yupdate::{summarize_update, PendingUpdates};
 use crate::yxml_element::YXmlElement;
 use crate::yxml_fragment::YXmlFragment;
 use crate::yxml_text::YXmlText;
-use magnus::{Error, Ruby};
-use std::cell::{RefCell, RefMut};
+use magnus::{Error, RHash, RString, Ruby};
+use std::cell::{Ref, RefCell, RefMut};
+use std::rc::Rc;
+use yrs::encoding::read::Error as DecodeError;
 use yrs::updates::decoder::Decode;
 use yrs::updates::encoder::Encode;
-use yrs::{ReadTxn, TransactionMut, Update};
+use yrs::{ReadTxn, Store, Transaction, TransactionMut, Update};
 
 #[magnus::wrap(class = "Y::Transaction")]
-pub(crate) struct YTransaction(pub(crate) RefCell<Option<TransactionMut<'static>>>);
+pub(crate) struct YTransaction(
+    pub(crate) RefCell<Option<TransactionMut<'static>>>,
+    pub(crate) RefCell<Option<Transaction<'static>>>,
+    pub(crate) Option<Rc<RefCell<PendingUpdates>>>,
+);
 
 /// SAFETY: This is safe because we only access this data when the GVL is held.
 unsafe impl Send for YTransaction {}
 
-impl YTransaction {}
+impl YTransaction {
+    // updates applied within the transaction are tracked until the document
+    // integrated them
+    pub(crate) fn with_pending(mut self, pending: Rc<RefCell<PendingUpdates>>) -> Self {
+        self.2 = Some(pending);
+        self
+    }
+}
 
 impl<'doc> From<TransactionMut<'doc>> for YTransaction {
     fn from(txn: TransactionMut<'doc>) -> Self {
         let txn: TransactionMut<'static> = unsafe { std::mem::transmute(txn) };
-        YTransaction(RefCell::from(Some(txn)))
+        YTransaction(RefCell::from(Some(txn)), RefCell::from(None), None)
+    }
+}
+
+impl<'doc> From<Transaction<'doc>> for YTransaction {
+    fn from(txn: Transaction<'doc>) -> Self {
+        let txn: Transaction<'static> = unsafe { std::mem::transmute(txn) };
+        YTransaction(RefCell::from(None), RefCell::from(Some(txn)), None)
+    }
+}
+
+// Read-only transactions only hold a shared lock on the document store, so
+// any number of them can be active at the same time. Read methods of shared
+// types accept both kinds of transactions.
+pub(crate) enum YReadTransaction<'a> {
+    ReadOnly(Ref<'a, Transaction<'static>>),
+    ReadWrite(Ref<'a, TransactionMut<'static>>),
+}
+
+impl ReadTxn for YReadTransaction<'_> {
+    fn store(&self) -> &Store {
+        match self {
+            YReadTransaction::ReadOnly(tx) => tx.store(),
+            YReadTransaction::ReadWrite(tx) => tx.store(),
+        }
+    }
+}
+
+// Work done without holding the GVL borrows the transaction exclusively, so
+// that other threads using the same transaction meanwhile get an error instead
+// of reading it concurrently.
+pub(crate) enum YExclusiveTransaction<'a> {
+    ReadOnly(RefMut<'a, Transaction<'static>>),
+    ReadWrite(RefMut<'a, TransactionMut<'static>>),
+}
+
+impl ReadTxn for YExclusiveTransaction<'_> {
+    fn store(&self) -> &Store {
+        match self {
+            YExclusiveTransaction::ReadOnly(tx) => tx.store(),
+            YExclusiveTransaction::ReadWrite(tx) => tx.store(),
+        }
     }
 }
 
+fn in_use_error() -> Error {
+    transaction_error("transaction is in use, e.g. by another thread")
+}
+
 // API which is eventually publicly exposed
 impl YTransaction {
-    pub(crate) fn ytransaction_apply_update(&self, update: Vec<u8>) -> Result<(), Error> {
-        let ruby = Ruby::get().unwrap();
-        Update::decode_v1(update.as_slice())
-            .map_err(|error| {
-                Error::new(
-                    ruby.exception_runtime_error(),
-                    format!("cannot decode update: {:?}", error),
-                )
-            })
-            .map(|u| self.transaction().as_mut().unwrap().apply_update(u))
-    }
-
-    pub(crate) fn ytransaction_apply_update_v2(&self, update: Vec<u8>) -> Result<(), Error> {
-        let ruby = Ruby::get().unwrap();
-        Update::decode_v2(update.as_slice())
-            .map_err(|error| {
-                Error::new(
-                    ruby.exception_runtime_error(),
-                    format!("cannot decode update: {:?}", error),
-                )
-            })
-            .map(|u| self.transaction().as_mut().unwrap().apply_update(u))
-    }
-
-    pub(crate) fn ytransaction_commit(&self) {
-        self.transaction().as_mut().unwrap().commit();
-    }
-
-    pub(crate) fn ytransaction_get_array(&self, name: String) -> Option<YArray> {
-        self.transaction()
-            .as_ref()
-            .unwrap()
+    pub(crate) fn ytransaction_apply_update(&self, update: EncodedBytes) -> Result<(), Error> {
+        let encoded = update.0.as_slice();
+        // borrows are taken (and released) while holding the GVL, other
+        // threads fail to borrow them meanwhile
+        let mut tx = self.transaction_mut()?;
+        let mut pending = self.pending_mut()?;
+
+        without_gvl(encoded.len(), || {
+            let update = Update::decode_v1(encoded)?;
+            let expected = update.state_vector();
+            tx.apply_update(update);
+            if let Some(pending) = pending.as_mut() {
+                pending.track(&mut tx, encoded, false, &expected);
+            }
+            Ok(())
+        })
+        .map_err(|error: DecodeError| decode_error("cannot decode update", error))
+    }
+
+    pub(crate) fn ytransaction_apply_update_v2(&self, update: EncodedBytes) -> Result<(), Error> {
+        let encoded = update.0.as_slice();
+        let mut tx = self.transaction_mut()?;
+        let mut pending = self.pending_mut()?;
+
+        without_gvl(encoded.len(), || {
+            let update = Update::decode_v2(encoded)?;
+            let expected = update.state_vector();
+            tx.apply_update(update);
+            if let Some(pending) = pending.as_mut() {
+                pending.track(&mut tx, encoded, true, &expected);
+            }
+            Ok(())
+        })
+        .map_err(|error: DecodeError| decode_error("cannot decode update", error))
+    }
+
+    pub(crate) fn ytransaction_commit(&self) -> Result<(), Error> {
+        // read-only transactions have nothing to commit
+        if let Some(tx) = self
+            .0
+            .try_borrow_mut()
+            .map_err(|_| in_use_error())?
+            .as_mut()
+        {
+            tx.commit();
+        }
+        raise_observer_error()
+    }
+
+    pub(crate) fn ytransaction_get_array(&self, name: String) -> Result<Option<YArray>, Error> {
+        Ok(self
+            .read_transaction()?
             .get_array(name.as_str())
-            .map(YArray::from)
+            .map(YArray::from))
     }
 
-    pub(crate) fn ytransaction_get_map(&self, name: String) -> Option<YMap> {
-        self.transaction()
-            .as_ref()
-            .unwrap()
+    pub(crate) fn ytransaction_get_map(&self, name: String) -> Result<Option<YMap>, Error> {
+        Ok(self
+            .read_transaction()?
             .get_map(name.as_str())
-            .map(YMap::from)
+            .map(YMap::from))
     }
 
-    pub(crate) fn ytransaction_get_text(&self, name: String) -> Option<YText> {
-        self.transaction()
-            .as_ref()
-            .unwrap()
+    pub(crate) fn ytransaction_get_text(&self, name: String) -> Result<Option<YText>, Error> {
+        Ok(self
+            .read_transaction()?
             .get_text(name.as_str())
-            .map(YText::from)
+            .map(YText::from))
     }
 
-    pub(crate) fn ytransaction_get_xml_element(&self, name: String) -> Option<YXmlElement> {
-        self.transaction()
-            .as_ref()
-            .unwrap()
+    pub(crate) fn ytransaction_get_xml_element(
+        &self,
+        name: String,
+    ) -> Result<Option<YXmlElement>, Error> {
+        Ok(self
+            .read_transaction()?
             .get_xml_element(name.as_str())
-            .map(YXmlElement::from)
+            .map(YXmlElement::from))
     }
 
-    pub(crate) fn ytransaction_get_xml_fragment(&self, name: String) -> Option<YXmlFragment> {
-        self.transaction()
-            .as_ref()
-            .unwrap()
+    pub(crate) fn ytransaction_get_xml_fragment(
+        &self,
+        name: String,
+    ) -> Result<Option<YXmlFragment>, Error> {
+        Ok(self
+            .read_transaction()?
             .get_xml_fragment(name.as_str())
-            .map(YXmlFragment::from)
+            .map(YXmlFragment::from))
     }
 
-    pub(crate) fn ytransaction_get_xml_text(&self, name: String) -> Option<YXmlText> {
-        self.transaction()
-            .as_ref()
-            .unwrap()
+    pub(crate) fn ytransaction_get_xml_text(
+        &self,
+        name: String,
+    ) -> Result<Option<YXmlText>, Error> {
+        Ok(self
+            .read_transaction()?
             .get_xml_text(name.as_str())
-            .map(YXmlText::from)
+            .map(YXmlText::from))
     }
 
-    pub(crate) fn ytransaction_state_vector(&self) -> Vec<u8> {
-        self.transaction()
-            .as_ref()
-            .unwrap()
-            .state_vector()
-            .encode_v1()
+    pub(crate) fn ytransaction_snapshot(&self) -> Result<YSnapshot, Error> {
+        Ok(YSnapshot::from(self.read_transaction()?.snapshot()))
     }
 
-    pub(crate) fn ytransaction_state_vector_v2(&self) -> Vec<u8> {
-        self.transaction()
-            .as_ref()
-            .unwrap()
+    pub(crate) fn ytransaction_state_vector(&self) -> Result<RString, Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        let state_vector = self.read_transaction()?.state_vector();
+        Ok(ruby.str_from_slice(&state_vector.encode_v1()))
+    }
+
+    pub(crate) fn ytransaction_state_vector_v2(&self) -> Result<RString, Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        let state_vector = self.read_transaction()?.state_vector();
+        Ok(ruby.str_from_slice(&state_vector.encode_v2()))
+    }
+
+    pub(crate) fn ytransaction_update_summary(&self, update: EncodedBytes) -> Result<RHash, Error> {
+        self.update_summary(update, false)
+    }
+
+    pub(crate) fn ytransaction_update_summary_v2(
+        &self,
+        update: EncodedBytes,
+    ) -> Result<RHash, Error> {
+        self.update_summary(update, true)
+    }
+
+    // the transactions are dropped (and committed) once the borrows are
+    // released, as observers may read through this transaction
+    pub(crate) fn ytransaction_free(&self) -> Result<(), Error> {
+        let read_write = self.0.try_borrow_mut().map_err(|_| in_use_error())?.take();
+        let read_only = self.1.try_borrow_mut().map_err(|_| in_use_error())?.take();
+        drop((read_write, read_only));
+        raise_observer_error()
+    }
+
+    // a read-only transaction that is borrowed exclusively is still active
+    pub(crate) fn ytransaction_read_only(&self) -> bool {
+        !matches!(self.1.try_borrow().as_deref(), Ok(None))
+    }
+
+    // a Y::Transaction is freed when the block it was passed to returns, but
+    // the object can outlive the block
+    pub(crate) fn read_transaction(&self) -> Result<YReadTransaction<'_>, Error> {
+        let read_only = self.1.try_borrow().map_err(|_| in_use_error())?;
+        if let Ok(tx) = Ref::filter_map(read_only, Option::as_ref) {
+            return Ok(YReadTransaction::ReadOnly(tx));
+        }
+        let read_write = self.0.try_borrow().map_err(|_| in_use_error())?;
+        Ref::filter_map(read_write, Option::as_ref)
+            .map(YReadTransaction::ReadWrite)
+            .map_err(|_| transaction_error("transaction is no longer active"))
+    }
+
+    // the blocks an update refers to are looked up in the document, which is
+    // therefore part of the work done without holding the GVL
+    fn update_summary(&self, update: EncodedBytes, v2: bool) -> Result<RHash, Error> {
+        let tx = &self.exclusive_transaction()?;
+        let clocks: usize = tx
             .state_vector()
-            .encode_v2()
+            .iter()
+            .map(|(_, clock)| *clock as usize)
+            .sum();
+        without_gvl(update.0.len() + clocks, || {
+            summarize_update(tx, update.0.as_slice(), v2)
+        })
+        .map_err(|error| decode_error("cannot decode update", error))?
+        .to_hash()
     }
 
-    pub(crate) fn ytransaction_free(&self) {
-        self.0.replace(None);
+    pub(crate) fn exclusive_transaction(&self) -> Result<YExclusiveTransaction<'_>, Error> {
+        let read_only = self.1.try_borrow_mut().map_err(|_| in_use_error())?;
+        if let Ok(tx) = RefMut::filter_map(read_only, Option::as_mut) {
+            return Ok(YExclusiveTransaction::ReadOnly(tx));
+        }
+        let read_write = self.0.try_borrow_mut().map_err(|_| in_use_error())?;
+        RefMut::filter_map(read_write, Option::as_mut)
+            .map(YExclusiveTransaction::ReadWrite)
+            .map_err(|_| transaction_error("transaction is no longer active"))
     }
 
-    pub(crate) fn transaction(&self) -> RefMut<'_, Option<TransactionMut<'static>>> {
-        self.0.borrow_mut()
+    pub(crate) fn transaction_mut(&self) -> Result<RefMut<'_, TransactionMut<'static>>, Error> {
+        let read_write = self.0.try_borrow_mut().map_err(|_| in_use_error())?;
+        RefMut::filter_map(read_write, Option::as_mut).map_err(|_| {
+            if self.ytransaction_read_only() {
+                transaction_error("cannot modify document in a read-only transaction")
+            } else {
+                transaction_error("transaction is no longer active")
+            }
+        })
+    }
+
+    fn pending_mut(&self) -> Result<Option<RefMut<'_, PendingUpdates>>, Error> {
+        self.2
+            .as_ref()
+            .map(|pending| pending.try_borrow_mut().map_err(|_| in_use_error()))
+            .transpose()
     }
 }
@@ -0,0 +1,215 @@
+use crate::utils::{origin_from_value, origin_to_value};
+use crate::yarray::YArray;
+use crate::ydoc::YDoc;
+use crate::yerror::{transaction_error, type_error};
+use crate::ymap::YMap;
+use crate::yorigin::{keep_observer_error, raise_observer_error};
+use crate::ytext::YText;
+use crate::yxml_element::YXmlElement;
+use crate::yxml_fragment::YXmlFragment;
+use crate::yxml_text::YXmlText;
+use magnus::block::Proc;
+use magnus::value::ReprValue;
+use magnus::{Error, RArray, RHash, Ruby, TryConvert, Value};
+use std::cell::RefCell;
+use yrs::types::{Branch, BranchPtr};
+use yrs::undo::{Event, EventKind, Options};
+use yrs::{Transact, UndoManager};
+
+#[magnus::wrap(class = "Y::UndoManager")]
+pub(crate) struct YUndoManager(pub(crate) RefCell<UndoManager>);
+
+/// SAFETY: This is safe because we only access this data when the GVL is held.
+unsafe impl Send for YUndoManager {}
+
+impl YUndoManager {
+    pub(crate) fn yundo_manager_new(
+        doc: &YDoc,
+        scope: RArray,
+        capture_timeout: Option<u64>,
+        tracked_origins: RArray,
+    ) -> Result<Self, Error> {
+        let ruby = Ruby::get().unwrap();
+        let doc = doc.0.borrow();
+
+        let mut branches = scope
+            .into_iter()
+            .map(branch_from_value)
+            .collect::<Result<Vec<BranchPtr>, Error>>()?
+            .into_iter();
+        let first = branches.next().ok_or_else(|| {
+            Error::new(
+                ruby.exception_arg_error(),
+                "provide at least one shared type to track",
+            )
+        })?;
+
+        let mut options = Options::default();
+        if let Some(timeout) = capture_timeout {
+            options.capture_timeout_millis = timeout;
+        }
+        for origin in tracked_origins.into_iter() {
+            options.tracked_origins.insert(origin_from_value(origin)?);
+        }
+
+        // the undo manager registers observers on the document, which is not
+        // possible while a transaction is active
+        doc.try_transact()
+            .map(drop)
+            .map_err(|err| transaction_error(err.to_string()))?;
+
+        let mut manager = UndoManager::with_options(&doc, &first, options);
+        for branch in branches {
+            manager.expand_scope(&branch);
+        }
+
+        Ok(Self(RefCell::new(manager)))
+    }
+
+    pub(crate) fn yundo_manager_can_redo(&self) -> bool {
+        self.0.borrow().can_redo()
+    }
+
+    pub(crate) fn yundo_manager_can_undo(&self) -> bool {
+        self.0.borrow().can_undo()
+    }
+
+    pub(crate) fn yundo_manager_clear(&self) -> Result<(), Error> {
+        self.0
+            .borrow_mut()
+            .clear()
+            .map_err(|err| transaction_error(err.to_string()))?;
+        raise_observer_error()
+    }
+
+    pub(crate) fn yundo_manager_exclude_origin(&self, origin: Value) -> Result<(), Error> {
+        let origin = origin_from_value(origin)?;
+        self.0.borrow_mut().exclude_origin(origin);
+        Ok(())
+    }
+
+    pub(crate) fn yundo_manager_expand_scope(&self, scope: Value) -> Result<(), Error> {
+        let branch = branch_from_value(scope)?;
+        self.0.borrow_mut().expand_scope(&branch);
+        Ok(())
+    }
+
+    pub(crate) fn yundo_manager_include_origin(&self, origin: Value) -> Result<(), Error> {
+        let origin = origin_from_value(origin)?;
+        self.0.borrow_mut().include_origin(origin);
+        Ok(())
+    }
+
+    pub(crate) fn yundo_manager_observe_item_added(&self, block: Proc) -> u32 {
+        self.0
+            .borrow()
+            .observe_item_added(move |_transaction, event| {
+                keep_observer_error(block.call::<(RHash,), Value>((undo_event_to_hash(event),)));
+            })
+            .into()
+    }
+
+    pub(crate) fn yundo_manager_observe_item_popped(&self, block: Proc) -> u32 {
+        self.0
+            .borrow()
+            .observe_item_popped(move |_transaction, event| {
+                keep_observer_error(block.call::<(RHash,), Value>((undo_event_to_hash(event),)));
+            })
+            .into()
+    }
+
+    pub(crate) fn yundo_manager_observe_item_updated(&self, block: Proc) -> u32 {
+        self.0
+            .borrow()
+            .observe_item_updated(move |_transaction, event| {
+                keep_observer_error(block.call::<(RHash,), Value>((undo_event_to_hash(event),)));
+            })
+            .into()
+    }
+
+    pub(crate) fn yundo_manager_redo(&self) -> Result<bool, Error> {
+        let changed = self
+            .0
+            .borrow_mut()
+            .redo()
+            .map_err(|err| transaction_error(err.to_string()))?;
+        raise_observer_error()?;
+        Ok(changed)
+    }
+
+    pub(crate) fn yundo_manager_stop_capturing(&self) {
+        self.0.borrow_mut().reset()
+    }
+
+    pub(crate) fn yundo_manager_undo(&self) -> Result<bool, Error> {
+        let changed = self
+            .0
+            .borrow_mut()
+            .undo()
+            .map_err(|err| transaction_error(err.to_string()))?;
+        raise_observer_error()?;
+        Ok(changed)
+    }
+
+    pub(crate) fn yundo_manager_unobserve_item_added(&self, subscription_id: u32) {
+        self.0.borrow().unobserve_item_added(subscription_id)
+    }
+
+    pub(crate) fn yundo_manager_unobserve_item_popped(&self, subscription_id: u32) {
+        self.0.borrow().unobserve_item_popped(subscription_id)
+    }
+
+    pub(crate) fn yundo_manager_unobserve_item_updated(&self, subscription_id: u32) {
+        self.0.borrow().unobserve_item_updated(subscription_id)
+    }
+}
+
+fn branch_ptr<T: AsRef<Branch>>(shared: &T) -> BranchPtr {
+    BranchPtr::from(shared.as_ref())
+}
+
+fn branch_from_value(value: Value) -> Result<BranchPtr, Error> {
+    if let Ok(text) = <&YText>::try_convert(value) {
+        return Ok(branch_ptr(&*text.0.borrow()));
+    }
+    if let Ok(array) = <&YArray>::try_convert(value) {
+        return Ok(branch_ptr(&*array.0.borrow()));
+    }
+    if let Ok(map) = <&YMap>::try_convert(value) {
+        return Ok(branch_ptr(&*map.0.borrow()));
+    }
+    if let Ok(xml_element) = <&YXmlElement>::try_convert(value) {
+        return Ok(branch_ptr(&*xml_element.0.borrow()));
+    }
+    if let Ok(xml_fragment) = <&YXmlFragment>::try_convert(value) {
+        return Ok(branch_ptr(&*xml_fragment.0.borrow()));
+    }
+    if let Ok(xml_text) = <&YXmlText>::try_convert(value) {
+        return Ok(branch_ptr(&*xml_text.0.borrow()));
+    }
+
+    Err(type_error(
+        "scope must be one of Y::Text, Y::Array, Y::Map, Y::XMLElement, Y::XMLFragment or Y::XMLText",
+    ))
+}
+
+fn undo_event_to_hash(event: &Event<()>) -> RHash {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let kind = match event.kind() {
+        EventKind::Undo => ruby.to_symbol("undo"),
+        EventKind::Redo => ruby.to_symbol("redo"),
+    };
+    let origin = event
+        .origin()
+        .map(origin_to_value)
+        .unwrap_or_else(|| ruby.qnil().as_value());
+
+    let payload = ruby.hash_new();
+    payload
+        .aset(ruby.to_symbol("kind"), kind)
+        .expect("cannot add undo event kind");
+    payload
+        .aset(ruby.to_symbol("origin"), origin)
+        .expect("cannot add undo event origin");
+    payload
+}
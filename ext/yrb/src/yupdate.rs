@@ -0,0 +1,927 @@
+use crate::utils::{without_gvl, EncodedBytes};
+use crate::yerror::decode_error;
+use magnus::{Error, RArray, RHash, RString, Ruby};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::Arc;
+use yrs::block::{
+    ClientID, ItemContent, BLOCK_GC_REF_NUMBER, BLOCK_ITEM_ANY_REF_NUMBER,
+    BLOCK_ITEM_BINARY_REF_NUMBER, BLOCK_ITEM_DELETED_REF_NUMBER, BLOCK_ITEM_DOC_REF_NUMBER,
+    BLOCK_ITEM_EMBED_REF_NUMBER, BLOCK_ITEM_FORMAT_REF_NUMBER, BLOCK_ITEM_JSON_REF_NUMBER,
+    BLOCK_ITEM_MOVE_REF_NUMBER, BLOCK_ITEM_STRING_REF_NUMBER, BLOCK_ITEM_TYPE_REF_NUMBER,
+    BLOCK_SKIP_REF_NUMBER, HAS_ORIGIN, HAS_PARENT_SUB, HAS_RIGHT_ORIGIN,
+};
+use yrs::encoding::read::{Cursor, Error as DecodeError};
+use yrs::updates::decoder::{Decode, Decoder, DecoderV1, DecoderV2};
+use yrs::updates::encoder::Encode;
+use yrs::{
+    diff_updates_v1, diff_updates_v2, encode_state_vector_from_update_v1,
+    encode_state_vector_from_update_v2, merge_updates_v1, merge_updates_v2, DeleteSet, Doc,
+    OffsetKind, Options, ReadTxn, StateVector, Transact, TransactionMut, Update, ID,
+};
+
+// Updates are processed without a document by the module functions (except
+// for compaction), so nothing is integrated and pending structs (with missing
+// dependencies) are preserved as they are. None of them touch Ruby objects
+// while processing, so large updates are processed without holding the GVL.
+
+pub(crate) fn yupdate_compact_v1(update: EncodedBytes) -> Result<RString, Error> {
+    let update = update.0.as_slice();
+    let compacted = without_gvl(update.len(), || compact(update));
+    encoded(compacted, "cannot compact update")
+}
+
+pub(crate) fn yupdate_compact_v2(update: EncodedBytes) -> Result<RString, Error> {
+    let update = update.0.as_slice();
+    let compacted = without_gvl(update.len(), || {
+        Update::decode_v2(update)
+            .and_then(|update| compact(&update.encode_v1()))
+            .and_then(|compacted| Update::decode_v1(&compacted))
+            .map(|update| update.encode_v2())
+    });
+    encoded(compacted, "cannot compact update")
+}
+
+pub(crate) fn yupdate_decode_v1(update: EncodedBytes) -> Result<RHash, Error> {
+    let update = update.0.as_slice();
+    decoded_to_hash(without_gvl(update.len(), || decode_v1(update)))
+}
+
+pub(crate) fn yupdate_decode_v2(update: EncodedBytes) -> Result<RHash, Error> {
+    let update = update.0.as_slice();
+    decoded_to_hash(without_gvl(update.len(), || decode_v2(update)))
+}
+
+pub(crate) fn yupdate_diff_v1(update: EncodedBytes, state: EncodedBytes) -> Result<RString, Error> {
+    let (update, state) = (update.0.as_slice(), state.0.as_slice());
+    let diff = without_gvl(update.len(), || diff_updates_v1(update, state));
+    encoded(diff, "cannot diff update")
+}
+
+pub(crate) fn yupdate_diff_v2(update: EncodedBytes, state: EncodedBytes) -> Result<RString, Error> {
+    let (update, state) = (update.0.as_slice(), state.0.as_slice());
+    let diff = without_gvl(update.len(), || diff_updates_v2(update, state));
+    encoded(diff, "cannot diff update")
+}
+
+pub(crate) fn yupdate_merge_v1(updates: Vec<EncodedBytes>) -> Result<RString, Error> {
+    let updates: Vec<&[u8]> = updates.iter().map(|update| update.0.as_slice()).collect();
+    let work = updates.iter().map(|update| update.len()).sum();
+    let merged = without_gvl(work, || merge_updates_v1(&updates));
+    encoded(merged, "cannot merge updates")
+}
+
+pub(crate) fn yupdate_merge_v2(updates: Vec<EncodedBytes>) -> Result<RString, Error> {
+    let updates: Vec<&[u8]> = updates.iter().map(|update| update.0.as_slice()).collect();
+    let work = updates.iter().map(|update| update.len()).sum();
+    let merged = without_gvl(work, || merge_updates_v2(&updates));
+    encoded(merged, "cannot merge updates")
+}
+
+pub(crate) fn yupdate_state_vector_v1(update: EncodedBytes) -> Result<RString, Error> {
+    let update = update.0.as_slice();
+    let state = without_gvl(update.len(), || encode_state_vector_from_update_v1(update));
+    encoded(state, "cannot read state vector from update")
+}
+
+pub(crate) fn yupdate_state_vector_v2(update: EncodedBytes) -> Result<RString, Error> {
+    let update = update.0.as_slice();
+    let state = without_gvl(update.len(), || encode_state_vector_from_update_v2(update));
+    encoded(state, "cannot read state vector from update")
+}
+
+pub(crate) fn yupdate_v1_to_v2(update: EncodedBytes) -> Result<RString, Error> {
+    let update = update.0.as_slice();
+    let converted = without_gvl(update.len(), || {
+        Update::decode_v1(update).map(|update| update.encode_v2())
+    });
+    encoded(converted, "cannot convert update")
+}
+
+pub(crate) fn yupdate_v2_to_v1(update: EncodedBytes) -> Result<RString, Error> {
+    let update = update.0.as_slice();
+    let converted = without_gvl(update.len(), || {
+        Update::decode_v2(update).map(|update| update.encode_v1())
+    });
+    encoded(converted, "cannot convert update")
+}
+
+fn encoded(result: Result<Vec<u8>, DecodeError>, message: &str) -> Result<RString, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    result
+        .map(|bytes| ruby.str_from_slice(&bytes))
+        .map_err(|error| decode_error(message, error))
+}
+
+// Integrates the update into an empty document, which collects the garbage of
+// deleted blocks when the transaction is committed. Blocks and deletions that
+// can not be integrated yet are kept as they are.
+fn compact(update: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let doc = Doc::with_options(Options {
+        skip_gc: false,
+        ..Options::default()
+    });
+    let mut pending = PendingUpdates::default();
+    {
+        let mut tx = doc.transact_mut();
+        let decoded = Update::decode_v1(update)?;
+        let expected = decoded.state_vector();
+        tx.apply_update(decoded);
+        pending.track(&mut tx, update, false, &expected);
+    }
+    let compacted = doc
+        .transact()
+        .encode_state_as_update_v1(&StateVector::default());
+    match pending.0 {
+        Some(pending) => merge_updates_v1(&[&compacted, &pending.update]),
+        None => Ok(compacted),
+    }
+}
+
+// Number of blocks of a client in the encoded state of a document, and how
+// many of them are deleted (tombstones) or have been garbage collected
+pub(crate) struct ClientStats {
+    pub(crate) client: ClientID,
+    pub(crate) clock: u32,
+    pub(crate) blocks: usize,
+    pub(crate) deleted: usize,
+    pub(crate) gc: usize,
+}
+
+pub(crate) fn client_stats(
+    state: &[u8],
+    delete_set: &DeleteSet,
+) -> Result<Vec<ClientStats>, DecodeError> {
+    let (clients, _) = decode_v1(state)?;
+    let stats = clients
+        .into_iter()
+        .map(|(client, blocks)| {
+            let mut stats = ClientStats {
+                client,
+                clock: 0,
+                blocks: 0,
+                deleted: 0,
+                gc: 0,
+            };
+            // blocks are split at the boundaries of deleted ranges
+            for block in blocks.iter().filter(|block| block.kind != "skip") {
+                stats.blocks += 1;
+                stats.clock = stats.clock.max(block.clock.end);
+                if block.kind == "gc" {
+                    stats.gc += 1;
+                } else if delete_set.is_deleted(&ID::new(client, block.clock.start)) {
+                    stats.deleted += 1;
+                }
+            }
+            stats
+        })
+        .collect();
+    Ok(stats)
+}
+
+// Summarizes which root types an update modifies, so that it can be checked
+// before it is applied. Blocks only carry their parent if they have no
+// origins, otherwise they inherit the parent (and map key) of their origin,
+// which can either be part of the update or of the document. Blocks and
+// deletions that refer to content neither of them contains are unresolved, as
+// yrs integrates them later on without another chance to check them. No Ruby
+// objects are touched, so that it can run without holding the GVL.
+pub(crate) fn summarize_update<T: ReadTxn>(
+    tx: &T,
+    update: &[u8],
+    v2: bool,
+) -> Result<UpdateSummary, DecodeError> {
+    let (clients, delete_set) = if v2 {
+        decode_v2(update)?
+    } else {
+        decode_v1(update)?
+    };
+
+    let mut resolver = RootResolver {
+        tx,
+        state: tx.state_vector(),
+        update: BlockIndex::new(clients),
+        document: BlockIndex(HashMap::new()),
+        loaded: HashSet::new(),
+        resolved: HashMap::new(),
+        unresolved: false,
+    };
+    let blocks: Vec<(ID, &'static str)> = resolver
+        .update
+        .0
+        .iter()
+        .flat_map(|(client, blocks)| {
+            blocks
+                .iter()
+                .map(move |block| (ID::new(*client, block.clock.start), block.kind))
+        })
+        .collect();
+
+    // content is only reported for blocks directly inserted into a root type,
+    // nested types and deletions only mark the root type as modified
+    let mut roots: HashMap<Arc<str>, HashSet<(&'static str, bool)>> = HashMap::new();
+    for (id, kind) in blocks {
+        if let Some(resolved) = resolver.resolve(id) {
+            let content = roots.entry(resolved.root).or_default();
+            if resolved.direct && kind != "deleted" {
+                content.insert((kind, resolved.keyed));
+            }
+        }
+    }
+    // diffs contain the whole delete set of a document, deletions that have
+    // already been applied are not reported again
+    if !delete_set.is_empty() {
+        let deleted = tx.snapshot().delete_set;
+        for (client, ranges) in delete_set.iter() {
+            for range in ranges.iter() {
+                let mut ids = resolver.update.overlapping(*client, range);
+                ids.extend(resolver.document(*client).overlapping(*client, range));
+                if !resolver.covers(*client, range) {
+                    resolver.unresolved = true;
+                }
+                for id in ids.into_iter().filter(|id| !deleted.is_deleted(id)) {
+                    if let Some(resolved) = resolver.resolve(id) {
+                        roots.entry(resolved.root).or_default();
+                    }
+                }
+            }
+        }
+    }
+
+    let mut clients: Vec<ClientID> = resolver.update.0.keys().copied().collect();
+    clients.sort_unstable();
+
+    let roots = roots
+        .into_iter()
+        .map(|(root, content)| {
+            let mut content: Vec<(&'static str, bool)> = content.into_iter().collect();
+            content.sort_unstable();
+            (root, content)
+        })
+        .collect();
+
+    Ok(UpdateSummary {
+        clients,
+        roots,
+        unresolved: resolver.unresolved,
+    })
+}
+
+// the clients of an update and the root types it modifies, with the kind of
+// content inserted directly into them and whether it is inserted at a key
+pub(crate) struct UpdateSummary {
+    clients: Vec<ClientID>,
+    roots: Vec<(Arc<str>, Vec<(&'static str, bool)>)>,
+    unresolved: bool,
+}
+
+impl UpdateSummary {
+    pub(crate) fn to_hash(&self) -> Result<RHash, Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        let roots = ruby.hash_new();
+        for (root, content) in self.roots.iter() {
+            let entries = ruby.ary_new();
+            for (kind, keyed) in content {
+                let entry = ruby.hash_new();
+                entry.aset(ruby.to_symbol("kind"), ruby.to_symbol(kind))?;
+                entry.aset(ruby.to_symbol("key"), *keyed)?;
+                entries.push(entry)?;
+            }
+            roots.aset(root.as_ref(), entries)?;
+        }
+
+        let summary = ruby.hash_new();
+        summary.aset(
+            ruby.to_symbol("clients"),
+            ruby.ary_from_vec(self.clients.clone()),
+        )?;
+        summary.aset(ruby.to_symbol("roots"), roots)?;
+        summary.aset(ruby.to_symbol("unresolved"), self.unresolved)?;
+
+        Ok(summary)
+    }
+}
+
+enum Parent {
+    Root(Arc<str>),
+    Branch(ID),
+}
+
+struct DecodedBlock {
+    clock: Range<u32>,
+    kind: &'static str,
+    origin: Option<ID>,
+    right_origin: Option<ID>,
+    parent: Option<Parent>,
+    key: Option<Arc<str>>,
+}
+
+type DecodedUpdate = (Vec<(ClientID, Vec<DecodedBlock>)>, DeleteSet);
+
+fn decode_v1(update: &[u8]) -> Result<DecodedUpdate, DecodeError> {
+    decode(&mut DecoderV1::from(update))
+}
+
+fn decode_v2(update: &[u8]) -> Result<DecodedUpdate, DecodeError> {
+    decode(&mut DecoderV2::new(Cursor::new(update))?)
+}
+
+// mirrors Update::decode, which keeps the decoded blocks private to yrs
+fn decode<D: Decoder>(decoder: &mut D) -> Result<DecodedUpdate, DecodeError> {
+    let clients_len: u32 = decoder.read_var()?;
+    let mut clients = Vec::new();
+    for _ in 0..clients_len {
+        let blocks_len: u32 = decoder.read_var()?;
+        let client = decoder.read_client()?;
+        let mut clock: u32 = decoder.read_var()?;
+        let mut blocks = Vec::new();
+        for _ in 0..blocks_len {
+            let block = decode_block(clock, decoder)?;
+            clock = block.clock.end;
+            blocks.push(block);
+        }
+        clients.push((client, blocks));
+    }
+    let delete_set = DeleteSet::decode(decoder)?;
+    Ok((clients, delete_set))
+}
+
+// the parent is only encoded if the block has no origins, otherwise it is
+// inherited from the origin block when the update is integrated
+fn decode_block<D: Decoder>(clock: u32, decoder: &mut D) -> Result<DecodedBlock, DecodeError> {
+    let mut origin = None;
+    let mut right_origin = None;
+    let (kind, len, parent, key) = match decoder.read_info()? {
+        BLOCK_SKIP_REF_NUMBER => ("skip", decoder.read_var()?, None, None),
+        BLOCK_GC_REF_NUMBER => ("gc", decoder.read_len()?, None, None),
+        info => {
+            let cant_copy_parent_info = info & (HAS_ORIGIN | HAS_RIGHT_ORIGIN) == 0;
+            if info & HAS_ORIGIN != 0 {
+                origin = Some(decoder.read_left_id()?);
+            }
+            if info & HAS_RIGHT_ORIGIN != 0 {
+                right_origin = Some(decoder.read_right_id()?);
+            }
+            let parent = if !cant_copy_parent_info {
+                None
+            } else if decoder.read_parent_info()? {
+                Some(Parent::Root(decoder.read_string()?.into()))
+            } else {
+                Some(Parent::Branch(decoder.read_left_id()?))
+            };
+            let key = if cant_copy_parent_info && info & HAS_PARENT_SUB != 0 {
+                Some(decoder.read_string()?.into())
+            } else {
+                None
+            };
+            let content = ItemContent::decode(decoder, info)?;
+            (
+                content_kind(&content)?,
+                content.len(OffsetKind::Utf16),
+                parent,
+                key,
+            )
+        }
+    };
+    let end = clock.checked_add(len).ok_or(DecodeError::UnexpectedValue)?;
+
+    Ok(DecodedBlock {
+        clock: clock..end,
+        kind,
+        origin,
+        right_origin,
+        parent,
+        key,
+    })
+}
+
+fn content_kind(content: &ItemContent) -> Result<&'static str, DecodeError> {
+    match content.get_ref_number() {
+        BLOCK_ITEM_ANY_REF_NUMBER => Ok("any"),
+        BLOCK_ITEM_BINARY_REF_NUMBER => Ok("binary"),
+        BLOCK_ITEM_DELETED_REF_NUMBER => Ok("deleted"),
+        BLOCK_ITEM_DOC_REF_NUMBER => Ok("doc"),
+        BLOCK_ITEM_EMBED_REF_NUMBER => Ok("embed"),
+        BLOCK_ITEM_FORMAT_REF_NUMBER => Ok("format"),
+        BLOCK_ITEM_JSON_REF_NUMBER => Ok("json"),
+        BLOCK_ITEM_MOVE_REF_NUMBER => Ok("move"),
+        BLOCK_ITEM_STRING_REF_NUMBER => Ok("string"),
+        BLOCK_ITEM_TYPE_REF_NUMBER => Ok("type"),
+        _ => Err(DecodeError::UnexpectedValue),
+    }
+}
+
+#[derive(Clone)]
+struct Resolved {
+    root: Arc<str>,
+    direct: bool,
+    keyed: bool,
+}
+
+enum Link {
+    Root(Arc<str>, bool),
+    Branch(ID),
+    Origin(ID),
+    Unknown,
+}
+
+impl DecodedBlock {
+    fn link(&self) -> Link {
+        match (&self.parent, self.origin.or(self.right_origin)) {
+            (Some(Parent::Root(name)), _) => Link::Root(name.clone(), self.key.is_some()),
+            (Some(Parent::Branch(id)), _) => Link::Branch(*id),
+            (None, Some(origin)) => Link::Origin(origin),
+            (None, None) => Link::Unknown,
+        }
+    }
+}
+
+// skipped ranges carry no information and are left out
+struct BlockIndex(HashMap<ClientID, Vec<DecodedBlock>>);
+
+impl BlockIndex {
+    fn new(clients: Vec<(ClientID, Vec<DecodedBlock>)>) -> Self {
+        let mut index: HashMap<ClientID, Vec<DecodedBlock>> = HashMap::new();
+        for (client, blocks) in clients {
+            index
+                .entry(client)
+                .or_default()
+                .extend(blocks.into_iter().filter(|block| block.kind != "skip"));
+        }
+        index.retain(|_, blocks| !blocks.is_empty());
+        for blocks in index.values_mut() {
+            blocks.sort_by_key(|block| block.clock.start);
+        }
+        BlockIndex(index)
+    }
+
+    fn find(&self, id: &ID) -> Option<&DecodedBlock> {
+        let blocks = self.0.get(&id.client)?;
+        let index = blocks.partition_point(|block| block.clock.end <= id.clock);
+        blocks
+            .get(index)
+            .filter(|block| block.clock.contains(&id.clock))
+    }
+
+    fn overlapping(&self, client: ClientID, range: &Range<u32>) -> Vec<ID> {
+        self.ranges(client, range)
+            .into_iter()
+            .map(|clock| ID::new(client, clock.start))
+            .collect()
+    }
+
+    // clock ranges of the blocks overlapping the range, limited to the range
+    fn ranges(&self, client: ClientID, range: &Range<u32>) -> Vec<Range<u32>> {
+        let blocks = match self.0.get(&client) {
+            Some(blocks) => blocks,
+            None => return Vec::new(),
+        };
+        let index = blocks.partition_point(|block| block.clock.end <= range.start);
+        blocks[index..]
+            .iter()
+            .take_while(|block| block.clock.start < range.end)
+            .map(|block| block.clock.start.max(range.start)..block.clock.end.min(range.end))
+            .collect()
+    }
+}
+
+struct RootResolver<'a, T: ReadTxn> {
+    tx: &'a T,
+    state: StateVector,
+    update: BlockIndex,
+    document: BlockIndex,
+    loaded: HashSet<ClientID>,
+    resolved: HashMap<ID, Option<Resolved>>,
+    unresolved: bool,
+}
+
+impl<'a, T: ReadTxn> RootResolver<'a, T> {
+    // blocks of the document are only encoded for the clients blocks refer to,
+    // every other client is excluded by passing its current clock
+    fn document(&mut self, client: ClientID) -> &BlockIndex {
+        if self.loaded.insert(client) && self.state.contains_client(&client) {
+            let mut state = StateVector::default();
+            for (other, clock) in self.state.iter().filter(|(other, _)| **other != client) {
+                state.set_max(*other, *clock);
+            }
+            let update = self.tx.encode_state_as_update_v1(&state);
+            if let Ok((clients, _)) = decode_v1(&update) {
+                self.document.0.extend(BlockIndex::new(clients).0);
+            }
+        }
+        &self.document
+    }
+
+    fn lookup(&mut self, id: &ID) -> Option<(ID, Link)> {
+        let block = match self.update.find(id) {
+            Some(block) => block,
+            None => self.document(id.client).find(id)?,
+        };
+        Some((ID::new(id.client, block.clock.start), block.link()))
+    }
+
+    // whether every clock of the range belongs to a block of the update or of
+    // the document
+    fn covers(&mut self, client: ClientID, range: &Range<u32>) -> bool {
+        let mut ranges = self.update.ranges(client, range);
+        ranges.extend(self.document(client).ranges(client, range));
+        ranges.sort_by_key(|range| range.start);
+        coalesce(ranges.into_iter())
+            .iter()
+            .any(|covered| covered.start <= range.start && covered.end >= range.end)
+    }
+
+    // follows parents and origins until a block with a named parent is found,
+    // all blocks on the way are remembered to resolve long chains only once.
+    // Garbage collected blocks have no parent, content positioned next to
+    // them is discarded when it is integrated.
+    fn resolve(&mut self, id: ID) -> Option<Resolved> {
+        let mut segments: Vec<Vec<ID>> = vec![Vec::new()];
+        let mut visited = HashSet::new();
+        let mut next = id;
+        let result = loop {
+            let (start, link) = match self.lookup(&next) {
+                Some(found) => found,
+                None => {
+                    self.unresolved = true;
+                    break None;
+                }
+            };
+            if let Some(resolved) = self.resolved.get(&start) {
+                break resolved.clone();
+            }
+            // malicious updates could contain cyclic origins
+            if !visited.insert(start) {
+                self.unresolved = true;
+                break None;
+            }
+            if let Some(segment) = segments.last_mut() {
+                segment.push(start);
+            }
+            match link {
+                Link::Root(root, keyed) => {
+                    break Some(Resolved {
+                        root,
+                        direct: true,
+                        keyed,
+                    })
+                }
+                Link::Branch(parent) => {
+                    segments.push(Vec::new());
+                    next = parent;
+                }
+                Link::Origin(origin) => next = origin,
+                Link::Unknown => break None,
+            }
+        };
+
+        // blocks visited before reaching the item of a nested type belong to
+        // that nested type
+        let nested = result.clone().map(|resolved| Resolved {
+            direct: false,
+            keyed: false,
+            ..resolved
+        });
+        let last = segments.len() - 1;
+        for (index, segment) in segments.into_iter().enumerate() {
+            let resolved = if index == last { &result } else { &nested };
+            for start in segment {
+                self.resolved.insert(start, resolved.clone());
+            }
+        }
+
+        if last == 0 {
+            result
+        } else {
+            nested
+        }
+    }
+}
+
+// yrs keeps blocks with missing dependencies, and deletions of blocks it does
+// not know yet, in the store of a document without exposing them. Besides,
+// yrs drops its pending blocks when an update that fills the gap in front of
+// them is integrated completely. Whatever the document has not integrated is
+// therefore tracked here as well, and applied again once blocks of the clients
+// it waits for arrived. Updates that have been integrated completely, which is
+// the common case, are told apart by their state vector and are not tracked.
+#[derive(Default)]
+pub(crate) struct PendingUpdates(Option<Pending>);
+
+struct Pending {
+    update: Vec<u8>,
+    // clocks of the clients the update waits for, when it has been tracked
+    waiting: StateVector,
+}
+
+impl PendingUpdates {
+    // expects the state vector of the update, which has already been applied
+    pub(crate) fn track(
+        &mut self,
+        tx: &mut TransactionMut,
+        update: &[u8],
+        v2: bool,
+        expected: &StateVector,
+    ) {
+        let state = tx.state_vector();
+        let integrated = expected
+            .iter()
+            .all(|(client, clock)| state.get(client) >= *clock);
+        let arrived = self.0.as_ref().is_some_and(|pending| {
+            pending
+                .waiting
+                .iter()
+                .any(|(client, clock)| state.get(client) > *clock)
+        });
+        if integrated && !arrived && !has_pending_deletions(update, v2, &state) {
+            return;
+        }
+
+        let update = if v2 {
+            Update::decode_v2(update).map(|update| update.encode_v1())
+        } else {
+            Ok(update.to_vec())
+        };
+        let merged = match (self.0.take(), update) {
+            (Some(pending), Ok(update)) => {
+                if arrived {
+                    if let Ok(pending) = Update::decode_v1(&pending.update) {
+                        tx.apply_update(pending);
+                    }
+                }
+                merge_updates_v1(&[&pending.update, &update])
+            }
+            (Some(pending), Err(_)) => Ok(pending.update),
+            (None, update) => update,
+        };
+        if let Ok(merged) = merged {
+            self.0 = Pending::remaining(&merged, &tx.state_vector());
+        }
+    }
+
+    pub(crate) fn describe<T: ReadTxn>(&self, tx: &T) -> Result<Option<RHash>, Error> {
+        let state = tx.state_vector();
+        match self
+            .0
+            .as_ref()
+            .and_then(|pending| PendingState::decode(&pending.update, &state))
+        {
+            Some(pending) if !pending.is_empty() => pending.to_hash(&state).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Pending {
+    fn remaining(update: &[u8], state: &StateVector) -> Option<Self> {
+        let update = diff_updates_v1(update, &state.encode_v1()).ok()?;
+        let pending = PendingState::decode(&update, state)?;
+        if pending.is_empty() {
+            return None;
+        }
+        let mut waiting = StateVector::default();
+        for (client, _, missing) in pending.clients(state) {
+            if !missing.is_empty() {
+                waiting.set_max(client, state.get(&client));
+            }
+        }
+        Some(Pending { update, waiting })
+    }
+}
+
+// deletions are kept by yrs as long as the deleted blocks are unknown, an
+// update without deletions ends with the length (zero) of its delete set
+fn has_pending_deletions(update: &[u8], v2: bool, state: &StateVector) -> bool {
+    if !v2 && update.last() == Some(&0) {
+        return false;
+    }
+    let decoded = if v2 {
+        decode_v2(update)
+    } else {
+        decode_v1(update)
+    };
+    decoded.is_ok_and(|(_, delete_set)| {
+        delete_set
+            .iter()
+            .any(|(client, ranges)| ranges.iter().any(|range| range.end > state.get(client)))
+    })
+}
+
+// a client, the clock ranges of its pending blocks and the ranges missing to
+// integrate them
+type PendingClient = (ClientID, Vec<Range<u32>>, Vec<Range<u32>>);
+
+// the blocks and deletions of an update which are above the clocks of the
+// document, all others have been integrated
+struct PendingState {
+    blocks: HashMap<ClientID, Vec<DecodedBlock>>,
+    delete_set: DeleteSet,
+}
+
+impl PendingState {
+    fn decode(update: &[u8], state: &StateVector) -> Option<Self> {
+        // yrs already decoded the update successfully, this only fails for
+        // content yrs does not integrate either
+        let (clients, deleted) = decode_v1(update).ok()?;
+
+        let mut blocks: HashMap<ClientID, Vec<DecodedBlock>> = HashMap::new();
+        for (client, client_blocks) in clients {
+            let clock = state.get(&client);
+            blocks.entry(client).or_default().extend(
+                client_blocks
+                    .into_iter()
+                    .filter(|block| block.kind != "skip" && block.clock.end > clock),
+            );
+        }
+        blocks.retain(|_, blocks| !blocks.is_empty());
+        for blocks in blocks.values_mut() {
+            blocks.sort_by_key(|block| block.clock.start);
+        }
+
+        let mut delete_set = DeleteSet::new();
+        for (client, ranges) in deleted.iter() {
+            let clock = state.get(client);
+            for range in ranges.iter().filter(|range| range.end > clock) {
+                let start = range.start.max(clock);
+                delete_set.insert(ID::new(*client, start), range.end - start);
+            }
+        }
+        delete_set.squash();
+
+        Some(PendingState { blocks, delete_set })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.blocks.is_empty() && self.delete_set.is_empty()
+    }
+
+    fn contains(&self, id: &ID) -> bool {
+        self.blocks
+            .get(&id.client)
+            .is_some_and(|blocks| blocks.iter().any(|block| block.clock.contains(&id.clock)))
+    }
+
+    fn clients(&self, state: &StateVector) -> Vec<PendingClient> {
+        let mut required: HashMap<ClientID, u32> = HashMap::new();
+        for blocks in self.blocks.values() {
+            for id in blocks.iter().flat_map(DecodedBlock::dependencies) {
+                if id.clock >= state.get(&id.client) && !self.contains(&id) {
+                    let end = required.entry(id.client).or_default();
+                    *end = (*end).max(id.clock + 1);
+                }
+            }
+        }
+        // deleted blocks have to be known to apply the deletion
+        for (client, ranges) in self.delete_set.iter() {
+            if let Some(range) = ranges.iter().next_back() {
+                let end = required.entry(*client).or_default();
+                *end = (*end).max(range.end);
+            }
+        }
+
+        let mut clients: Vec<ClientID> =
+            self.blocks.keys().chain(required.keys()).copied().collect();
+        clients.sort_unstable();
+        clients.dedup();
+
+        clients
+            .into_iter()
+            .map(|client| {
+                let ranges = coalesce(
+                    self.blocks
+                        .get(&client)
+                        .into_iter()
+                        .flatten()
+                        .map(|block| block.clock.clone()),
+                );
+                // gaps in front of pending blocks are missing, as well as the
+                // blocks they depend on or delete
+                let limit = ranges
+                    .last()
+                    .map(|range| range.start)
+                    .unwrap_or_default()
+                    .max(required.get(&client).copied().unwrap_or_default());
+                let mut clock = state.get(&client);
+                let mut gaps = Vec::new();
+                for range in ranges.iter() {
+                    if range.start > clock {
+                        gaps.push(clock..range.start);
+                    }
+                    clock = clock.max(range.end);
+                }
+                if limit > clock {
+                    gaps.push(clock..limit);
+                }
+                (client, ranges, gaps)
+            })
+            .collect()
+    }
+
+    fn to_hash(&self, state: &StateVector) -> Result<RHash, Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        let structs = ruby.hash_new();
+        let missing = ruby.hash_new();
+        for (client, ranges, gaps) in self.clients(state) {
+            if !ranges.is_empty() {
+                structs.aset(client, ranges_to_array(ranges.into_iter())?)?;
+            }
+            if !gaps.is_empty() {
+                missing.aset(client, ranges_to_array(gaps.into_iter())?)?;
+            }
+        }
+
+        let pending = ruby.hash_new();
+        pending.aset(ruby.to_symbol("structs"), structs)?;
+        pending.aset(ruby.to_symbol("missing"), missing)?;
+        pending.aset(
+            ruby.to_symbol("delete_set"),
+            delete_set_to_hash(&self.delete_set)?,
+        )?;
+
+        Ok(pending)
+    }
+}
+
+impl DecodedBlock {
+    // blocks are only integrated once their origins and parent are known
+    fn dependencies(&self) -> impl Iterator<Item = ID> {
+        let parent = match self.parent {
+            Some(Parent::Branch(id)) => Some(id),
+            _ => None,
+        };
+        [self.origin, self.right_origin, parent]
+            .into_iter()
+            .flatten()
+    }
+}
+
+// merges overlapping and adjacent ranges, which have to be sorted by start
+fn coalesce(ranges: impl Iterator<Item = Range<u32>>) -> Vec<Range<u32>> {
+    let mut coalesced: Vec<Range<u32>> = Vec::new();
+    for range in ranges {
+        match coalesced.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => coalesced.push(range),
+        }
+    }
+    coalesced
+}
+
+fn decoded_to_hash(decoded: Result<DecodedUpdate, DecodeError>) -> Result<RHash, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let (clients, delete_set) =
+        decoded.map_err(|error| decode_error("cannot decode update", error))?;
+
+    let blocks = ruby.hash_new();
+    for (client, decoded_blocks) in clients {
+        let client_blocks = ruby.ary_new();
+        for block in decoded_blocks {
+            let hash = ruby.hash_new();
+            hash.aset(
+                ruby.to_symbol("clock"),
+                ruby.range_new(block.clock.start, block.clock.end, true)?,
+            )?;
+            hash.aset(ruby.to_symbol("kind"), ruby.to_symbol(block.kind))?;
+            match block.parent {
+                Some(Parent::Root(name)) => hash.aset(ruby.to_symbol("parent"), name.as_ref())?,
+                Some(Parent::Branch(id)) => {
+                    let parent = ruby.hash_new();
+                    parent.aset(ruby.to_symbol("client"), id.client)?;
+                    parent.aset(ruby.to_symbol("clock"), id.clock)?;
+                    hash.aset(ruby.to_symbol("parent"), parent)?
+                }
+                None => hash.aset(ruby.to_symbol("parent"), ruby.qnil())?,
+            }
+            hash.aset(ruby.to_symbol("key"), block.key.as_deref())?;
+            client_blocks.push(hash)?;
+        }
+        blocks.aset(client, client_blocks)?;
+    }
+
+    let payload = ruby.hash_new();
+    payload.aset(ruby.to_symbol("blocks"), blocks)?;
+    payload.aset(
+        ruby.to_symbol("delete_set"),
+        delete_set_to_hash(&delete_set)?,
+    )?;
+
+    Ok(payload)
+}
+
+fn delete_set_to_hash(delete_set: &DeleteSet) -> Result<RHash, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let deleted = ruby.hash_new();
+    for (client, ranges) in delete_set.iter() {
+        deleted.aset(*client, ranges_to_array(ranges.iter().cloned())?)?;
+    }
+    Ok(deleted)
+}
+
+fn ranges_to_array(ranges: impl Iterator<Item = Range<u32>>) -> Result<RArray, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let clock_ranges = ruby.ary_new();
+    for range in ranges {
+        clock_ranges.push(ruby.range_new(range.start, range.end, true)?)?;
+    }
+    Ok(clock_ranges)
+}
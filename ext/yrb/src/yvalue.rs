@@ -1,14 +1,15 @@
-use crate::{YText, YXmlElement, YXmlText};
+use crate::utils::indifferent_hash_key;
+use crate::yerror::type_error;
+use crate::{YDoc, YText, YXmlElement, YXmlFragment, YXmlText};
 use magnus::r_hash::ForEach::Continue;
 use magnus::value::{Qnil, ReprValue};
-use magnus::{Float, Integer, IntoValue, RArray, RHash, RString, Ruby, Symbol, Value};
+use magnus::{Error, Float, Integer, IntoValue, RArray, RHash, RString, Ruby, Symbol, Value};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::sync::Arc;
-use yrs::types::Value as YrsValue;
 use yrs::{
-    Any, Array, Map, TextRef as YrsText, Transact, XmlElementRef as YrsXmlElement,
-    XmlTextRef as YrsXmlText,
+    Any, Doc as YrsDoc, TextRef as YrsText, XmlElementRef as YrsXmlElement,
+    XmlFragmentRef as YrsXmlFragment, XmlTextRef as YrsXmlText,
 };
 
 pub(crate) struct YValue(pub(crate) RefCell<Value>);
@@ -82,6 +83,13 @@ impl From<Vec<u8>> for YValue {
     }
 }
 
+impl From<YrsDoc> for YValue {
+    fn from(value: YrsDoc) -> Self {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        YValue(RefCell::from(YDoc::from(value).into_value_with(&ruby)))
+    }
+}
+
 impl From<YrsText> for YValue {
     fn from(value: YrsText) -> Self {
         let ruby = unsafe { Ruby::get_unchecked() };
@@ -100,6 +108,15 @@ impl From<YrsXmlElement> for YValue {
     }
 }
 
+impl From<YrsXmlFragment> for YValue {
+    fn from(value: YrsXmlFragment) -> Self {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        YValue(RefCell::from(
+            YXmlFragment(RefCell::from(value)).into_value_with(&ruby),
+        ))
+    }
+}
+
 impl From<YrsXmlText> for YValue {
     fn from(value: YrsXmlText) -> Self {
         let ruby = unsafe { Ruby::get_unchecked() };
@@ -164,93 +181,57 @@ impl From<Any> for YValue {
     }
 }
 
-impl From<YrsValue> for YValue {
-    fn from(value: YrsValue) -> Self {
-        let ruby = unsafe { Ruby::get_unchecked() };
-        match value {
-            YrsValue::Any(val) => YValue::from(val),
-            YrsValue::YText(text) => YValue::from(text),
-            YrsValue::YXmlElement(el) => YValue::from(el),
-            YrsValue::YXmlText(text) => YValue::from(text),
-            YrsValue::YArray(val) => {
-                let tx = val.transact();
-                let arr = ruby.ary_new();
-                for item in val.iter(&tx) {
-                    let val = YValue::from(item.clone());
-                    let val = *val.0.borrow();
-                    arr.push(val).expect("cannot push item event to array");
-                }
-                YValue::from(arr)
-            }
-            YrsValue::YMap(val) => {
-                let tx = val.transact();
-                let hash = ruby.hash_new();
-                for (key, value) in val.iter(&tx) {
-                    let val = YValue::from(value);
-                    let val = val.0.into_inner();
-                    hash.aset(key, val).expect("cannot insert into hash");
-                }
-                YValue::from(hash)
-            }
-            v => panic!("cannot map complex yrs values to yvalue: {:?}", v),
-        }
-    }
-}
+impl TryFrom<YValue> for Any {
+    type Error = Error;
 
-impl From<YValue> for Any {
-    fn from(val: YValue) -> Self {
+    fn try_from(val: YValue) -> Result<Self, Self::Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
         let value = val.0.into_inner();
         if value.is_nil() {
-            Any::Null
-        } else if value.is_kind_of(ruby.class_float()) {
-            let f = Float::from_value(value).unwrap();
-            Any::Number(f.to_f64())
-        } else if value.is_kind_of(ruby.class_integer()) {
-            let i = Integer::from_value(value).unwrap();
-            Any::BigInt(i.to_i64().unwrap())
-        } else if value.is_kind_of(ruby.class_symbol()) {
-            let s = Symbol::from_value(value).unwrap();
-            Any::String(Arc::from(s.name().unwrap()))
+            Ok(Any::Null)
+        } else if let Some(f) = Float::from_value(value) {
+            Ok(Any::Number(f.to_f64()))
+        } else if let Some(i) = Integer::from_value(value) {
+            i.to_i64().map(Any::BigInt).map_err(|_| {
+                type_error(format!(
+                    "integer {} does not fit into a signed 64-bit integer",
+                    i
+                ))
+            })
+        } else if let Some(s) = Symbol::from_value(value) {
+            Ok(Any::String(Arc::from(s.name()?)))
         } else if value.is_kind_of(ruby.class_true_class()) {
-            Any::Bool(true)
+            Ok(Any::Bool(true))
         } else if value.is_kind_of(ruby.class_false_class()) {
-            Any::Bool(false)
-        } else if value.is_kind_of(ruby.class_string()) {
-            let s = RString::from_value(value).unwrap();
-            unsafe { Any::String(Arc::from(s.as_str().unwrap().to_string())) }
-        } else if value.is_kind_of(ruby.class_array()) {
-            let arr = RArray::from_value(value).unwrap();
+            Ok(Any::Bool(false))
+        } else if let Some(s) = RString::from_value(value) {
+            Ok(Any::String(Arc::from(s.to_string().map_err(|_| {
+                type_error("string can not be encoded as UTF-8")
+            })?)))
+        } else if let Some(arr) = RArray::from_value(value) {
             let items = arr
                 .into_iter()
-                .map(|item| {
-                    let yvalue = YValue::from(item);
-                    Any::from(yvalue)
-                })
-                .collect::<Vec<Any>>();
-            Any::Array(Arc::from(items))
-        } else if value.is_kind_of(ruby.class_hash()) {
-            let map = RHash::from_value(value).unwrap();
+                .map(|item| Any::try_from(YValue::from(item)))
+                .collect::<Result<Vec<Any>, Error>>()?;
+            Ok(Any::Array(Arc::from(items)))
+        } else if let Some(map) = RHash::from_value(value) {
             let mut m: HashMap<String, Any> = HashMap::new();
 
             // we need to map symbol keys to strings, because we can't store
             // symbols in any of the yrs data structures
             map.foreach(|key: Value, val: Value| {
-                let k = if let Some(converted_key) = Symbol::from_value(key) {
-                    converted_key.to_string()
-                } else {
-                    let converted_key = RString::from_value(key).unwrap();
-                    let result = converted_key.to_string();
-                    result.unwrap()
-                };
-                m.insert(k, Any::from(YValue::from(val)));
+                let k = indifferent_hash_key(key)
+                    .ok_or_else(|| type_error("hash keys must be of type Symbol or String"))?;
+                m.insert(k, Any::try_from(YValue::from(val))?);
                 Ok(Continue)
-            })
-            .expect("cannot map key/value pair");
+            })?;
 
-            Any::Map(Arc::from(m))
+            Ok(Any::Map(Arc::from(m)))
         } else {
-            Any::Undefined
+            Err(type_error(format!(
+                "cannot store value of class {}",
+                value.class()
+            )))
         }
     }
 }
@@ -1,4 +1,7 @@
-use crate::yvalue::YValue;
+use crate::utils::convert_yvalue_to_ruby_value;
+use crate::yerror::{check_index, check_range};
+use crate::yevent::observe_deep;
+use crate::yorigin::{call_observer, keep_observer_error};
 use crate::yxml_fragment::YXmlFragment;
 use crate::yxml_text::YXmlText;
 use crate::YTransaction;
@@ -7,8 +10,8 @@ use magnus::{Error, IntoValue, RArray, RHash, Ruby, Value};
 use std::cell::RefCell;
 use yrs::types::Change;
 use yrs::{
-    GetString, Observable, Xml, XmlElementPrelim, XmlElementRef, XmlFragment, XmlNode,
-    XmlTextPrelim,
+    DeepObservable, GetString, Observable, Xml, XmlElementPrelim, XmlElementRef, XmlFragment,
+    XmlNode, XmlTextPrelim,
 };
 
 #[magnus::wrap(class = "Y::XMLElement")]
@@ -18,92 +21,106 @@ pub(crate) struct YXmlElement(pub(crate) RefCell<XmlElementRef>);
 unsafe impl Send for YXmlElement {}
 
 impl YXmlElement {
-    pub(crate) fn yxml_element_attributes(&self, transaction: &YTransaction) -> RHash {
+    pub(crate) fn yxml_element_attributes(
+        &self,
+        transaction: &YTransaction,
+    ) -> Result<RHash, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
         let hash = ruby.hash_new();
         for (k, v) in self.0.borrow().attributes(tx) {
             hash.aset(k, v).expect("cannot insert into hash");
         }
-        hash
+        Ok(hash)
     }
-    pub(crate) fn yxml_element_first_child(&self, transaction: &YTransaction) -> Option<Value> {
+    pub(crate) fn yxml_element_first_child(
+        &self,
+        transaction: &YTransaction,
+    ) -> Result<Option<Value>, Error> {
         self.yxml_element_get(transaction, 0)
     }
-    pub(crate) fn yxml_element_get(&self, transaction: &YTransaction, index: u32) -> Option<Value> {
+    pub(crate) fn yxml_element_get(
+        &self,
+        transaction: &YTransaction,
+        index: u32,
+    ) -> Result<Option<Value>, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
-        self.0.borrow().get(tx, index).map(|node| match node {
+        Ok(self.0.borrow().get(tx, index).map(|node| match node {
             XmlNode::Element(element) => YXmlElement::from(element).into_value_with(&ruby),
             XmlNode::Fragment(fragment) => YXmlFragment::from(fragment).into_value_with(&ruby),
             XmlNode::Text(text) => YXmlText::from(text).into_value_with(&ruby),
-        })
+        }))
     }
     pub(crate) fn yxml_element_get_attribute(
         &self,
         transaction: &YTransaction,
         name: String,
-    ) -> Option<String> {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+    ) -> Result<Option<String>, Error> {
+        let tx = &transaction.read_transaction()?;
 
-        self.0.borrow().get_attribute(tx, name.as_str())
+        Ok(self.0.borrow().get_attribute(tx, name.as_str()))
     }
     pub(crate) fn yxml_element_insert_attribute(
         &self,
         transaction: &YTransaction,
         name: String,
         value: String,
-    ) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+
+        self.0.borrow_mut().insert_attribute(tx, name, value);
 
-        self.0.borrow_mut().insert_attribute(tx, name, value)
+        Ok(())
     }
     pub(crate) fn yxml_element_insert_element(
         &self,
         transaction: &YTransaction,
         index: u32,
         tag: String,
-    ) -> YXmlElement {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<YXmlElement, Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_index(index, self.0.borrow().len(tx))?;
 
         let node = XmlElementPrelim::empty(tag);
-        YXmlElement::from(self.0.borrow_mut().insert(tx, index, node))
+        Ok(YXmlElement::from(
+            self.0.borrow_mut().insert(tx, index, node),
+        ))
     }
     pub(crate) fn yxml_element_insert_text(
         &self,
         transaction: &YTransaction,
         index: u32,
         content: String,
-    ) -> YXmlText {
+    ) -> Result<YXmlText, Error> {
         let text = XmlTextPrelim::new(content.as_str());
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_index(index, self.0.borrow().len(tx))?;
 
-        YXmlText::from(self.0.borrow_mut().insert(tx, index, text))
+        Ok(YXmlText::from(self.0.borrow_mut().insert(tx, index, text)))
     }
-    pub(crate) fn yxml_element_len(&self, transaction: &YTransaction) -> u32 {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    pub(crate) fn yxml_element_len(&self, transaction: &YTransaction) -> Result<u32, Error> {
+        let tx = &transaction.read_transaction()?;
 
-        self.0.borrow().len(tx)
+        Ok(self.0.borrow().len(tx))
     }
-    pub(crate) fn yxml_element_next_sibling(&self, transaction: &YTransaction) -> Option<Value> {
+    pub(crate) fn yxml_element_next_sibling(
+        &self,
+        transaction: &YTransaction,
+    ) -> Result<Option<Value>, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
-        self.0.borrow().siblings(tx).next().map(|item| match item {
+        Ok(self.0.borrow().siblings(tx).next().map(|item| match item {
             XmlNode::Element(el) => YXmlElement::from(el).into_value_with(&ruby),
             XmlNode::Fragment(fragment) => YXmlFragment::from(fragment).into_value_with(&ruby),
             XmlNode::Text(text) => YXmlText::from(text).into_value_with(&ruby),
-        })
+        }))
     }
     pub(crate) fn yxml_element_observe(&self, block: Proc) -> Result<u32, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
@@ -124,7 +141,8 @@ impl YXmlElement {
                         Change::Added(v) => {
                             let values = ruby.ary_new();
                             for value in v.iter() {
-                                let value = YValue::from(value.clone());
+                                let value =
+                                    convert_yvalue_to_ruby_value(value.clone(), transaction);
                                 let value = *value.0.borrow();
                                 values.push(value).expect("cannot push value to array");
                             }
@@ -161,13 +179,14 @@ impl YXmlElement {
                     }
                 }
 
-                block
-                    .call::<(RArray,), Value>((changes,))
-                    .expect("cannot call block");
+                keep_observer_error(call_observer(block, changes, transaction));
             });
 
         Ok(subscription_id.into())
     }
+    pub(crate) fn yxml_element_observe_deep(&self, block: Proc, document: Value) -> u32 {
+        observe_deep(&mut *self.0.borrow_mut(), block, document)
+    }
     pub(crate) fn yxml_element_parent(&self) -> Option<Value> {
         let ruby = unsafe { Ruby::get_unchecked() };
         self.0.borrow().parent().map(|item| match item {
@@ -176,12 +195,15 @@ impl YXmlElement {
             XmlNode::Text(text) => YXmlText::from(text).into_value_with(&ruby),
         })
     }
-    pub(crate) fn yxml_element_prev_sibling(&self, transaction: &YTransaction) -> Option<Value> {
+    pub(crate) fn yxml_element_prev_sibling(
+        &self,
+        transaction: &YTransaction,
+    ) -> Result<Option<Value>, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
-        self.0
+        Ok(self
+            .0
             .borrow()
             .siblings(tx)
             .next_back()
@@ -189,73 +211,84 @@ impl YXmlElement {
                 XmlNode::Element(el) => YXmlElement::from(el).into_value_with(&ruby),
                 XmlNode::Fragment(fragment) => YXmlFragment::from(fragment).into_value_with(&ruby),
                 XmlNode::Text(text) => YXmlText::from(text).into_value_with(&ruby),
-            })
+            }))
     }
     pub(crate) fn yxml_element_push_element_back(
         &self,
         transaction: &YTransaction,
         tag: String,
-    ) -> YXmlElement {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<YXmlElement, Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
         let node = XmlElementPrelim::empty(tag);
-        YXmlElement::from(self.0.borrow_mut().push_back(tx, node))
+        Ok(YXmlElement::from(self.0.borrow_mut().push_back(tx, node)))
     }
     pub(crate) fn yxml_element_push_element_front(
         &self,
         transaction: &YTransaction,
         tag: String,
-    ) -> YXmlElement {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<YXmlElement, Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
         let node = XmlElementPrelim::empty(tag);
-        YXmlElement::from(self.0.borrow_mut().push_front(tx, node))
+        Ok(YXmlElement::from(self.0.borrow_mut().push_front(tx, node)))
     }
     pub(crate) fn yxml_element_push_text_back(
         &self,
         transaction: &YTransaction,
         content: String,
-    ) -> YXmlText {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<YXmlText, Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
         let text = XmlTextPrelim::new(content.as_str());
-        YXmlText::from(self.0.borrow_mut().push_back(tx, text))
+        Ok(YXmlText::from(self.0.borrow_mut().push_back(tx, text)))
     }
     pub(crate) fn yxml_element_push_text_front(
         &self,
         transaction: &YTransaction,
         content: String,
-    ) -> YXmlText {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<YXmlText, Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
         let text = XmlTextPrelim::new(content.as_str());
-        YXmlText::from(self.0.borrow_mut().push_front(tx, text))
+        Ok(YXmlText::from(self.0.borrow_mut().push_front(tx, text)))
     }
-    pub(crate) fn yxml_element_remove_attribute(&self, transaction: &YTransaction, name: String) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    pub(crate) fn yxml_element_remove_attribute(
+        &self,
+        transaction: &YTransaction,
+        name: String,
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+
+        self.0.borrow_mut().remove_attribute(tx, &name);
 
-        self.0.borrow_mut().remove_attribute(tx, &name)
+        Ok(())
     }
     pub(crate) fn yxml_element_remove_range(
         &self,
         transaction: &YTransaction,
         index: u32,
         length: u32,
-    ) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_range(index, length, self.0.borrow().len(tx))?;
+
+        self.0.borrow_mut().remove_range(tx, index, length);
 
-        self.0.borrow_mut().remove_range(tx, index, length)
+        Ok(())
     }
-    pub(crate) fn yxml_element_siblings(&self, transaction: &YTransaction) -> RArray {
+    pub(crate) fn yxml_element_siblings(
+        &self,
+        transaction: &YTransaction,
+    ) -> Result<RArray, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
         let array = ruby.ary_new();
         for item in self.0.borrow().siblings(tx) {
@@ -266,26 +299,27 @@ impl YXmlElement {
             };
             array.push(value).expect("cannot push value to array");
         }
-        array
+        Ok(array)
     }
-    pub(crate) fn yxml_element_size(&self, transaction: &YTransaction) -> u32 {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+    pub(crate) fn yxml_element_size(&self, transaction: &YTransaction) -> Result<u32, Error> {
+        let tx = &transaction.read_transaction()?;
 
-        self.0.borrow().len(tx)
+        Ok(self.0.borrow().len(tx))
     }
     pub(crate) fn yxml_element_tag(&self) -> String {
         self.0.borrow().tag().to_string()
     }
-    pub(crate) fn yxml_element_to_s(&self, transaction: &YTransaction) -> String {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+    pub(crate) fn yxml_element_to_s(&self, transaction: &YTransaction) -> Result<String, Error> {
+        let tx = &transaction.read_transaction()?;
 
-        self.0.borrow().get_string(tx)
+        Ok(self.0.borrow().get_string(tx))
     }
     pub(crate) fn yxml_element_unobserve(&self, subscription_id: u32) {
         self.0.borrow_mut().unobserve(subscription_id);
     }
+    pub(crate) fn yxml_element_unobserve_deep(&self, subscription_id: u32) {
+        self.0.borrow_mut().unobserve_deep(subscription_id);
+    }
 }
 
 impl From<XmlElementRef> for YXmlElement {
@@ -1,9 +1,12 @@
+use crate::yerror::{check_index, check_range};
+use crate::yevent::observe_deep;
 use crate::ytransaction::YTransaction;
 use crate::yxml_element::YXmlElement;
 use crate::yxml_text::YXmlText;
-use magnus::{IntoValue, RArray, Ruby, Value};
+use magnus::block::Proc;
+use magnus::{Error, IntoValue, RArray, Ruby, Value};
 use std::cell::RefCell;
-use yrs::{GetString, XmlElementPrelim, XmlFragment, XmlFragmentRef, XmlNode};
+use yrs::{DeepObservable, GetString, XmlElementPrelim, XmlFragment, XmlFragmentRef, XmlNode};
 
 #[magnus::wrap(class = "Y::XMLFragment")]
 pub(crate) struct YXmlFragment(pub(crate) RefCell<XmlFragmentRef>);
@@ -25,16 +28,15 @@ impl YXmlFragment {
         &self,
         transaction: &YTransaction,
         index: u32,
-    ) -> Option<Value> {
+    ) -> Result<Option<Value>, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
-        self.0.borrow().get(tx, index).map(|node| match node {
+        Ok(self.0.borrow().get(tx, index).map(|node| match node {
             XmlNode::Element(element) => YXmlElement::from(element).into_value_with(&ruby),
             XmlNode::Fragment(fragment) => YXmlFragment::from(fragment).into_value_with(&ruby),
             XmlNode::Text(text) => YXmlText::from(text).into_value_with(&ruby),
-        })
+        }))
     }
 
     pub(crate) fn yxml_fragment_insert(
@@ -42,19 +44,25 @@ impl YXmlFragment {
         transaction: &YTransaction,
         index: u32,
         tag: String,
-    ) -> YXmlElement {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<YXmlElement, Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_index(index, self.0.borrow().len(tx))?;
 
         let node = XmlElementPrelim::empty(tag);
-        YXmlElement::from(self.0.borrow_mut().insert(tx, index, node))
+        Ok(YXmlElement::from(
+            self.0.borrow_mut().insert(tx, index, node),
+        ))
     }
 
-    pub(crate) fn yxml_fragment_len(&self, transaction: &YTransaction) -> u32 {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+    pub(crate) fn yxml_fragment_len(&self, transaction: &YTransaction) -> Result<u32, Error> {
+        let tx = &transaction.read_transaction()?;
+
+        Ok(self.0.borrow().len(tx))
+    }
 
-        self.0.borrow().len(tx)
+    pub(crate) fn yxml_fragment_observe_deep(&self, block: Proc, document: Value) -> u32 {
+        observe_deep(&mut *self.0.borrow_mut(), block, document)
     }
 
     pub(crate) fn yxml_fragment_parent(&self) -> Option<Value> {
@@ -70,24 +78,24 @@ impl YXmlFragment {
         &self,
         transaction: &YTransaction,
         tag: String,
-    ) -> YXmlElement {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<YXmlElement, Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
         let node = XmlElementPrelim::empty(tag);
-        YXmlElement::from(self.0.borrow_mut().push_back(tx, node))
+        Ok(YXmlElement::from(self.0.borrow_mut().push_back(tx, node)))
     }
 
     pub(crate) fn yxml_fragment_push_front(
         &self,
         transaction: &YTransaction,
         tag: String,
-    ) -> YXmlElement {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<YXmlElement, Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
 
         let node = XmlElementPrelim::empty(tag);
-        YXmlElement::from(self.0.borrow_mut().push_front(tx, node))
+        Ok(YXmlElement::from(self.0.borrow_mut().push_front(tx, node)))
     }
 
     pub(crate) fn yxml_fragment_remove_range(
@@ -95,17 +103,22 @@ impl YXmlFragment {
         transaction: &YTransaction,
         index: u32,
         length: u32,
-    ) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_range(index, length, self.0.borrow().len(tx))?;
 
         self.0.borrow_mut().remove_range(tx, index, length);
+
+        Ok(())
     }
 
-    pub(crate) fn yxml_fragment_successors(&self, transaction: &YTransaction) -> RArray {
+    pub(crate) fn yxml_fragment_successors(
+        &self,
+        transaction: &YTransaction,
+    ) -> Result<RArray, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
         let fragment = self.0.borrow();
 
@@ -118,14 +131,17 @@ impl YXmlFragment {
             };
             array.push(value).expect("cannot push value to array");
         }
-        array
+        Ok(array)
     }
 
-    pub(crate) fn yxml_fragment_to_s(&self, transaction: &YTransaction) -> String {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+    pub(crate) fn yxml_fragment_to_s(&self, transaction: &YTransaction) -> Result<String, Error> {
+        let tx = &transaction.read_transaction()?;
+
+        Ok(self.0.borrow().get_string(tx))
+    }
 
-        self.0.borrow().get_string(tx)
+    pub(crate) fn yxml_fragment_unobserve_deep(&self, subscription_id: u32) {
+        self.0.borrow_mut().unobserve_deep(subscription_id);
     }
 }
 
@@ -1,10 +1,14 @@
 use crate::utils::map_rhash_to_attrs;
+use crate::yerror::{check_index, check_range};
+use crate::yevent::observe_deep;
+use crate::ysticky_index::{sticky_index_at, YStickyIndex};
 use crate::yvalue::YValue;
 use crate::yxml_fragment::YXmlFragment;
 use crate::{YTransaction, YXmlElement};
-use magnus::{Error, IntoValue, RHash, Ruby, Value};
+use magnus::block::Proc;
+use magnus::{Error, IntoValue, RHash, Ruby, Symbol, Value};
 use std::cell::RefCell;
-use yrs::{Any, GetString, Text, Xml, XmlNode, XmlTextRef};
+use yrs::{Any, DeepObservable, GetString, Text, Xml, XmlNode, XmlTextRef};
 
 #[magnus::wrap(class = "Y::XMLText")]
 pub(crate) struct YXmlText(pub(crate) RefCell<XmlTextRef>);
@@ -13,16 +17,15 @@ pub(crate) struct YXmlText(pub(crate) RefCell<XmlTextRef>);
 unsafe impl Send for YXmlText {}
 
 impl YXmlText {
-    pub(crate) fn yxml_text_attributes(&self, transaction: &YTransaction) -> RHash {
+    pub(crate) fn yxml_text_attributes(&self, transaction: &YTransaction) -> Result<RHash, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
         let hash = ruby.hash_new();
         for (k, v) in self.0.borrow().attributes(tx) {
             hash.aset(k, v).expect("cannot insert into hash");
         }
-        hash
+        Ok(hash)
     }
     pub(crate) fn yxml_text_format(
         &self,
@@ -31,8 +34,9 @@ impl YXmlText {
         length: u32,
         attrs: RHash,
     ) -> Result<(), Error> {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_range(index, length, self.0.borrow().len(tx))?;
 
         map_rhash_to_attrs(attrs).map(|a| self.0.borrow_mut().format(tx, index, length, a))
     }
@@ -40,28 +44,37 @@ impl YXmlText {
         &self,
         transaction: &YTransaction,
         name: String,
-    ) -> Option<String> {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+    ) -> Result<Option<String>, Error> {
+        let tx = &transaction.read_transaction()?;
 
-        self.0.borrow().get_attribute(tx, name.as_str())
+        Ok(self.0.borrow().get_attribute(tx, name.as_str()))
     }
-    pub(crate) fn yxml_text_insert(&self, transaction: &YTransaction, index: u32, content: String) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    pub(crate) fn yxml_text_insert(
+        &self,
+        transaction: &YTransaction,
+        index: u32,
+        content: String,
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_index(index, self.0.borrow().len(tx))?;
+
+        self.0.borrow_mut().insert(tx, index, content.as_str());
 
-        self.0.borrow_mut().insert(tx, index, content.as_str())
+        Ok(())
     }
     pub(crate) fn yxml_text_insert_attribute(
         &self,
         transaction: &YTransaction,
         name: String,
         value: String,
-    ) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+
+        self.0.borrow_mut().insert_attribute(tx, name, value);
 
-        self.0.borrow_mut().insert_attribute(tx, name, value)
+        Ok(())
     }
     pub(crate) fn yxml_text_insert_embed_with_attributes(
         &self,
@@ -70,11 +83,12 @@ impl YXmlText {
         content: Value,
         attrs: RHash,
     ) -> Result<(), Error> {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_index(index, self.0.borrow().len(tx))?;
 
         let yvalue = YValue::from(content);
-        let avalue = Any::from(yvalue);
+        let avalue = Any::try_from(yvalue)?;
 
         map_rhash_to_attrs(attrs)
             .map(|a| {
@@ -89,13 +103,15 @@ impl YXmlText {
         transaction: &YTransaction,
         index: u32,
         embed: Value,
-    ) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_index(index, self.0.borrow().len(tx))?;
+
+        let avalue = Any::try_from(YValue::from(embed))?;
+        self.0.borrow_mut().insert_embed(tx, index, avalue);
 
-        self.0
-            .borrow_mut()
-            .insert_embed(tx, index, Any::from(YValue::from(embed)));
+        Ok(())
     }
     pub(crate) fn yxml_text_insert_with_attributes(
         &self,
@@ -104,8 +120,9 @@ impl YXmlText {
         content: String,
         attrs: RHash,
     ) -> Result<(), Error> {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_index(index, self.0.borrow().len(tx))?;
 
         map_rhash_to_attrs(attrs).map(|a| {
             self.0
@@ -113,24 +130,28 @@ impl YXmlText {
                 .insert_with_attributes(tx, index, content.as_str(), a);
         })
     }
-    pub(crate) fn yxml_text_length(&self, transaction: &YTransaction) -> u32 {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+    pub(crate) fn yxml_text_length(&self, transaction: &YTransaction) -> Result<u32, Error> {
+        let tx = &transaction.read_transaction()?;
 
-        self.0.borrow().len(tx)
+        Ok(self.0.borrow().len(tx))
     }
-    pub(crate) fn yxml_text_next_sibling(&self, transaction: &YTransaction) -> Option<Value> {
+    pub(crate) fn yxml_text_next_sibling(
+        &self,
+        transaction: &YTransaction,
+    ) -> Result<Option<Value>, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
-        self.0.borrow().siblings(tx).next().map(|item| match item {
+        Ok(self.0.borrow().siblings(tx).next().map(|item| match item {
             XmlNode::Element(el) => YXmlElement(RefCell::from(el)).into_value_with(&ruby),
             XmlNode::Fragment(fragment) => {
                 YXmlFragment(RefCell::from(fragment)).into_value_with(&ruby)
             }
             XmlNode::Text(text) => YXmlText(RefCell::from(text)).into_value_with(&ruby),
-        })
+        }))
+    }
+    pub(crate) fn yxml_text_observe_deep(&self, block: Proc, document: Value) -> u32 {
+        observe_deep(&mut *self.0.borrow_mut(), block, document)
     }
     pub(crate) fn yxml_text_parent(&self) -> Option<Value> {
         let ruby = unsafe { Ruby::get_unchecked() };
@@ -142,12 +163,15 @@ impl YXmlText {
             XmlNode::Text(text) => YXmlText(RefCell::from(text)).into_value_with(&ruby),
         })
     }
-    pub(crate) fn yxml_text_prev_sibling(&self, transaction: &YTransaction) -> Option<Value> {
+    pub(crate) fn yxml_text_prev_sibling(
+        &self,
+        transaction: &YTransaction,
+    ) -> Result<Option<Value>, Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+        let tx = &transaction.read_transaction()?;
 
-        self.0
+        Ok(self
+            .0
             .borrow()
             .siblings(tx)
             .next_back()
@@ -157,30 +181,49 @@ impl YXmlText {
                     YXmlFragment(RefCell::from(fragment)).into_value_with(&ruby)
                 }
                 XmlNode::Text(text) => YXmlText(RefCell::from(text)).into_value_with(&ruby),
-            })
+            }))
     }
-    pub(crate) fn yxml_text_push(&self, transaction: &YTransaction, content: String) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    pub(crate) fn yxml_text_push(
+        &self,
+        transaction: &YTransaction,
+        content: String,
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+
+        self.0.borrow_mut().push(tx, content.as_str());
 
-        self.0.borrow_mut().push(tx, content.as_str())
+        Ok(())
     }
     pub(crate) fn yxml_text_remove_range(
         &self,
         transaction: &YTransaction,
         index: u32,
         length: u32,
-    ) {
-        let mut tx = transaction.transaction();
-        let tx = tx.as_mut().unwrap();
+    ) -> Result<(), Error> {
+        let mut tx = transaction.transaction_mut()?;
+        let tx = &mut *tx;
+        check_range(index, length, self.0.borrow().len(tx))?;
+
+        self.0.borrow_mut().remove_range(tx, index, length);
 
-        self.0.borrow_mut().remove_range(tx, index, length)
+        Ok(())
     }
-    pub(crate) fn yxml_text_to_s(&self, transaction: &YTransaction) -> String {
-        let tx = transaction.transaction();
-        let tx = tx.as_ref().unwrap();
+    pub(crate) fn yxml_text_sticky_index(
+        &self,
+        transaction: &YTransaction,
+        index: u32,
+        assoc: Symbol,
+    ) -> Result<Option<YStickyIndex>, Error> {
+        sticky_index_at(&*self.0.borrow(), transaction, index, assoc)
+    }
+    pub(crate) fn yxml_text_to_s(&self, transaction: &YTransaction) -> Result<String, Error> {
+        let tx = &transaction.read_transaction()?;
 
-        self.0.borrow().get_string(tx)
+        Ok(self.0.borrow().get_string(tx))
+    }
+    pub(crate) fn yxml_text_unobserve_deep(&self, subscription_id: u32) {
+        self.0.borrow_mut().unobserve_deep(subscription_id);
     }
 }
 
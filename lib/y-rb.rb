@@ -13,10 +13,19 @@ require_relative "y/array"
 require_relative "y/awareness"
 require_relative "y/diff"
 require_relative "y/doc"
+require_relative "y/error"
 require_relative "y/map"
+require_relative "y/origin"
+require_relative "y/room"
+require_relative "y/snapshot"
+require_relative "y/sticky_index"
+require_relative "y/sync"
 require_relative "y/text"
 require_relative "y/xml"
 require_relative "y/transaction"
+require_relative "y/undo_manager"
+require_relative "y/update"
+require_relative "y/update_policy"
 require_relative "y/version"
 
 module Y
@@ -38,7 +38,9 @@ module Y
 
     # Retrieves element at position
     #
+    # @param index [Integer]
     # @return [true, false, Float, Integer, String, Array, Hash]
+    # @raise [Y::IndexError] If the index is out of bounds
     def [](index)
       document.current_transaction { |tx| yarray_get(tx, index) }
     end
@@ -46,15 +48,17 @@ module Y
     # Inserts value at position
     #
     # @param index [Integer]
-    # @param value [true, false, Float, Integer, String, Array, Hash]
+    # @param value [true, false, Float, Integer, String, Array, Hash, Y::Doc]
     # @return [void]
+    # @raise [Y::IndexError] If the index is beyond the end of the array
+    # @raise [Y::TypeError] If the value can not be stored
     def []=(index, value)
       document.current_transaction { |tx| yarray_insert(tx, index, value) }
     end
 
     # Adds an element to the end of the array
     #
-    # @param value [true, false, Float, Integer, String, ::Array, Hash]
+    # @param value [true, false, Float, Integer, String, ::Array, Hash, Y::Doc]
     # @return [void]
     def <<(value, *values)
       document.current_transaction do |tx|
@@ -75,6 +79,11 @@ module Y
     #     arr << 1
     #   end
     #
+    # @example Receive the origin of the transaction
+    #   arr.attach { |delta, origin| pp origin.value }
+    #
+    #   local.transact(origin: :import) { arr << 1 } # :import
+    #
     # @param block [Block]
     # @return [Integer]
     def attach(&block)
@@ -130,7 +139,9 @@ module Y
     #
     # @return [true, false, Float, Integer, String, ::Array, Hash, nil]
     def first
-      document.current_transaction { |tx| yarray_get(tx, 0) }
+      document.current_transaction do |tx|
+        yarray_get(tx, 0) if yarray_length(tx).positive?
+      end
     end
 
     # Returns last element in array if there is at least one element
@@ -147,6 +158,34 @@ module Y
 
     # rubocop:disable Naming/MethodParameterName
 
+    # Attach a listener to changes of this array and all of its nested types
+    #
+    # See {Y::Map#observe_deep} for the structure of the events. Listeners
+    # accepting a second argument receive the {Y::Origin} of the transaction.
+    #
+    # @example Listen to changes of nested types
+    #   arr.observe_deep do |events|
+    #     events.each { |event| pp [event[:path], event[:keys]] }
+    #   end
+    #
+    # @param callback [Proc]
+    # @param block [Block]
+    # @return [Integer] The subscription ID
+    def observe_deep(callback = nil, &block)
+      listener = callback || block&.to_proc
+      return if listener.nil?
+
+      yarray_observe_deep(listener, document)
+    end
+
+    # Detach a listener attached with {#observe_deep}
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    def unobserve_deep(subscription_id)
+      yarray_unobserve_deep(subscription_id)
+    end
+
     # Removes last (n) element(s) from array
     #
     # @param n [Integer, nil] Number of elements to remove
@@ -219,6 +258,8 @@ module Y
     #   Removes a range of elements
     #
     # @return [void]
+    # @raise [Y::IndexError] If elements beyond the end of the array are
+    #   removed
     def slice!(*args)
       document.current_transaction do |tx| # rubocop:disable Metrics/BlockLength
         if args.empty?
@@ -262,6 +303,22 @@ module Y
 
     # rubocop:enable Metrics/AbcSize, Metrics/CyclomaticComplexity, Metrics/MethodLength, Metrics/PerceivedComplexity
 
+    # Creates a sticky index at the given position
+    #
+    # In contrast to a plain index, a sticky index keeps pointing to the same
+    # position, even when content is inserted or removed before it, e.g. by
+    # remote peers. Use {Y::StickyIndex#offset} to resolve it to an index.
+    #
+    # @param index [Integer]
+    # @param assoc [Symbol] Associate the index with the element `:after`
+    #   (default) or `:before` the given position
+    # @return [Y::StickyIndex, nil] Returns nil if the index is out of bounds
+    def sticky_index(index, assoc: :after)
+      document.current_transaction do |tx|
+        yarray_sticky_index(tx, index, assoc)
+      end
+    end
+
     # Convert this array to a Ruby Array
     #
     # @return [Array<true, false, Float, Integer, String, ::Array, Hash>]
@@ -306,7 +363,7 @@ module Y
     #
     # @param transaction [Y::Transaction]
     # @param index [Integer]
-    # @param arr [Array<Boolean, Float, Integer, Array, Hash, Text>]
+    # @param arr [Array<Boolean, Float, Integer, Array, Hash, Text, Y::Doc>]
     # @return [void]
     # @!visibility private
 
@@ -339,6 +396,13 @@ module Y
     # @return [Integer]
     # @!visibility private
 
+    # @!method yarray_observe_deep(proc, document)
+    #   Observe changes of this type and all nested types
+    #
+    # @param proc [Proc]
+    # @param document [Y::Doc]
+    # @return [Integer]
+
     # @!method yarray_remove(transaction, index)
     #   Removes a single element from array at index
     #
@@ -356,6 +420,15 @@ module Y
     # @return [void]
     # @!visibility private
 
+    # @!method yarray_sticky_index(tx, index, assoc)
+    #   Creates a sticky index at the given position
+    #
+    # @param tx [Y::Transaction]
+    # @param index [Integer]
+    # @param assoc [Symbol]
+    # @return [Y::StickyIndex, nil]
+    # @!visibility private
+
     # @!method yarray_to_a(transaction)
     #   Transforms the array into a Ruby array
     # @param transaction [Y::Transaction]
@@ -367,5 +440,11 @@ module Y
     # @param subscription_id [Integer]
     # @return [void]
     # @!visibility private
+
+    # @!method yarray_unobserve_deep(subscription_id)
+    #   Detach deep listener
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
   end
 end
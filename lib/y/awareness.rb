@@ -6,14 +6,15 @@ module Y
   # status, ..). Each client can update its own local state and listen to state
   # changes of remote clients.
   #
-  # Each client is identified by a unique client id (something we borrow from
-  # doc.client_id). A client can override its own state by propagating a message
-  # with an increasing timestamp (clock). If such a message is received, it is
-  # applied if the known state of that client is older than the new state
-  # (`clock < new_clock`). If a client thinks that a remote client is offline,
-  # it may propagate a message with `{ clock, state: null, client }`. If such a
-  # message is received, and the known clock of that client equals the received
-  # clock, it will clean the state.
+  # Each client is identified by a unique client id, the client id of the
+  # document the awareness belongs to. A client can override its own state by
+  # propagating a message with an increasing timestamp (clock). If such a
+  # message is received, it is applied if the known state of that client is
+  # older than the new state (`clock < new_clock`). If a client thinks that a
+  # remote client is offline, it may propagate a message with
+  # `{ clock, state: null, client }`. If such a message is received, and the
+  # known clock of that client equals the received clock, it will clean the
+  # state.
   #
   # Before a client disconnects, it should propagate a null state with an
   # updated clock.
@@ -45,6 +46,41 @@ module Y
   #   client_a.sync(client_b.diff)
   #   client_a.clients # {1242157267=>"{\"name\":\"User A\"}", 2401067547=>…
   class Awareness
+    # Seconds after which the state of a remote client that has not been
+    # updated is outdated, the same as in y-protocols
+    OUTDATED_TIMEOUT = 30
+
+    # @return [Y::Doc] The document this awareness belongs to
+    attr_reader :doc
+
+    # Creates an awareness instance for a document
+    #
+    # The awareness shares the client id of the document, so the presence of
+    # a user and their changes to the document are attributed to the same
+    # client.
+    #
+    # @example Share presence of the user editing a document
+    #   doc = Y::Doc.new
+    #   awareness = Y::Awareness.new(doc)
+    #   awareness.client_id == doc.client_id # true
+    #
+    # @example Control the time in tests
+    #   now = Time.now
+    #   awareness = Y::Awareness.new(clock: -> { now })
+    #
+    # @param doc [Y::Doc] The document, a new one is created by default
+    # @param clock [#call] Returns the current time, used to record when the
+    #   state of a client was last updated and to find outdated clients
+    # @return [Y::Awareness]
+    def self.new(doc = Y::Doc.new, clock: -> { Time.now })
+      awareness = yawareness_new(doc)
+      awareness.instance_variable_set(:@doc, doc)
+      awareness.send(:track_updates, clock)
+      awareness
+    end
+
+    private_class_method :yawareness_new
+
     # Applies an incoming update. This gets the local awareness instance in
     # sync with changes from another client. i.e., updates the state of another
     # user in the local awareness instance.
@@ -55,7 +91,8 @@ module Y
     #   awareness = Y::Awareness.new
     #   awareness.sync(update)
     #
-    # @param diff [Array<Integer>] A binary encoded update
+    # @param diff [Array<Integer>, String] A binary encoded update, either as
+    #   an array of bytes or as a binary string
     # @return [void]
     def sync(diff)
       yawareness_apply_update(diff)
@@ -140,13 +177,70 @@ module Y
 
     # Subscribes to changes
     #
-    # @return [Integer] The subscription ID
+    # The listener is called until it is detached, or until the subscription
+    # is garbage collected.
+    #
+    # @return [Y::AwarenessSubscription] The subscription
     def attach(callback = nil, &block)
       return yawareness_on_update(callback) unless callback.nil?
 
       yawareness_on_update(block.to_proc) unless block.nil?
     end
 
+    # Detach a listener attached with {#attach}
+    #
+    # @example Stop listening to changes
+    #   subscription = awareness.attach { |event| puts event.added }
+    #   awareness.detach(subscription)
+    #
+    # @param subscription [Y::AwarenessSubscription]
+    # @return [void]
+    def detach(subscription)
+      yawareness_unobserve(subscription)
+    end
+
+    # Returns the meta information of a client
+    #
+    # The clock is increased with every update of the client's state,
+    # including its removal. The time of the last update is taken from the
+    # clock of this instance, and is only known for clients whose state has
+    # changed since this instance was created and has not been removed since.
+    #
+    # @example Find out when a client has been seen for the last time
+    #   awareness.meta(client_id)
+    #   # { clock: 3, last_updated: 2026-10-18 12:00:00.123 +0000 }
+    #
+    # @param client_id [Integer]
+    # @return [Hash{Symbol => Integer, Time, nil}, nil] The clock and the time
+    #   of the last update, or nil if the client is unknown
+    def meta(client_id)
+      yawareness_meta(client_id)&.merge(last_updated: @last_updated[client_id])
+    end
+
+    # Removes the states of all remote clients that have not been updated
+    # within the timeout, e.g. because they disconnected without removing
+    # their state. Observers attached with {#attach} are notified of the
+    # removal, so it can be sent to other peers.
+    #
+    # Clients are expected to renew their state periodically, y-protocols does
+    # this every 15 seconds. Call this method periodically as well. The
+    # current time is taken from the clock of this instance.
+    #
+    # @example Remove clients that have not been seen for a minute
+    #   awareness.remove_outdated(60)
+    #
+    # @param timeout [Numeric] Seconds without update after which the state
+    #   of a client is outdated
+    # @return [::Array<Integer>] IDs of the removed clients
+    def remove_outdated(timeout = OUTDATED_TIMEOUT)
+      now = @clock.call
+      outdated = yawareness_clients.keys.select do |id|
+        id != client_id && outdated?(id, now, timeout)
+      end
+      outdated.each { |id| remove_state(id) }
+      outdated
+    end
+
     # Clears out a state of a given client, effectively marking it as
     # disconnected.
     #
@@ -159,9 +253,13 @@ module Y
     # Returns a serializable update object which is representation of a current
     # Awareness state.
     #
-    # @return [::Array<Integer>] Binary encoded update of the local instance
-    def diff
-      yawareness_update
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded update of the local
+    #   instance
+    def diff(binary: false)
+      update = yawareness_update
+      binary ? update : update.bytes
     end
 
     # Returns a serializable update object which is representation of a current
@@ -171,14 +269,48 @@ module Y
     # Error::ClientNotFound error will be returned.
     #
     # @param clients [::Array<Integer>] A list of client IDs
-    # @return [String] Binary encoded update including all given client IDs
-    def diff_with_clients(*clients)
-      yawareness_update_with_clients(clients)
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded update including all
+    #   given client IDs
+    def diff_with_clients(*clients, binary: false)
+      update = yawareness_update_with_clients(clients)
+      binary ? update : update.bytes
     end
 
-    # rubocop:disable Lint/UselessAccessModifier
     private
 
+    # y-sync keeps the time of the last update of every client private, so it
+    # is recorded by an observer of our own. The observer is kept in an
+    # instance variable, as the native subscription does not protect it from
+    # the garbage collector.
+    def track_updates(clock)
+      @clock = clock
+      @last_updated = {}
+      @tracker = proc { |event| record_updates(event) }
+      @tracker_subscription = yawareness_on_update(@tracker)
+    end
+
+    def record_updates(event)
+      now = @clock.call
+      (event.added + event.updated).each { |id| @last_updated[id] = now }
+      event.removed.each { |id| @last_updated.delete(id) }
+    end
+
+    # clients whose last update is unknown have not been updated since this
+    # instance was created
+    def outdated?(id, now, timeout)
+      updated = @last_updated[id]
+      updated.nil? || now - updated >= timeout
+    end
+
+    # @!method self.yawareness_new(doc)
+    #   Creates an awareness instance that shares the given document
+    #
+    # @param doc [Y::Doc]
+    # @return [Y::Awareness]
+    # @!visibility private
+
     # @!method yawareness_apply_update(update)
     #   Applies an update
     #
@@ -218,15 +350,21 @@ module Y
     #   current Awareness instance.
     # @!visibility private
 
+    # @!method yawareness_meta(client_id)
+    #
+    # @param client_id [Integer]
+    # @return [Hash{Symbol => Integer}, nil] The clock of the client
+    # @!visibility private
+
     # @!method yawareness_on_update(callback, &block)
     #
     # @param callback [callback]
-    # @return [Integer] The subscription ID
+    # @return [Y::AwarenessSubscription] The subscription
     # @!visibility private
 
-    # @!method yawareness_remove_on_update(subscription_id)
+    # @!method yawareness_unobserve(subscription)
     #
-    # @param subscription_id [Integer] The subscription id to remove
+    # @param subscription [Y::AwarenessSubscription] The subscription to remove
     # @!visibility private
 
     # @!method yawareness_remove_state(client_id)
@@ -254,7 +392,7 @@ module Y
     #   Returns a serializable update object which is representation of a
     #   current Awareness state.
     #
-    # @return [Y::AwarenessUpdate] The update object
+    # @return [String] A serialized (binary encoded) update object
     # @!visibility private
 
     # @!method yawareness_update_with_clients(clients)
@@ -265,14 +403,25 @@ module Y
     #   otherwise an error will be returned.
     #
     # @param clients [::Array<Integer>]
-    # @return [::Array<Integer>] A serialized (binary encoded) update object
+    # @return [String] A serialized (binary encoded) update object
     # @!visibility private
-
-    # rubocop:enable Lint/UselessAccessModifier
   end
 
   # @!visibility private
   class AwarenessEvent
+    # Returns the awareness update with the state of all changed clients, that
+    # can be sent to other peers as it is
+    #
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String, nil] Binary encoded awareness update
+    def update(binary: false)
+      update = yawareness_event_update
+      return if update.nil?
+
+      binary ? update : update.bytes
+    end
+
     private # rubocop:disable Lint/UselessAccessModifier
 
     # @!method added
@@ -286,5 +435,9 @@ module Y
     # @!method removed
     # @return [::Array<Integer>] Removed clients
     # @!visibility private
+
+    # @!method yawareness_event_update
+    # @return [String, nil] Binary encoded update of the changed clients
+    # @!visibility private
   end
 end
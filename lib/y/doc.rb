@@ -1,5 +1,6 @@
 # frozen_string_literal: true
 
+require "json"
 require_relative "transaction"
 
 module Y
@@ -15,18 +16,117 @@ module Y
   #
   #   remote_map = remote.get_map("my_map")
   #   pp remote_map.to_h #=> {hello: "world"}
-  class Doc
+  #
+  # @example Restore a document as it was at the time of a snapshot
+  #   doc = Y::Doc.new(skip_gc: true)
+  #   text = doc.get_text("my text")
+  #   text << "Hello"
+  #
+  #   snapshot = doc.snapshot
+  #   text << ", World!"
+  #
+  #   past = Y::Doc.new
+  #   past.sync(doc.encode_state_from_snapshot(snapshot))
+  #   past.get_text("my text").to_s #=> "Hello"
+  class Doc # rubocop:disable Metrics/ClassLength
     ZERO_STATE = [0].freeze
     private_constant :ZERO_STATE
 
     ZERO_STATE_V2 = [0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0].freeze
     private_constant :ZERO_STATE_V2
 
+    ROOT_GETTERS = {
+      array: :get_array,
+      map: :get_map,
+      text: :get_text,
+      xml_element: :get_xml_element,
+      xml_fragment: :get_xml_fragment,
+      xml_text: :get_xml_text
+    }.freeze
+    private_constant :ROOT_GETTERS
+
+    UNOBSERVE = {
+      update: :ydoc_unobserve_update,
+      update_v2: :ydoc_unobserve_update_v2,
+      subdocs: :ydoc_unobserve_subdocs,
+      after_transaction: :ydoc_unobserve_after_transaction,
+      transaction_cleanup: :ydoc_unobserve_transaction_cleanup
+    }.freeze
+    private_constant :UNOBSERVE
+
+    # @!method self.new(client_id = nil, **options)
+    #   Creates a new document
+    #
+    #   @example Create a document that keeps deleted content
+    #     doc = Y::Doc.new(gc: false)
+    #     doc.gc? # false
+    #
+    #   @param client_id [Integer, nil] Unique client identifier, defaults to a
+    #     random number
+    #   @param options [Hash]
+    #   @option options [String] :guid Globally unique document identifier,
+    #     defaults to a random UUID v4
+    #   @option options [String] :collection_id Associate the document with a
+    #     collection, only relevant for providers with a concept of collections
+    #   @option options [true, false] :gc Garbage collect deleted content,
+    #     enabled by default
+    #   @option options [true, false] :skip_gc Inverse of `gc`
+    #   @option options [true, false] :auto_load Automatically load this
+    #     document when it is used as a subdocument, defaults to false
+    #   @option options [true, false] :should_load Whether the document should
+    #     be synced by the provider now, defaults to true
+    #   @option options [Symbol] :offset_kind How text lengths and offsets are
+    #     counted, `:utf16` (default, compatible with Yjs) or `:bytes`.
+    #     Codepoint offsets (`:utf32`) are not supported by the underlying yrs
+    #     version.
+    #   @return [Y::Doc]
+    #   @raise [ArgumentError] if `gc` and `skip_gc` contradict each other or
+    #     the offset kind is not supported
+    #   @raise [Y::TypeError] if the client id is not an unsigned 64-bit
+    #     integer
+
+    # @return [true, false] True if this document is automatically loaded when
+    #   used as a subdocument
+    def auto_load?
+      ydoc_auto_load
+    end
+
+    # @return [Integer] The unique client identifier of this document
+    def client_id
+      ydoc_client_id
+    end
+
+    # @return [String, nil] The collection this document is associated with
+    def collection_id
+      ydoc_collection_id
+    end
+
     # Attach a listener to document changes. If one of the data structures is
-    # changes, the block is called with the update as its only argument.
+    # changes, the block is called with the update as its first argument.
+    #
+    # If the block accepts a second argument, it receives the origin of the
+    # transaction that caused the update. This allows to tell local changes
+    # apart from updates received from other peers.
+    #
+    # @example Do not echo updates back to where they came from
+    #   doc.attach do |update, origin|
+    #     next if origin.value == client
+    #
+    #     broadcast(update)
+    #   end
+    #
+    #   doc.sync(update_from_client, origin: client)
+    #
+    # @example Write binary updates to a socket
+    #   doc.attach(binary: true) { |update| socket.write(update) }
     #
-    # @yield [update] Called when document is updated
-    # @yieldparam [Array<Integer>] update The encoded document updates
+    # @param binary [true, false] Yield updates as binary (ASCII-8BIT) strings
+    #   instead of arrays of bytes
+    # @yield [update, origin] Called when document is updated
+    # @yieldparam [::Array<Integer>, String] update The encoded document
+    #   updates
+    # @yieldparam [Y::Origin] origin The origin of the transaction
+    # @return [Integer] The subscription ID, use it to {#detach} the listener
 
     # Example: Attach listener to document changes
     #   doc = described_class.new
@@ -34,8 +134,24 @@ module Y
     #
     #   text = doc.get_text("my text")
     #   text << "1"
-    def attach(&block)
-      ydoc_observe_update(block)
+    def attach(binary: false, &block)
+      subscribe(:update, ydoc_observe_update(block, binary))
+    end
+
+    # Attach a listener to document changes, receiving v2 encoded updates
+    #
+    # This works like {#attach}, the updates can be applied with {#sync_v2}.
+    #
+    # @param binary [true, false] Yield updates as binary (ASCII-8BIT) strings
+    #   instead of arrays of bytes
+    # @yield [update, origin] Called when document is updated
+    # @yieldparam [::Array<Integer>, String] update The v2 encoded document
+    #   updates
+    # @yieldparam [Y::Origin] origin The origin of the transaction
+    # @return [Integer] The subscription ID, use it to {#detach_v2} the
+    #   listener
+    def attach_v2(binary: false, &block)
+      subscribe(:update_v2, ydoc_observe_update_v2(block, binary))
     end
 
     # Commit current transaction
@@ -48,33 +164,191 @@ module Y
       current_transaction(&:commit)
     end
 
+    # Creates a compacted update of the full state of this document
+    #
+    # The state is loaded into an empty document that collects the garbage
+    # of deleted content, so the update only contains live content and the
+    # minimal information about deletions that peers need to merge changes.
+    # This document is left as it is. Use the update to replace the stored
+    # state of a document that grows with every persisted {#diff}.
+    #
+    # Compaction discards the history that {#encode_state_from_snapshot}
+    # needs to restore past states.
+    #
+    # @example Replace the stored state with a compacted one
+    #   result = doc.compact(binary: true)
+    #   File.binwrite("doc.bin", result[:update])
+    #   result[:size_before] #=> 11653
+    #   result[:size_after] #=> 8834
+    #
+    # @param binary [true, false] Return the update as an ASCII-8BIT string
+    #   instead of an array of bytes
+    # @return [Hash{Symbol => Object}] The compacted `update`, and the sizes in
+    #   bytes of the full state before (`size_before`) and after compaction
+    #   (`size_after`)
+    def compact(binary: false)
+      state = full_diff(binary: true)
+      compacted = Y::Update.compact(state, binary: true)
+
+      {
+        update: binary ? compacted : compacted.bytes,
+        size_before: state.bytesize,
+        size_after: compacted.bytesize
+      }
+    end
+
+    # Creates a compacted v2 update of the full state of this document
+    #
+    # @see #compact
+    # @param binary [true, false] Return the update as an ASCII-8BIT string
+    #   instead of an array of bytes
+    # @return [Hash{Symbol => Object}] The compacted `update`, and the sizes in
+    #   bytes of the full state before (`size_before`) and after compaction
+    #   (`size_after`)
+    def compact_v2(binary: false)
+      state = diff_v2(binary: true)
+      compacted = Y::Update.compact_v2(state, binary: true)
+
+      {
+        update: binary ? compacted : compacted.bytes,
+        size_before: state.bytesize,
+        size_after: compacted.bytesize
+      }
+    end
+
+    # Detach a listener attached with {#attach}
+    #
+    # @example Stop sending updates to a disconnected client
+    #   subscription_id = doc.attach { |update| socket.write(update) }
+    #
+    #   socket.on_close { doc.detach(subscription_id) }
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    def detach(subscription_id)
+      ydoc_unobserve_update(subscription_id)
+      unsubscribe(:update, subscription_id)
+    end
+
+    # Detach all listeners of this document
+    #
+    # @return [void]
+    def detach_all
+      listener_registry.each do |event, subscription_ids|
+        subscription_ids.each { |id| send(UNOBSERVE.fetch(event), id) }
+      end
+      listener_registry.clear
+    end
+
+    # Detach a listener attached with {#attach_v2}
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    def detach_v2(subscription_id)
+      ydoc_unobserve_update_v2(subscription_id)
+      unsubscribe(:update_v2, subscription_id)
+    end
+
     # Create a diff between this document and another document. The diff is
     # created based on a state vector provided by the other document. It only
     # returns the missing blocks, as binary encoded sequence.
     #
-    # @param state [::Array<Integer>] The state to create the diff against
-    # @return [::Array<Integer>] Binary encoded diff
-    def diff(state = ZERO_STATE)
-      current_transaction { |tx| ydoc_encode_diff_v1(tx, state) }
+    # The state can be passed as an array of bytes or as a binary string. The
+    # diff is returned as an array of bytes, unless `binary: true` is given.
+    # Binary strings avoid allocating one Ruby object per byte, which matters
+    # for large documents.
+    #
+    # Large diffs are encoded without holding the GVL, so other threads keep
+    # running meanwhile. Other threads that use the same transaction until the
+    # diff is returned get a {Y::TransactionError}.
+    #
+    # @example Store the diff without converting it
+    #   File.binwrite("doc.bin", doc.diff(binary: true))
+    #
+    # @param state [::Array<Integer>, String] The state to create the diff
+    #   against
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded diff
+    # @raise [Y::DecodeError] If the state can not be decoded
+    def diff(state = ZERO_STATE, binary: false)
+      update = current_transaction { |tx| ydoc_encode_diff_v1(tx, state) }
+      binary ? update : update.bytes
     end
 
     # Create a v2 diff between this document and another document. The diff is
     # created based on a state vector provided by the other document. It only
     # returns the missing blocks, as binary encoded sequence.
     #
-    # @param state [::Array<Integer>] The state to create the diff against
-    # @return [::Array<Integer>] Binary encoded diff
-    def diff_v2(state = ZERO_STATE_V2)
-      current_transaction { |tx| ydoc_encode_diff_v2(tx, state) }
+    # @param state [::Array<Integer>, String] The state to create the diff
+    #   against
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded diff
+    # @raise [Y::DecodeError] If the state can not be decoded
+    def diff_v2(state = ZERO_STATE_V2, binary: false)
+      update = current_transaction { |tx| ydoc_encode_diff_v2(tx, state) }
+      binary ? update : update.bytes
+    end
+
+    # Encodes the document state as it was at the time the snapshot was
+    # taken. The result is a binary encoded update that can be applied to an
+    # empty document with {#sync}.
+    #
+    # This requires the document to be created with `skip_gc: true`, otherwise
+    # deleted content needed to restore the past state might be gone.
+    #
+    # @param snapshot [Y::Snapshot]
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded update
+    # @raise [Y::Error] if garbage collection is enabled for this document
+    def encode_state_from_snapshot(snapshot, binary: false)
+      update = current_transaction do |tx|
+        ydoc_encode_state_from_snapshot_v1(tx, snapshot)
+      end
+      binary ? update : update.bytes
+    end
+
+    # Encodes the document state as it was at the time the snapshot was
+    # taken, as a v2 binary encoded update. See {#encode_state_from_snapshot}.
+    #
+    # @param snapshot [Y::Snapshot]
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded update
+    # @raise [Y::Error] if garbage collection is enabled for this document
+    def encode_state_from_snapshot_v2(snapshot, binary: false)
+      update = current_transaction do |tx|
+        ydoc_encode_state_from_snapshot_v2(tx, snapshot)
+      end
+      binary ? update : update.bytes
     end
 
     # Creates a full diff for the current document. It is similar to {#diff},
     # but does not take a state. Instead it creates an empty state and passes it
     # to the encode_diff function.
     #
-    # @return [::Array<Integer>] Binary encoded diff
-    def full_diff
-      diff
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded diff
+    def full_diff(binary: false)
+      diff(binary: binary)
+    end
+
+    # Returns the globally unique identifier of this document
+    #
+    # Unlike the client ID, the guid is not shared with subdocuments. It is
+    # used to identify a subdocument across all peers.
+    #
+    # @return [String]
+    def guid
+      ydoc_guid
+    end
+
+    # @return [true, false] True if deleted content is garbage collected
+    def gc?
+      !ydoc_skip_gc
     end
 
     # Gets or creates a new array by name
@@ -86,6 +360,7 @@ module Y
     # @param name [String] The name of the structure
     # @param values [::Array] Optional initial values
     # @return [Y::Array]
+    # @raise [Y::TransactionError] If the document is in a transaction
     def get_array(name, values = nil)
       array = ydoc_get_or_insert_array(name)
       array.document = self
@@ -102,6 +377,7 @@ module Y
     # @param name [String] The name of the structure
     # @param input [Hash] Optional initial map key-value pairs
     # @return [Y::Map]
+    # @raise [Y::TransactionError] If the document is in a transaction
     def get_map(name, input = nil)
       map = ydoc_get_or_insert_map(name)
       map.document = self
@@ -118,6 +394,7 @@ module Y
     # @param name [String] The name of the structure
     # @param input [String] Optional initial text value
     # @return [Y::Text]
+    # @raise [Y::TransactionError] If the document is in a transaction
     def get_text(name, input = nil)
       text = ydoc_get_or_insert_text(name)
       text.document = self
@@ -129,6 +406,7 @@ module Y
     #
     # @param name [String] The name of the structure
     # @return [Y::XMLElement]
+    # @raise [Y::TransactionError] If the document is in a transaction
     def get_xml_element(name)
       xml_element = ydoc_get_or_insert_xml_element(name)
       xml_element.document = self
@@ -139,6 +417,7 @@ module Y
     #
     # @param name [String] The name of the fragment
     # @return [Y::XMLFragment]
+    # @raise [Y::TransactionError] If the document is in a transaction
     def get_xml_fragment(name)
       xml_fragment = ydoc_get_or_insert_xml_fragment(name)
       xml_fragment.document = self
@@ -150,6 +429,7 @@ module Y
     # @param name [String] The name of the structure
     # @param input [String] Optional initial text value
     # @return [Y::XMLText]
+    # @raise [Y::TransactionError] If the document is in a transaction
     def get_xml_text(name, input = nil)
       xml_text = ydoc_get_or_insert_xml_text(name)
       xml_text.document = self
@@ -157,36 +437,388 @@ module Y
       xml_text
     end
 
+    # Captures the current state of the document. Together with
+    # {#encode_state_from_snapshot} this allows to restore past versions of a
+    # document without storing full copies of it.
+    #
+    # @return [Y::Snapshot]
+    def snapshot
+      current_transaction(&:snapshot)
+    end
+
+    # Requests to load this subdocument
+    #
+    # This is a no-op for subdocuments that are already loaded. Otherwise, a
+    # `loaded` event is emitted for this document to the subdocs listeners of
+    # the parent document (see {#observe_subdocs}).
+    #
+    # @example Load a subdocument on demand
+    #   doc = Y::Doc.new
+    #   doc.observe_subdocs { |event| pp event[:loaded].map(&:guid) }
+    #
+    #   page = doc.get_map("pages")["home"]
+    #   page.load
+    #
+    # @param parent [Y::Doc, nil] The parent document, must be provided when
+    #   there is an active transaction on the parent document
+    # @return [void]
+    # @raise [Y::Error] if this document is not a subdocument
+    def load(parent = nil)
+      return ydoc_load(nil) if parent.nil?
+
+      parent.current_transaction { |tx| ydoc_load(tx) }
+    end
+
+    # Lists the subscription IDs of all listeners attached to this document,
+    # grouped by event: `:update` ({#attach}), `:update_v2` ({#attach_v2}),
+    # `:subdocs` ({#observe_subdocs}), `:after_transaction`
+    # ({#observe_after_transaction}) and `:transaction_cleanup`
+    # ({#observe_transaction_cleanup}).
+    #
+    # @example
+    #   doc = Y::Doc.new
+    #   doc.attach { |update| pp update }
+    #   doc.listeners # { update: [0] }
+    #
+    # @return [Hash{Symbol => ::Array<Integer>}]
+    def listeners
+      listener_registry.transform_values(&:dup)
+    end
+
+    # @return [Symbol] How text lengths and offsets are counted, either
+    #   `:utf16` or `:bytes`
+    def offset_kind
+      ydoc_offset_kind
+    end
+
+    # Attach a listener that is called once for every committed transaction
+    #
+    # The listener receives a Hash describing the transaction:
+    #
+    # - `before_state` and `after_state` are the encoded state vectors of the
+    #   document before and after the transaction, e.g. to create a diff of
+    #   the transaction with {#diff}. They are arrays of bytes, or binary
+    #   (ASCII-8BIT) strings with `binary: true`
+    # - `delete_set` maps client IDs to the clock ranges deleted within the
+    #   transaction
+    # - `changed` lists the names of the root types that have been changed,
+    #   including changes to their nested types
+    #
+    # Listeners accepting a second argument receive the {Y::Origin} of the
+    # transaction. The listener is called after all type observers, but
+    # before garbage collection of the deleted content. Transactions that
+    # neither insert nor delete anything, e.g. the ones used to read the
+    # document, are skipped.
+    #
+    # @example Invalidate a search index
+    #   doc.observe_after_transaction do |event, origin|
+    #     index.invalidate(event[:changed]) if origin.remote?
+    #   end
+    #
+    # @param callback [Proc]
+    # @param binary [true, false] Pass state vectors as binary strings
+    #   instead of arrays of bytes
+    # @param block [Block]
+    # @return [Integer] The subscription ID
+    def observe_after_transaction(callback = nil, binary: false, &block)
+      listener = callback || block&.to_proc
+      return if listener.nil?
+
+      subscribe(
+        :after_transaction,
+        ydoc_observe_after_transaction(listener, binary)
+      )
+    end
+
+    # Attach a listener to subdocument changes of this document
+    #
+    # The listener is called with a Hash containing lists of the subdocuments
+    # that have been `added`, `removed` or `loaded` within a transaction.
+    #
+    # @example Listen to new subdocuments
+    #   doc = Y::Doc.new
+    #   doc.observe_subdocs { |event| pp event[:added].map(&:guid) }
+    #
+    #   doc.get_map("pages")["home"] = Y::Doc.new
+    #
+    # @param callback [Proc]
+    # @param block [Block]
+    # @return [Integer] The subscription ID
+    def observe_subdocs(callback = nil, &block)
+      listener = callback || block&.to_proc
+      return if listener.nil?
+
+      subscribe(:subdocs, ydoc_observe_subdocs(listener))
+    end
+
+    # Attach a listener that is called once for every committed transaction,
+    # after the transaction has been cleaned up
+    #
+    # The listener receives the same Hash as {#observe_after_transaction},
+    # but is called after garbage collection and right before the update
+    # listeners ({#attach}).
+    #
+    # @example Write an audit log
+    #   doc.observe_transaction_cleanup do |event, origin|
+    #     logger.info("#{origin.value} changed #{event[:changed].join(", ")}")
+    #   end
+    #
+    # @param callback [Proc]
+    # @param binary [true, false] Pass state vectors as binary strings
+    #   instead of arrays of bytes
+    # @param block [Block]
+    # @return [Integer] The subscription ID
+    def observe_transaction_cleanup(callback = nil, binary: false, &block)
+      listener = callback || block&.to_proc
+      return if listener.nil?
+
+      subscribe(
+        :transaction_cleanup,
+        ydoc_observe_transaction_cleanup(listener, binary)
+      )
+    end
+
+    # Describes the updates that have been received but not integrated yet
+    #
+    # Updates can only be integrated once the blocks they build upon are
+    # known, e.g. when updates from a peer arrive out of order. Until then,
+    # their blocks and deletions are pending. The description contains the
+    # pending clock ranges (`structs`) and deletions (`delete_set`) per client,
+    # and the clock ranges the document is `missing` to integrate them. The
+    # missing data can be requested from peers, e.g. by sending them the
+    # {#state} of this document.
+    #
+    # Pending updates are integrated as soon as the missing updates are
+    # applied with {#sync}, {#sync_v2} or {#restore} on this instance.
+    #
+    # @example Receive updates out of order
+    #   doc.sync(second_update)
+    #
+    #   doc.pending
+    #   # { structs: { 1234 => [5...11] }, missing: { 1234 => [0...5] },
+    #   #   delete_set: {} }
+    #
+    #   doc.sync(first_update)
+    #   doc.pending #=> nil
+    #
+    # @return [Hash{Symbol => Hash}, nil] Pending and missing clock ranges per
+    #   client, or nil if nothing is pending
+    def pending
+      read { |tx| ydoc_pending(tx) }
+    end
+
+    # Whether updates have been received that could not be integrated yet
+    #
+    # @see #pending
+    # @return [true, false]
+    def pending?
+      !pending.nil?
+    end
+
+    # Returns all root types of this document with their kind and a reference
+    #
+    # This includes root types received from other clients that have not been
+    # fetched with one of the typed getters yet, e.g. {#get_text}. The kind of
+    # those is inferred from their content, root types without any content
+    # are of kind `:undefined` and have no reference.
+    #
+    # @example Discover the root types of a document loaded from an update
+    #   doc = Y::Doc.new
+    #   doc.sync(update)
+    #
+    #   doc.roots
+    #   # { "my text" => { kind: :text, value: #<Y::Text> } }
+    #
+    # @return [Hash{String => Hash}] Kind and reference by root type name
+    def roots
+      current_transaction do |tx|
+        ydoc_roots(tx).to_h do |name, kind|
+          getter = ROOT_GETTERS[kind]
+          [name, { kind: kind, value: getter && tx.public_send(getter, name) }]
+        end
+      end
+    end
+
+    # @return [true, false] True if the document should be synced by the
+    #   provider, this is the case after {#load} has been called
+    def should_load?
+      ydoc_should_load
+    end
+
+    # @return [true, false] True if deleted content is kept in the document
+    def skip_gc?
+      ydoc_skip_gc
+    end
+
+    # Returns statistics about the content and memory usage of this document
+    #
+    # The statistics are meant to spot pathological documents, e.g. documents
+    # with lots of tombstones that would benefit from {#compact}. They are
+    # collected by encoding the state of the document, which takes time
+    # proportional to its size.
+    #
+    # - `clients`: the clock, the number of blocks, and how many of them are
+    #   deleted (tombstones) or garbage collected, per client
+    # - `blocks`, `deleted`, `gc`: the totals over all clients
+    # - `delete_set`: the number of deleted clock ranges per client
+    # - `roots`: the length of each root type, the number of entries of maps
+    # - `size_v1`, `size_v2`: the size of the encoded state in bytes
+    # - `heap_bytes`: a rough estimate of the memory held by the document
+    #
+    # @example Find documents with many tombstones
+    #   stats = doc.stats
+    #   doc.compact if stats[:deleted] > stats[:blocks] / 2
+    #
+    # @return [Hash{Symbol => Object}]
+    def stats
+      read { |tx| ydoc_stats(tx) }
+    end
+
+    # Returns all subdocuments of this document
+    #
+    # @return [::Array<Y::Doc>]
+    def subdocs
+      current_transaction { |tx| ydoc_subdocs(tx) }
+    end
+
+    # Converts the whole document into plain Ruby data
+    #
+    # Follows the semantics of Yjs `doc.toJSON()`: texts are converted into
+    # strings, XML types into their string representation, arrays and maps
+    # into arrays and hashes. Root types received from other clients are
+    # converted based on their inferred kind, see {#roots}.
+    #
+    # @example Export a document
+    #   doc = Y::Doc.new
+    #   doc.get_text("my text") << "Hello"
+    #   doc.get_map("my map")[:count] = 1
+    #
+    #   doc.to_h # { "my text" => "Hello", "my map" => { "count" => 1 } }
+    #
+    # @return [Hash{String => Object}]
+    def to_h
+      current_transaction { |tx| ydoc_to_h(tx) }
+    end
+
+    # Returns the JSON representation of the whole document, see {#to_h}
+    #
+    # @return [String]
+    def to_json(*args)
+      to_h.to_json(*args)
+    end
+
+    # Detach a listener attached with {#observe_after_transaction}
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    def unobserve_after_transaction(subscription_id)
+      ydoc_unobserve_after_transaction(subscription_id)
+      unsubscribe(:after_transaction, subscription_id)
+    end
+
+    # Detach a subdocuments listener
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    def unobserve_subdocs(subscription_id)
+      ydoc_unobserve_subdocs(subscription_id)
+      unsubscribe(:subdocs, subscription_id)
+    end
+
+    # Detach a listener attached with {#observe_transaction_cleanup}
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    def unobserve_transaction_cleanup(subscription_id)
+      ydoc_unobserve_transaction_cleanup(subscription_id)
+      unsubscribe(:transaction_cleanup, subscription_id)
+    end
+
     # Creates a state vector of this document. This can be used to compare the
     # state of two documents with each other and to later on sync them.
     #
-    # @return [::Array<Integer>] Binary encoded state vector
-    def state
-      current_transaction(&:state)
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded state vector
+    def state(binary: false)
+      current_transaction { |tx| tx.state(binary: binary) }
     end
 
     # Creates a v2 state vector of this document. This can be used to compare
     # the state of two documents with each other and to later on sync them.
     #
-    # @return [::Array<Integer>] Binary encoded state vector
-    def state_v2
-      current_transaction(&:state_v2)
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded state vector
+    def state_v2(binary: false)
+      current_transaction { |tx| tx.state_v2(binary: binary) }
     end
 
     # Synchronizes this document with the diff from another document
     #
-    # @param diff [::Array<Integer>] Binary encoded update
+    # Large diffs are decoded and integrated without holding the GVL, so other
+    # threads keep running meanwhile. Observers are still called with the GVL
+    # held. Other threads that use the transaction of the document until the
+    # diff is applied get a {Y::TransactionError}.
+    #
+    # @param diff [::Array<Integer>, String] Binary encoded update, either as
+    #   an array of bytes or as a binary string
+    # @param origin [String, Symbol, Integer, nil] Optional origin, e.g. the
+    #   peer the update was received from
+    # @param policy [Y::UpdatePolicy, nil] Optional policy the diff must
+    #   comply with, a violating diff is not applied
     # @return [void]
-    def sync(diff)
-      current_transaction { |tx| tx.apply(diff) }
+    # @raise [Y::TransactionError] If the origin differs from the origin of
+    #   the active transaction
+    # @raise [Y::DecodeError] If the diff can not be decoded
+    # @raise [Y::UpdateRejected] If the diff violates the policy
+    def sync(diff, origin: nil, policy: nil)
+      current_transaction(origin: origin, local: false) do |tx|
+        tx.apply(diff, policy: policy)
+      end
     end
 
     # Synchronizes this document with the v2 diff from another document
     #
-    # @param diff [::Array<Integer>] Binary encoded update
+    # @param diff [::Array<Integer>, String] Binary encoded update
+    # @param origin [String, Symbol, Integer, nil] Optional origin, e.g. the
+    #   peer the update was received from
+    # @param policy [Y::UpdatePolicy, nil] Optional policy the diff must
+    #   comply with, a violating diff is not applied
     # @return [void]
-    def sync_v2(diff)
-      current_transaction { |tx| tx.apply_v2(diff) }
+    # @raise [Y::TransactionError] If the origin differs from the origin of
+    #   the active transaction
+    # @raise [Y::DecodeError] If the diff can not be decoded
+    # @raise [Y::UpdateRejected] If the diff violates the policy
+    def sync_v2(diff, origin: nil, policy: nil)
+      current_transaction(origin: origin, local: false) do |tx|
+        tx.apply_v2(diff, policy: policy)
+      end
+    end
+
+    # Checks whether an update complies with a policy, without applying it
+    #
+    # @example Reject updates to unknown root types
+    #   policy = Y::UpdatePolicy.new(roots: ["content"])
+    #   violation = doc.validate_update(update, policy)
+    #   violation&.reason #=> :root
+    #
+    # @param update [::Array<Integer>, String] Binary encoded update
+    # @param policy [Y::UpdatePolicy]
+    # @return [Y::UpdateRejected, nil] The violation, or nil if the update
+    #   complies with the policy
+    def validate_update(update, policy)
+      read { |tx| tx.validate_update(update, policy) }
+    end
+
+    # Checks whether a v2 update complies with a policy, without applying it
+    #
+    # @param update [::Array<Integer>, String] Binary encoded v2 update
+    # @param policy [Y::UpdatePolicy]
+    # @return [Y::UpdateRejected, nil] The violation, or nil if the update
+    #   complies with the policy
+    def validate_update_v2(update, policy)
+      read { |tx| tx.validate_update_v2(update, policy) }
     end
 
     # Restores a specific document from an update that contains full state
@@ -194,42 +826,193 @@ module Y
     # This is doing the same as {#sync}, but it exists to be explicit about
     # the intent. This is the companion to {#full_diff}.
     #
-    # @param full_diff [::Array<Integer>] Binary encoded update
+    # @param full_diff [::Array<Integer>, String] Binary encoded update
+    # @param origin [String, Symbol, Integer, nil] Optional origin, e.g. the
+    #   peer the update was received from
     # @return [void]
-    def restore(full_diff)
-      current_transaction { |tx| tx.apply(full_diff) }
+    # @raise [Y::TransactionError] If the origin differs from the origin of
+    #   the active transaction
+    # @raise [Y::DecodeError] If the update can not be decoded
+    def restore(full_diff, origin: nil)
+      current_transaction(origin: origin, local: false) do |tx|
+        tx.apply(full_diff)
+      end
+    end
+
+    # Creates a read-only transaction
+    #
+    # Read-only transactions only hold a shared lock on the document, any
+    # number of them can be active at the same time. Reading types within the
+    # block reuses the transaction, while modifying the document raises a
+    # {Y::TransactionError}. Creating a (read-write) transaction fails as long
+    # as any read-only transaction is active.
+    #
+    # When called within an active transaction, the block is executed using
+    # that transaction. Like with {#transact}, root types must be retrieved
+    # before entering the block.
+    #
+    # @example Read multiple types at once
+    #   doc = Y::Doc.new
+    #   title = doc.get_text("title", "Hello")
+    #   meta = doc.get_map("meta")
+    #   meta[:author] = "me"
+    #
+    #   doc.read do
+    #     title.to_s #=> "Hello"
+    #     meta.to_h #=> {"author" => "me"}
+    #   end
+    #
+    # @yieldparam [Y::Transaction] transaction A read-only transaction
+    # @return [Object] The result of the block
+    def read(&block)
+      transaction = @current_transaction || read_transactions[Thread.current]
+      return yield transaction if transaction
+
+      read_only_transaction(&block)
     end
 
     # Creates a new transaction
-    def transact
+    #
+    # An optional origin can be attached to the transaction. It allows to
+    # identify who caused a change, e.g. {Y::UndoManager} only captures
+    # changes from the origins it tracks.
+    #
+    # @example Create a transaction with an origin
+    #   doc = Y::Doc.new
+    #   text = doc.get_text("my text")
+    #
+    #   doc.transact(origin: "local") do
+    #     text << "Hello, World!"
+    #   end
+    #
+    # Transactions are local by default. Updates applied with {#sync},
+    # {#sync_v2} and {#restore} run in remote transactions, which observers
+    # can distinguish via {Y::Origin#local?}. Within a block, they join the
+    # transaction of the block, an explicit origin given to them must match
+    # the origin of the block.
+    #
+    # @example Apply updates of a peer within one transaction
+    #   doc.transact(origin: "peer", local: false) do
+    #     updates.each { |update| doc.sync(update, origin: "peer") }
+    #   end
+    #
+    # @param origin [String, Symbol, Integer, nil] Optional transaction origin
+    # @param local [true, false] Whether the changes are made locally
+    # Errors raised by observers are raised once the transaction is committed,
+    # the first one wins.
+    #
+    # @raise [Y::TransactionError] If a read-only transaction is active
+    def transact(origin: nil, local: true)
       # 1. release potentially existing transaction
-      if @current_transaction
-        @current_transaction.free
-        @current_transaction = nil
-      end
+      release_current_transaction
 
       # 2. store new transaction in instance variable
-      @current_transaction = ydoc_transact
+      @current_transaction = ydoc_transact(origin, local)
       @current_transaction.document = self
+      @current_origin = origin
 
       # 3. call block with reference to current_transaction
       yield @current_transaction
     ensure
-      @current_transaction&.free
-      @current_transaction = nil
+      release_current_transaction
     end
 
+    # Yields the active transaction, or a new one with the given origin
+    #
+    # An active transaction is joined as it is, including its locality, e.g.
+    # {#sync} within a {#transact} block applies the update in the local
+    # transaction of the block. Only an explicit origin must match the origin
+    # of the active transaction.
+    #
+    # @raise [Y::TransactionError] If the origin differs from the origin of
+    #   the active transaction
     # @!visibility private
-    def current_transaction(&block)
+    def current_transaction(origin: nil, local: true, &block)
       raise "provide a block" unless block
 
       # 1. instance variable is set, just use it
-      return yield @current_transaction if @current_transaction
+      if @current_transaction
+        check_current_origin(origin)
+        return yield @current_transaction
+      end
+
+      # 2. reuse read-only transaction of the current thread, if any
+      transaction = read_transactions[Thread.current]
+      return yield transaction if transaction
+
+      # 3. forward block to transact
+      transact(origin: origin, local: local, &block)
+    end
+
+    private
+
+    # The transaction is detached before it is freed, freeing it raises the
+    # errors of observers called while committing
+    def release_current_transaction
+      transaction = @current_transaction
+      @current_transaction = nil
+      @current_origin = nil
+      transaction&.free
+    end
+
+    def check_current_origin(origin)
+      active = @current_origin
+      return if origin.nil? || origin == active
+
+      raise Y::TransactionError,
+            "cannot join the active transaction with origin " \
+            "#{active.inspect}, origin #{origin.inspect} was requested"
+    end
+
+    # Subscription IDs are only unique per event, e.g. the first v1 and v2
+    # update listeners both have ID 0
+    def listener_registry
+      @listener_registry ||= {}
+    end
+
+    # Read-only transactions are tracked per thread, so that threads do not
+    # free each others transactions
+    def read_transactions
+      @read_transactions ||= {}
+    end
+
+    def read_only_transaction
+      transaction = ydoc_read_transaction
+      transaction.document = self
+      read_transactions[Thread.current] = transaction
+
+      yield transaction
+    ensure
+      read_transactions.delete(Thread.current)
+      transaction&.free
+    end
+
+    def subscribe(event, subscription_id)
+      (listener_registry[event] ||= []) << subscription_id
+      subscription_id
+    end
 
-      # 2. forward block to transact
-      transact(&block) unless @current_transaction
+    def unsubscribe(event, subscription_id)
+      subscription_ids = listener_registry.fetch(event, [])
+      subscription_ids.delete(subscription_id)
+      listener_registry.delete(event) if subscription_ids.empty?
     end
 
+    # @!method ydoc_auto_load
+    #
+    # @return [true, false]
+    # @!visibility private
+
+    # @!method ydoc_client_id
+    #
+    # @return [Integer]
+    # @!visibility private
+
+    # @!method ydoc_collection_id
+    #
+    # @return [String, nil]
+    # @!visibility private
+
     # @!method ydoc_encode_diff_v1(tx, state_vector)
     #   Encodes the diff of current document state vs provided state
     #
@@ -237,7 +1020,7 @@ module Y
     #     doc = Y::Doc.new
     #     tx = doc.ydoc_encode_diff_v1(other_state)
     #
-    # @return [Array<Integer>] Binary encoded update
+    # @return [String] Binary encoded update
     # @!visibility private
 
     # @!method ydoc_encode_diff_v2(tx, state_vector)
@@ -248,16 +1031,100 @@ module Y
     #     doc = Y::Doc.new
     #     tx = doc.ydoc_encode_diff_v2(other_state)
     #
-    # @return [Array<Integer>] Binary encoded update
+    # @return [String] Binary encoded update
     # @!visibility private
 
-    # @!method ydoc_transact
+    # @!method ydoc_encode_state_from_snapshot_v1(tx, snapshot)
+    #   Encodes the document state at the time of the snapshot
+    #
+    # @param tx [Y::Transaction]
+    # @param snapshot [Y::Snapshot]
+    # @return [String] Binary encoded update
+    # @!visibility private
+
+    # @!method ydoc_encode_state_from_snapshot_v2(tx, snapshot)
+    #   Encodes the document state at the time of the snapshot in the v2
+    #   format
+    #
+    # @param tx [Y::Transaction]
+    # @param snapshot [Y::Snapshot]
+    # @return [String] Binary encoded update
+    # @!visibility private
+
+    # @!method ydoc_guid
+    #
+    # @return [String]
+    # @!visibility private
+
+    # @!method ydoc_load(tx)
+    #   Requests to load this subdocument, using the transaction of the parent
+    #   document if present
+    #
+    # @param tx [Y::Transaction, nil]
+    # @return [void]
+    # @!visibility private
+
+    # @!method ydoc_offset_kind
+    #
+    # @return [Symbol]
+    # @!visibility private
+
+    # @!method ydoc_pending(tx)
+    #   Describes pending updates of the document
+    #
+    # @param tx [Y::Transaction]
+    # @return [Hash{Symbol => Hash}, nil]
+    # @!visibility private
+
+    # @!method ydoc_read_transaction
+    #   Creates a new read-only transaction
+    #
+    # @return [Y::Transaction]
+    # @!visibility private
+
+    # @!method ydoc_roots(tx)
+    #
+    # @param tx [Y::Transaction]
+    # @return [Hash{String => Symbol}]
+    # @!visibility private
+
+    # @!method ydoc_should_load
+    #
+    # @return [true, false]
+    # @!visibility private
+
+    # @!method ydoc_skip_gc
+    #
+    # @return [true, false]
+    # @!visibility private
+
+    # @!method ydoc_stats(tx)
+    #
+    # @param tx [Y::Transaction]
+    # @return [Hash{Symbol => Object}]
+    # @!visibility private
+
+    # @!method ydoc_subdocs(tx)
+    #
+    # @param tx [Y::Transaction]
+    # @return [Array<Y::Doc>]
+    # @!visibility private
+
+    # @!method ydoc_to_h(tx)
+    #
+    # @param tx [Y::Transaction]
+    # @return [Hash{String => Object}]
+    # @!visibility private
+
+    # @!method ydoc_transact(origin, local)
     #   Creates a new transaction for the document
     #
     #   @example Create transaction on doc
     #     doc = Y::Doc.new
-    #     tx = doc.ydoc_transact
+    #     tx = doc.ydoc_transact(nil, true)
     #
+    # @param origin [String, Symbol, Integer, nil]
+    # @param local [true, false]
     # @return [Y::Transaction] The transaction object
     # @!visibility private
 
@@ -303,11 +1170,71 @@ module Y
     # @return [Y::XMLText]
     # @!visibility private
 
-    # @!method ydoc_observe_update(block)
+    # @!method ydoc_observe_after_transaction(block, binary)
+    #
+    # @param [Proc] block
+    # @param [true, false] binary
+    # @return [Integer]
+    # @!visibility private
+
+    # @!method ydoc_observe_transaction_cleanup(block, binary)
+    #
+    # @param [Proc] block
+    # @param [true, false] binary
+    # @return [Integer]
+    # @!visibility private
+
+    # @!method ydoc_observe_update(block, binary)
     #   Creates a subscription to observe changes to the document
     #
     # @param [Proc] block
+    # @param [true, false] binary Pass updates as binary strings
     # @return [Integer]
     # @!visibility private
+
+    # @!method ydoc_observe_update_v2(block, binary)
+    #   Creates a subscription to observe v2 encoded changes to the document
+    #
+    # @param [Proc] block
+    # @param [true, false] binary Pass updates as binary strings
+    # @return [Integer]
+    # @!visibility private
+
+    # @!method ydoc_observe_subdocs(block)
+    #   Creates a subscription to observe subdocument changes
+    #
+    # @param [Proc] block
+    # @return [Integer]
+    # @!visibility private
+
+    # @!method ydoc_unobserve_after_transaction(subscription_id)
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    # @!visibility private
+
+    # @!method ydoc_unobserve_subdocs(subscription_id)
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    # @!visibility private
+
+    # @!method ydoc_unobserve_transaction_cleanup(subscription_id)
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    # @!visibility private
+
+    # @!method ydoc_unobserve_update(subscription_id)
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    # @!visibility private
+
+    # @!method ydoc_unobserve_update_v2(subscription_id)
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    # @!visibility private
   end
 end
@@ -0,0 +1,30 @@
+# frozen_string_literal: true
+
+module Y
+  # Base class of all errors raised by y-rb. Invalid arguments, e.g. an
+  # unknown option, still raise Ruby's `ArgumentError`.
+  #
+  # @example Handle any error of a document operation
+  #   begin
+  #     doc.sync(update)
+  #   rescue Y::Error => e
+  #     logger.warn("cannot sync document: #{e.message}")
+  #   end
+  class Error < StandardError; end
+
+  # Raised when an update, state vector, snapshot or sticky index can not be
+  # decoded. The message includes the reason reported by the decoder.
+  class DecodeError < Error; end
+
+  # Raised when an index or a range is out of the bounds of a type
+  class IndexError < Error; end
+
+  # Raised when a Ruby value can not be stored in a document, e.g. an integer
+  # that does not fit into 64 bits, or when an argument is of the wrong type
+  class TypeError < Error; end
+
+  # Raised when a transaction can not be created, because another one is
+  # active, or when a transaction is used to modify a document it may not
+  # modify, e.g. a read-only transaction
+  class TransactionError < Error; end
+end
@@ -36,6 +36,9 @@ module Y
 
     # Attach a listener to get notified about any changes to the map
     #
+    # Listeners accepting a second argument receive the {Y::Origin} of the
+    # transaction that caused the changes.
+    #
     # @param callback [Proc]
     # @param block [Block]
     # @return [Integer]
@@ -113,6 +116,48 @@ module Y
       document.current_transaction { |tx| ymap_insert(tx, key, val) }
     end
 
+    # Attach a listener to changes of this map and all of its nested types
+    #
+    # The listener is called once per transaction with a list of events, one
+    # for every changed type. Each event is a Hash with:
+    #
+    # - `target` the changed type
+    # - `path` the keys and indices leading from this map to the target
+    # - `keys` the changed entries, if the target is a map or XML node
+    # - `delta` the changed content, if the target is an array, a text or an
+    #   XML node
+    #
+    # Listeners accepting a second argument receive the {Y::Origin} of the
+    # transaction.
+    #
+    # @example Listen to changes of maps nested in an array
+    #   doc = Y::Doc.new
+    #   pages = doc.get_map("pages")
+    #   pages.observe_deep do |events|
+    #     events.each { |event| pp event[:path] } # ["home", 0]
+    #   end
+    #
+    #   # a peer changes the map at index 0 of the array stored at "home"
+    #   doc.sync(update)
+    #
+    # @param callback [Proc]
+    # @param block [Block]
+    # @return [Integer] The subscription ID
+    def observe_deep(callback = nil, &block)
+      listener = callback || block&.to_proc
+      return if listener.nil?
+
+      ymap_observe_deep(listener, document)
+    end
+
+    # Detach a listener attached with {#observe_deep}
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    def unobserve_deep(subscription_id)
+      ymap_unobserve_deep(subscription_id)
+    end
+
     # Returns size of map
     #
     # @return [Integer]
@@ -173,6 +218,13 @@ module Y
     # @param callback [Proc]
     # @return [Integer]
 
+    # @!method ymap_observe_deep(proc, document)
+    #   Observe changes of this type and all nested types
+    #
+    # @param proc [Proc]
+    # @param document [Y::Doc]
+    # @return [Integer]
+
     # @!method ymap_remove(tx, key)
     #   Removes key-value pair from Map if key exists.
     #
@@ -195,5 +247,11 @@ module Y
     #
     # @param subscription_id [Integer]
     # @return [void]
+
+    # @!method ymap_unobserve_deep(subscription_id)
+    #   Detach deep listener
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
   end
 end
@@ -0,0 +1,43 @@
+# frozen_string_literal: true
+
+module Y
+  # The origin of a transaction, passed to observers as their second argument.
+  #
+  # Changes made with {Y::Doc#transact} are local, unless `local: false` is
+  # given. Updates applied with {Y::Doc#sync}, {Y::Doc#sync_v2} and
+  # {Y::Doc#restore} are remote.
+  #
+  # @example Skip side effects for changes received from other peers
+  #   doc = Y::Doc.new
+  #   text = doc.get_text("my text")
+  #   text.attach do |delta, origin|
+  #     notify(delta) if origin.local?
+  #   end
+  class Origin
+    # @return [true, false] True if the changes were made locally
+    def local?
+      yorigin_local
+    end
+
+    # @return [true, false] True if the changes were received from a peer
+    def remote?
+      !local?
+    end
+
+    # @return [String, Symbol, Integer, nil] The origin the transaction was
+    #   created with
+    def value
+      yorigin_value
+    end
+
+    # @!method yorigin_local
+    #
+    # @return [true, false]
+    # @!visibility private
+
+    # @!method yorigin_value
+    #
+    # @return [String, Symbol, Integer, nil]
+    # @!visibility private
+  end
+end
@@ -0,0 +1,261 @@
+# frozen_string_literal: true
+
+require "monitor"
+require_relative "sync"
+
+module Y
+  # A room shares a document and its awareness with many connections.
+  #
+  # Every connection is represented by an ID and a callable that sends binary
+  # encoded y-sync messages to the peer, e.g. over a websocket. Messages
+  # received from a peer are passed to {#receive}, which replies to the peer
+  # as the {Y::Sync::Protocol} does. Changes of the document and the awareness
+  # are sent to all connections, except the one they were received from. Each
+  # change is encoded once, no matter how many connections there are.
+  #
+  # Senders are called while the room is locked, and must not block for long.
+  # A sender that raises an error is disconnected.
+  #
+  # @example Share a document with all subscribers of a channel
+  #   room = Y::Room.new(doc)
+  #
+  #   room.connect(connection_id) do |message|
+  #     transmit(message)
+  #   end
+  #
+  #   room.receive(connection_id, data)
+  #   room.disconnect(connection_id)
+  class Room
+    # @return [Y::Doc] The shared document
+    attr_reader :doc
+
+    # @return [Y::Awareness] The shared awareness
+    attr_reader :awareness
+
+    # Creates a room for a document and its awareness
+    #
+    # @param doc [Y::Doc] The document to share
+    # @param awareness [Y::Awareness] The awareness to share, bound to the
+    #   document by default
+    # @param policy [Y::UpdatePolicy, nil] Policy the updates of all
+    #   connections must comply with
+    # @return [Y::Room]
+    def self.new(doc = Y::Doc.new, awareness = Y::Awareness.new(doc),
+                 policy: nil)
+      room = yroom_new
+      room.instance_variable_set(:@doc, doc)
+      room.instance_variable_set(:@awareness, awareness)
+      room.instance_variable_set(:@policy, policy)
+      room.instance_variable_set(:@monitor, Monitor.new)
+      room.send(:subscribe)
+      room
+    end
+
+    private_class_method :yroom_new
+
+    # Connects a peer to the room, and sends it the first step of the sync
+    # handshake and the current awareness state.
+    #
+    # The ID is used as origin of the updates received from the peer.
+    #
+    # @param id [String, Symbol, Integer] Unique ID of the connection
+    # @param sender [#call, nil] Called with every message to send to the
+    #   peer, as ASCII-8BIT string
+    # @yield [message] Called with every message to send, if no sender is
+    #   given
+    # @yieldparam [String] message Binary encoded y-sync message
+    # @return [void]
+    # @raise [ArgumentError] if the ID is already connected
+    def connect(id, sender = nil, &block)
+      sender ||= block
+      raise ArgumentError, "a sender is required" if sender.nil?
+
+      synchronize do
+        protocol = protocol_for(id)
+        yroom_connect(id, sender, protocol)
+        protocol.start(binary: true).each { |message| sender.call(message) }
+      end
+    end
+
+    # @param id [String, Symbol, Integer]
+    # @return [true, false] True if a peer with this ID is connected
+    def connected?(id)
+      synchronize { yroom_connected(id) }
+    end
+
+    # @return [::Array<String, Symbol, Integer>] IDs of all connections
+    def connections
+      synchronize { yroom_connections }
+    end
+
+    # Disconnects a peer, and removes the awareness states of the clients it
+    # has introduced.
+    #
+    # @param id [String, Symbol, Integer]
+    # @return [void]
+    def disconnect(id)
+      synchronize { yroom_disconnect(id) }
+    end
+
+    # Handles a message received from a peer. Replies are sent to the peer,
+    # changes are sent to all other connections.
+    #
+    # @param id [String, Symbol, Integer] The ID of the connection
+    # @param data [::Array<Integer>, String] Binary encoded y-sync message
+    # @return [void]
+    # @raise [ArgumentError] if the ID is not connected
+    # @raise [Y::DecodeError] if the message can not be decoded
+    # @raise [Y::UpdateRejected] if an update violates the policy
+    def receive(id, data)
+      synchronize do
+        protocol = yroom_protocol(id)
+        replies = receiving(id) { protocol.handle(data, binary: true) }
+        replies.each { |reply| yroom_send(id, reply) }
+      end
+    end
+
+    # Removes the awareness states of clients that have not been updated
+    # within the timeout, and sends their removal to all connections
+    #
+    # @see Y::Awareness#remove_outdated
+    # @param timeout [Numeric] Seconds without update after which the state
+    #   of a client is outdated
+    # @return [::Array<Integer>] IDs of the removed clients
+    def remove_outdated(timeout = Awareness::OUTDATED_TIMEOUT)
+      synchronize { awareness.remove_outdated(timeout) }
+    end
+
+    # Stops sending changes to the connections, and disconnects all of them
+    #
+    # @return [void]
+    def close
+      synchronize do
+        doc.detach(@update_subscription)
+        awareness.detach(@awareness_subscription)
+        yroom_connections.each { |id| yroom_disconnect(id) }
+      end
+    end
+
+    private
+
+    # Changes are sent while the room is locked, no matter which thread
+    # changes the document or the awareness. Stale clients can not be removed
+    # while the awareness notifies its observers, so they are left to the
+    # next synchronized call. The listeners are kept in instance variables, as
+    # the native subscriptions do not protect them from the garbage collector.
+    def subscribe
+      @update_listener = proc do |update, origin|
+        synchronize { yroom_broadcast_update(update, origin.value) }
+      end
+      @awareness_listener = proc do |event|
+        @monitor.synchronize { yroom_broadcast_awareness(event) }
+      end
+      @update_subscription = doc.attach(binary: true, &@update_listener)
+      @awareness_subscription = awareness.attach(@awareness_listener)
+    end
+
+    def protocol_for(id)
+      Sync::Protocol.new(doc, awareness, origin: id, policy: @policy)
+    end
+
+    def synchronize
+      @monitor.synchronize do
+        result = yield
+        remove_stale_clients
+        result
+      end
+    end
+
+    def receiving(id)
+      yroom_set_receiving(id)
+      yield
+    ensure
+      yroom_set_receiving(nil)
+    end
+
+    # Removing the states of stale clients notifies the connections, which
+    # disconnects the ones that can not be sent to
+    def remove_stale_clients
+      until (clients = yroom_take_stale_clients).empty?
+        clients.each { |client| awareness.remove_state(client) }
+      end
+    end
+
+    # @!method yroom_new
+    #
+    # @return [Y::Room]
+    # @!visibility private
+
+    # @!method yroom_broadcast_awareness(event)
+    #   Sends the update of an awareness event to all connections, except
+    #   the one the event was received from
+    #
+    # @param event [Y::AwarenessEvent]
+    # @return [nil]
+    # @!visibility private
+
+    # @!method yroom_broadcast_update(update, origin)
+    #   Sends an update message to all connections, except the origin
+    #
+    # @param update [String] Binary encoded update
+    # @param origin [String, Symbol, Integer, nil] The ID of the connection
+    #   the update was received from
+    # @return [nil]
+    # @!visibility private
+
+    # @!method yroom_connect(id, sender, protocol)
+    #
+    # @param id [String, Symbol, Integer]
+    # @param sender [#call]
+    # @param protocol [Y::Sync::Protocol]
+    # @return [nil]
+    # @raise [ArgumentError] if the ID is already connected
+    # @!visibility private
+
+    # @!method yroom_connected(id)
+    #
+    # @param id [String, Symbol, Integer]
+    # @return [true, false]
+    # @!visibility private
+
+    # @!method yroom_connections
+    #
+    # @return [::Array<String, Symbol, Integer>]
+    # @!visibility private
+
+    # @!method yroom_disconnect(id)
+    #
+    # @param id [String, Symbol, Integer]
+    # @return [nil]
+    # @!visibility private
+
+    # @!method yroom_protocol(id)
+    #
+    # @param id [String, Symbol, Integer]
+    # @return [Y::Sync::Protocol]
+    # @raise [ArgumentError] if the ID is not connected
+    # @!visibility private
+
+    # @!method yroom_send(id, message)
+    #
+    # @param id [String, Symbol, Integer]
+    # @param message [String] Binary encoded y-sync message
+    # @return [nil]
+    # @raise [ArgumentError] if the ID is not connected
+    # @!visibility private
+
+    # @!method yroom_set_receiving(id)
+    #   Marks the connection whose message is handled, nil once it is done
+    #
+    # @param id [String, Symbol, Integer, nil]
+    # @return [nil]
+    # @!visibility private
+
+    # @!method yroom_take_stale_clients
+    #   Returns and forgets the clients of disconnected peers, unless a
+    #   message or an awareness event is handled
+    #
+    # @return [::Array<Integer>]
+    # @!visibility private
+  end
+end
@@ -0,0 +1,94 @@
+# frozen_string_literal: true
+
+module Y
+  # A snapshot captures the state of a document at a specific point in time.
+  #
+  # Snapshots are lightweight, they only consist of a state vector and a
+  # delete set. The actual content is restored from the document itself
+  # with {Y::Doc#encode_state_from_snapshot}, which requires the document to
+  # be created with garbage collection disabled.
+  #
+  # @example Store a snapshot and restore the document later on
+  #   doc = Y::Doc.new(skip_gc: true)
+  #   text = doc.get_text("my text")
+  #   text << "Hello"
+  #
+  #   encoded = doc.snapshot.encode
+  #   text << ", World!"
+  #
+  #   snapshot = Y::Snapshot.decode(encoded)
+  #   past = Y::Doc.new
+  #   past.sync(doc.encode_state_from_snapshot(snapshot))
+  #   past.get_text("my text").to_s #=> "Hello"
+  class Snapshot
+    # Decodes a binary encoded snapshot
+    #
+    # @param encoded [::Array<Integer>, String] Array of bytes or binary string
+    # @return [Y::Snapshot]
+    def self.decode(encoded)
+      ysnapshot_decode(encoded)
+    end
+
+    # Decodes a v2 binary encoded snapshot
+    #
+    # @param encoded [::Array<Integer>, String] Array of bytes or binary string
+    # @return [Y::Snapshot]
+    def self.decode_v2(encoded)
+      ysnapshot_decode_v2(encoded)
+    end
+
+    private_class_method :ysnapshot_decode, :ysnapshot_decode_v2
+
+    # Encodes the snapshot
+    #
+    # @return [::Array<Integer>] Binary encoded snapshot
+    def encode
+      ysnapshot_encode
+    end
+
+    # Encodes the snapshot in the v2 format
+    #
+    # @return [::Array<Integer>] Binary encoded snapshot
+    def encode_v2
+      ysnapshot_encode_v2
+    end
+
+    # Two snapshots are equal if they capture the same document state
+    #
+    # @param other [Object]
+    # @return [true, false]
+    def ==(other)
+      other.is_a?(Snapshot) && ysnapshot_equals(other)
+    end
+
+    # @!method ysnapshot_decode(encoded)
+    #   Decodes a binary encoded snapshot
+    #
+    # @param encoded [Array<Integer>, String]
+    # @return [Y::Snapshot]
+    # @!visibility private
+
+    # @!method ysnapshot_decode_v2(encoded)
+    #   Decodes a v2 binary encoded snapshot
+    #
+    # @param encoded [Array<Integer>, String]
+    # @return [Y::Snapshot]
+    # @!visibility private
+
+    # @!method ysnapshot_encode
+    #
+    # @return [Array<Integer>]
+    # @!visibility private
+
+    # @!method ysnapshot_encode_v2
+    #
+    # @return [Array<Integer>]
+    # @!visibility private
+
+    # @!method ysnapshot_equals(other)
+    #
+    # @param other [Y::Snapshot]
+    # @return [true, false]
+    # @!visibility private
+  end
+end
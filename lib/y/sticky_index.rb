@@ -0,0 +1,146 @@
+# frozen_string_literal: true
+
+require "json"
+
+module Y
+  # A sticky index is a position within a text, array or XMLText that is
+  # permanently attached to the content around it. Unlike a plain index, it
+  # does not shift when content is inserted or removed before it, which makes
+  # it a good fit for cursors and anchors (e.g. for comments).
+  #
+  # Sticky indexes can be stored (binary or JSON encoded) and resolved to an
+  # absolute index at any time later, even after synchronizing the document
+  # with remote peers.
+  #
+  # @example Anchor a position within a text
+  #   doc = Y::Doc.new
+  #   text = doc.get_text("my text")
+  #   text << "Hello, World!"
+  #
+  #   anchor = text.sticky_index(7) # right before "World!"
+  #   text.insert(0, ">> ")
+  #
+  #   anchor.offset(doc) # 10
+  #
+  # @example Persist a sticky index as JSON
+  #   json = anchor.to_json
+  #
+  #   anchor = Y::StickyIndex.from_json(json)
+  #   anchor.offset(doc) # 10
+  class StickyIndex
+    # Decodes a binary encoded sticky index
+    #
+    # @param encoded [::Array<Integer>, String] Array of bytes or binary string
+    # @return [Y::StickyIndex]
+    def self.decode(encoded)
+      ysticky_index_decode(encoded)
+    end
+
+    # Creates a sticky index from its Hash representation
+    #
+    # The format is compatible with the JSON representation of relative
+    # positions in Yjs, keys can be either Strings or Symbols.
+    #
+    # @param hash [Hash]
+    # @return [Y::StickyIndex]
+    def self.from_h(hash)
+      ysticky_index_from_h(hash)
+    end
+
+    # Creates a sticky index from its JSON representation
+    #
+    # @param json [String]
+    # @return [Y::StickyIndex]
+    def self.from_json(json)
+      from_h(JSON.parse(json))
+    end
+
+    private_class_method :ysticky_index_decode, :ysticky_index_from_h
+
+    # Returns whether the index sticks to the element after or before it
+    #
+    # @return [Symbol] Either `:after` or `:before`
+    def assoc
+      ysticky_index_assoc
+    end
+
+    # Encodes the sticky index
+    #
+    # @return [::Array<Integer>] Binary encoded sticky index
+    def encode
+      ysticky_index_encode
+    end
+
+    # Resolves the sticky index to an absolute index within its shared type
+    #
+    # @param document [Y::Doc] The document the sticky index belongs to
+    # @return [Integer, nil] Returns nil if the position cannot be resolved,
+    #   e.g. because the shared type does not exist (yet) in the document
+    def offset(document)
+      document.current_transaction { |tx| ysticky_index_offset(tx) }
+    end
+
+    # Returns the Hash representation of the sticky index
+    #
+    # @return [Hash]
+    def to_h
+      ysticky_index_to_h
+    end
+
+    # Returns the JSON representation of the sticky index
+    #
+    # @return [String]
+    def to_json(*args)
+      to_h.to_json(*args)
+    end
+
+    # Two sticky indexes are equal if they point to the same position
+    #
+    # @param other [Object]
+    # @return [true, false]
+    def ==(other)
+      other.is_a?(StickyIndex) && ysticky_index_equals(other)
+    end
+
+    # @!method ysticky_index_decode(encoded)
+    #   Decodes a binary encoded sticky index
+    #
+    # @param encoded [Array<Integer>, String]
+    # @return [Y::StickyIndex]
+    # @!visibility private
+
+    # @!method ysticky_index_from_h(hash)
+    #   Creates a sticky index from a Hash
+    #
+    # @param hash [Hash]
+    # @return [Y::StickyIndex]
+    # @!visibility private
+
+    # @!method ysticky_index_assoc
+    #
+    # @return [Symbol]
+    # @!visibility private
+
+    # @!method ysticky_index_encode
+    #
+    # @return [Array<Integer>]
+    # @!visibility private
+
+    # @!method ysticky_index_equals(other)
+    #
+    # @param other [Y::StickyIndex]
+    # @return [true, false]
+    # @!visibility private
+
+    # @!method ysticky_index_offset(tx)
+    #
+    # @param tx [Y::Transaction]
+    # @return [Integer, nil]
+    # @!visibility private
+
+    # @!method ysticky_index_to_h
+    #
+    # @return [Hash]
+    # @!visibility private
+  end
+end
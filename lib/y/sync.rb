@@ -0,0 +1,459 @@
+# frozen_string_literal: true
+
+require_relative "error"
+
+module Y
+  # Implementation of the y-sync protocol, that is used by y-websocket and
+  # other providers to synchronize documents and awareness between peers.
+  module Sync
+    # Raised when a peer denies the permission to access a document
+    class PermissionDenied < Error
+      # @return [String] The reason given by the peer
+      attr_reader :reason
+
+      # @param reason [String]
+      def initialize(reason)
+        super("permission denied: #{reason}")
+
+        @reason = reason
+      end
+    end
+
+    # Raised when a message of a custom type is received, but the protocol
+    # has no handler for custom messages
+    class UnsupportedMessage < Error
+      # @return [Integer] The tag of the message
+      attr_reader :tag
+
+      # @param tag [Integer]
+      def initialize(tag)
+        super("unsupported message tag: #{tag}")
+
+        @tag = tag
+      end
+    end
+
+    # A message of the y-sync protocol
+    #
+    # Messages are encoded with the same framing as y-protocols in Yjs (a
+    # variable length message type, followed by the message content), so they
+    # can be exchanged with y-websocket clients as they are. A message does not
+    # include the name of the document (or room) it belongs to, this must be
+    # handled by the transport, e.g. by the URL of the websocket.
+    #
+    # The type of a message is one of:
+    #
+    # - `:sync_step1` carries the state vector of the sender and asks the
+    #   receiver for the updates the sender is missing
+    # - `:sync_step2` carries the updates missing from a received state vector
+    # - `:update` carries an incremental update of the document
+    # - `:awareness` carries an awareness update
+    # - `:query_awareness` asks the receiver for its awareness states
+    # - `:auth` tells a client whether it may access the document
+    # - `:custom` carries application specific data with a custom tag
+    #
+    # @example Answer the first sync step of a client
+    #   message = Y::Sync::Message.decode(data)
+    #   if message.type == :sync_step1
+    #     diff = doc.diff(message.payload)
+    #     socket.write(Y::Sync::Message.sync_step2(diff).encode(binary: true))
+    #   end
+    class Message
+      # Creates an auth message
+      #
+      # Without a reason, the message grants permission to access the
+      # document, with a reason it denies it.
+      #
+      # @param reason [String, nil] The reason why permission is denied
+      # @return [Y::Sync::Message]
+      def self.auth(reason = nil)
+        ysync_message_auth(reason)
+      end
+
+      # Creates an awareness message
+      #
+      # @param update [::Array<Integer>, String] Binary encoded awareness
+      #   update, e.g. created by {Y::Awareness#diff}
+      # @return [Y::Sync::Message]
+      # @raise [Y::DecodeError] if the awareness update is malformed
+      def self.awareness(update)
+        ysync_message_awareness(update)
+      end
+
+      # Creates a message of an application specific type
+      #
+      # @param tag [Integer] The message type, between 4 and 127, as the types
+      #   0 to 3 are used by the protocol
+      # @param data [::Array<Integer>, String] The content of the message
+      # @return [Y::Sync::Message]
+      # @raise [ArgumentError] if the tag is outside of the allowed range
+      def self.custom(tag, data)
+        ysync_message_custom(tag, data)
+      end
+
+      # Decodes a binary encoded message
+      #
+      # @param encoded [::Array<Integer>, String] Array of bytes or binary
+      #   string
+      # @return [Y::Sync::Message]
+      # @raise [Y::DecodeError] if the message is malformed
+      def self.decode(encoded)
+        ysync_message_decode(encoded)
+      end
+
+      # Creates a message that asks for the awareness states of the receiver
+      #
+      # @return [Y::Sync::Message]
+      def self.query_awareness
+        ysync_message_query_awareness
+      end
+
+      # Creates the first message of the sync handshake
+      #
+      # @param state_vector [::Array<Integer>, String] Binary encoded state
+      #   vector of the sender, e.g. created by {Y::Doc#state}
+      # @return [Y::Sync::Message]
+      # @raise [Y::DecodeError] if the state vector is malformed
+      def self.sync_step1(state_vector)
+        ysync_message_sync_step1(state_vector)
+      end
+
+      # Creates the second message of the sync handshake
+      #
+      # @param update [::Array<Integer>, String] Binary encoded update, with
+      #   everything missing from the state vector of the first step
+      # @return [Y::Sync::Message]
+      def self.sync_step2(update)
+        ysync_message_sync_step2(update)
+      end
+
+      # Creates an update message
+      #
+      # @param update [::Array<Integer>, String] Binary encoded update
+      # @return [Y::Sync::Message]
+      def self.update(update)
+        ysync_message_update(update)
+      end
+
+      private_class_method :ysync_message_auth, :ysync_message_awareness,
+                           :ysync_message_custom, :ysync_message_decode,
+                           :ysync_message_query_awareness,
+                           :ysync_message_sync_step1, :ysync_message_sync_step2,
+                           :ysync_message_update
+
+      # Encodes the message
+      #
+      # @param binary [true, false] Return an ASCII-8BIT string instead of an
+      #   array of bytes
+      # @return [::Array<Integer>, String] Binary encoded message
+      def encode(binary: false)
+        encoded = ysync_message_encode
+        binary ? encoded : encoded.bytes
+      end
+
+      # Returns the content of the message
+      #
+      # This is the state vector of a `:sync_step1` message, the update of
+      # `:sync_step2`, `:update` and `:awareness` messages, and the data of a
+      # `:custom` message. Other messages have no payload.
+      #
+      # @param binary [true, false] Return an ASCII-8BIT string instead of an
+      #   array of bytes
+      # @return [::Array<Integer>, String, nil] Binary encoded payload
+      def payload(binary: false)
+        payload = ysync_message_payload
+        return if payload.nil?
+
+        binary ? payload : payload.bytes
+      end
+
+      # Returns the reason why permission is denied by an `:auth` message
+      #
+      # @return [String, nil] The reason, or nil if permission is granted or
+      #   this is not an `:auth` message
+      def reason
+        ysync_message_reason
+      end
+
+      # Returns the message type as it is encoded, e.g. `0` for all sync
+      # messages, or the tag of a `:custom` message
+      #
+      # @return [Integer]
+      def tag
+        ysync_message_tag
+      end
+
+      # Returns the type of the message
+      #
+      # @return [Symbol] One of `:sync_step1`, `:sync_step2`, `:update`,
+      #   `:awareness`, `:query_awareness`, `:auth` or `:custom`
+      def type
+        ysync_message_type
+      end
+
+      # Two messages are equal if they have the same type and content
+      #
+      # @param other [Object]
+      # @return [true, false]
+      def ==(other)
+        other.is_a?(Message) && ysync_message_equals(other)
+      end
+
+      # @!method self.ysync_message_auth(reason)
+      #
+      # @param reason [String, nil]
+      # @return [Y::Sync::Message]
+      # @!visibility private
+
+      # @!method self.ysync_message_awareness(update)
+      #
+      # @param update [Array<Integer>, String]
+      # @return [Y::Sync::Message]
+      # @!visibility private
+
+      # @!method self.ysync_message_custom(tag, data)
+      #
+      # @param tag [Integer]
+      # @param data [Array<Integer>, String]
+      # @return [Y::Sync::Message]
+      # @!visibility private
+
+      # @!method self.ysync_message_decode(encoded)
+      #
+      # @param encoded [Array<Integer>, String]
+      # @return [Y::Sync::Message]
+      # @!visibility private
+
+      # @!method self.ysync_message_query_awareness
+      #
+      # @return [Y::Sync::Message]
+      # @!visibility private
+
+      # @!method self.ysync_message_sync_step1(state_vector)
+      #
+      # @param state_vector [Array<Integer>, String]
+      # @return [Y::Sync::Message]
+      # @!visibility private
+
+      # @!method self.ysync_message_sync_step2(update)
+      #
+      # @param update [Array<Integer>, String]
+      # @return [Y::Sync::Message]
+      # @!visibility private
+
+      # @!method self.ysync_message_update(update)
+      #
+      # @param update [Array<Integer>, String]
+      # @return [Y::Sync::Message]
+      # @!visibility private
+
+      # @!method ysync_message_encode
+      #
+      # @return [String]
+      # @!visibility private
+
+      # @!method ysync_message_equals(other)
+      #
+      # @param other [Y::Sync::Message]
+      # @return [true, false]
+      # @!visibility private
+
+      # @!method ysync_message_payload
+      #
+      # @return [String, nil]
+      # @!visibility private
+
+      # @!method ysync_message_reason
+      #
+      # @return [String, nil]
+      # @!visibility private
+
+      # @!method ysync_message_tag
+      #
+      # @return [Integer]
+      # @!visibility private
+
+      # @!method ysync_message_type
+      #
+      # @return [Symbol]
+      # @!visibility private
+    end
+
+    # Handles the messages of a single peer for the server side of the
+    # y-sync protocol, like `DefaultProtocol` of the y-sync crate does.
+    #
+    # A protocol is created for every connection, and shares the document and
+    # the awareness with the protocols of all other connections to the same
+    # document. Incoming messages are applied to the document or awareness,
+    # and the messages to reply with are returned:
+    #
+    # - `:sync_step1` is answered with a `:sync_step2` message, that contains
+    #   everything the peer is missing
+    # - `:sync_step2` and `:update` messages are applied to the document
+    # - `:awareness` messages are applied to the awareness
+    # - `:query_awareness` is answered with the state of all known clients
+    # - `:auth` and `:custom` messages are passed to the hooks registered with
+    #   {#on_auth} and {#on_custom}
+    #
+    # Updates are applied with the origin and policy given to the protocol,
+    # so observers of the document can tell which peer made a change (and do
+    # not need to send it back), and peers can be restricted to the changes
+    # they are allowed to make.
+    #
+    # @example Sync a document with a y-websocket client
+    #   protocol = Y::Sync::Protocol.new(doc, awareness, origin: client_id)
+    #   protocol.start(binary: true).each { |reply| socket.write(reply) }
+    #
+    #   socket.each_message do |data|
+    #     replies = protocol.handle(data, binary: true)
+    #     replies.each { |reply| socket.write(reply) }
+    #   end
+    #
+    # @example Only allow a peer to edit the text of a document
+    #   policy = Y::UpdatePolicy.new(roots: ["content"])
+    #   protocol = Y::Sync::Protocol.new(doc, awareness, policy: policy)
+    class Protocol
+      # @return [Y::Doc] The document that is synchronized
+      attr_reader :doc
+
+      # @return [Y::Awareness] The awareness that is synchronized
+      attr_reader :awareness
+
+      # Creates a protocol for a single peer
+      #
+      # @param doc [Y::Doc] The document to synchronize
+      # @param awareness [Y::Awareness] The awareness to synchronize, bound to
+      #   the document by default
+      # @param origin [String, Symbol, Integer, nil] Origin of the updates
+      #   applied on behalf of the peer, e.g. its client id
+      # @param policy [Y::UpdatePolicy, nil] Policy the updates of the peer
+      #   must comply with
+      def initialize(doc = Y::Doc.new, awareness = Y::Awareness.new(doc),
+                     origin: nil, policy: nil)
+        @doc = doc
+        @awareness = awareness
+        @origin = origin
+        @policy = policy
+        @auth_handler = nil
+        @custom_handler = nil
+      end
+
+      # Registers a handler for incoming `:auth` messages
+      #
+      # Without a handler, an `:auth` message that denies the permission to
+      # access the document raises {Y::Sync::PermissionDenied}.
+      #
+      # @yield [message] Called for every `:auth` message
+      # @yieldparam [Y::Sync::Message] message The auth message
+      # @yieldreturn [Y::Sync::Message, ::Array<Y::Sync::Message>, nil]
+      #   Messages to reply with
+      # @return [self]
+      def on_auth(&block)
+        @auth_handler = block
+        self
+      end
+
+      # Registers a handler for incoming messages of a custom type
+      #
+      # Without a handler, custom messages raise {Y::Sync::UnsupportedMessage}.
+      #
+      # @example Answer pings of a peer
+      #   protocol.on_custom do |message|
+      #     Y::Sync::Message.custom(PONG, []) if message.tag == PING
+      #   end
+      #
+      # @yield [message] Called for every custom message
+      # @yieldparam [Y::Sync::Message] message The custom message
+      # @yieldreturn [Y::Sync::Message, ::Array<Y::Sync::Message>, nil]
+      #   Messages to reply with
+      # @return [self]
+      def on_custom(&block)
+        @custom_handler = block
+        self
+      end
+
+      # Returns the messages to send when the connection has been established:
+      # the first step of the sync handshake and the current awareness state.
+      #
+      # @param binary [true, false] Return ASCII-8BIT strings instead of
+      #   arrays of bytes
+      # @return [::Array<::Array<Integer>, String>] Binary encoded messages
+      def start(binary: false)
+        messages = [
+          Message.sync_step1(doc.state(binary: true)),
+          Message.awareness(awareness.diff(binary: true))
+        ]
+        messages.map { |message| message.encode(binary: binary) }
+      end
+
+      # Handles a binary encoded message of the peer
+      #
+      # @param data [::Array<Integer>, String] Binary encoded message
+      # @param binary [true, false] Return ASCII-8BIT strings instead of
+      #   arrays of bytes
+      # @return [::Array<::Array<Integer>, String>] Binary encoded messages to
+      #   reply with
+      # @raise [Y::DecodeError] if the message can not be decoded
+      # @raise [Y::UpdateRejected] if an update violates the policy
+      # @raise [Y::Sync::PermissionDenied] if the peer denies access
+      # @raise [Y::Sync::UnsupportedMessage] if a custom message is received
+      #   without a handler
+      def handle(data, binary: false)
+        replies = handle_message(Message.decode(data))
+        replies.map { |reply| reply.encode(binary: binary) }
+      end
+
+      # Handles a decoded message of the peer
+      #
+      # @see #handle
+      # @param message [Y::Sync::Message]
+      # @return [::Array<Y::Sync::Message>] Messages to reply with
+      def handle_message(message)
+        replies =
+          case message.type
+          when :sync_step1 then sync_step2(message)
+          when :sync_step2, :update then apply_update(message)
+          when :awareness then apply_awareness(message)
+          when :query_awareness then query_awareness
+          when :auth then auth(message)
+          when :custom then custom(message)
+          end
+        Array(replies)
+      end
+
+      private
+
+      def sync_step2(message)
+        diff = doc.diff(message.payload(binary: true), binary: true)
+        Message.sync_step2(diff)
+      end
+
+      def apply_update(message)
+        update = message.payload(binary: true)
+        doc.sync(update, origin: @origin, policy: @policy)
+        nil
+      end
+
+      def apply_awareness(message)
+        awareness.sync(message.payload(binary: true))
+        nil
+      end
+
+      def query_awareness
+        Message.awareness(awareness.diff(binary: true))
+      end
+
+      def auth(message)
+        return @auth_handler.call(message) unless @auth_handler.nil?
+
+        raise PermissionDenied, message.reason unless message.reason.nil?
+      end
+
+      def custom(message)
+        raise UnsupportedMessage, message.tag if @custom_handler.nil?
+
+        @custom_handler.call(message)
+      end
+    end
+  end
+end
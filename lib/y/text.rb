@@ -17,7 +17,7 @@ module Y
   #
   #   text << "Hello, World!"
   #   puts text.to_s
-  class Text
+  class Text # rubocop:disable Metrics/ClassLength
     # @!attribute [r] document
     #
     # @return [Y::Doc] The document this text belongs to
@@ -63,7 +63,13 @@ module Y
     #   # todo: required, otherwise segfault
     #   local.commit
     #
-    # @param callback [Proc]
+    # @example Distinguish local and remote changes
+    #   text.attach do |delta, origin|
+    #     pp delta if origin.remote?
+    #   end
+    #
+    # @param callback [Proc] Receives the delta, and the {Y::Origin} of the
+    #   transaction if it accepts a second argument
     # @param block [Block]
     # @return [Integer]
     def attach(callback, &block)
@@ -183,6 +189,30 @@ module Y
 
     # rubocop:disable Metrics/AbcSize, Metrics/CyclomaticComplexity, Metrics/MethodLength
 
+    # Attach a listener to changes of this text and of shared types embedded
+    # into it
+    #
+    # See {Y::Map#observe_deep} for the structure of the events. Listeners
+    # accepting a second argument receive the {Y::Origin} of the transaction.
+    #
+    # @param callback [Proc]
+    # @param block [Block]
+    # @return [Integer] The subscription ID
+    def observe_deep(callback = nil, &block)
+      listener = callback || block&.to_proc
+      return if listener.nil?
+
+      ytext_observe_deep(listener, document)
+    end
+
+    # Detach a listener attached with {#observe_deep}
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    def unobserve_deep(subscription_id)
+      ytext_unobserve_deep(subscription_id)
+    end
+
     # Removes a part from text
     #
     # **Attention:** In comparison to String#slice, {Text#slice!} will not
@@ -268,6 +298,22 @@ module Y
 
     # rubocop:enable Metrics/AbcSize, Metrics/CyclomaticComplexity, Metrics/MethodLength
 
+    # Creates a sticky index at the given position
+    #
+    # In contrast to a plain index, a sticky index keeps pointing to the same
+    # position, even when content is inserted or removed before it, e.g. by
+    # remote peers. Use {Y::StickyIndex#offset} to resolve it to an index.
+    #
+    # @param index [Integer]
+    # @param assoc [Symbol] Associate the index with the element `:after`
+    #   (default) or `:before` the given position
+    # @return [Y::StickyIndex, nil] Returns nil if the index is out of bounds
+    def sticky_index(index, assoc: :after)
+      document.current_transaction do |tx|
+        ytext_sticky_index(tx, index, assoc)
+      end
+    end
+
     # Returns string representation of text
     #
     # @example
@@ -369,6 +415,21 @@ module Y
     # @param proc [Proc]
     # @return [Integer]
 
+    # @!method ytext_observe_deep(proc, document)
+    #   Observe changes of this type and all nested types
+    #
+    # @param proc [Proc]
+    # @param document [Y::Doc]
+    # @return [Integer]
+
+    # @!method ytext_sticky_index(tx, index, assoc)
+    #   Creates a sticky index at the given position
+    #
+    # @param tx [Y::Transaction]
+    # @param index [Integer]
+    # @param assoc [Symbol]
+    # @return [Y::StickyIndex, nil]
+
     # @!method ytext_to_s()
     #   Returns string representation of text
     #
@@ -379,5 +440,11 @@ module Y
     #
     # @param subscription_id [Integer]
     # @return [void]
+
+    # @!method ytext_unobserve_deep(subscription_id)
+    #   Detach deep listener
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
   end
 end
@@ -16,22 +16,43 @@ module Y
     # Applies the encoded update on this document. This will bring the
     # the document to the same state as the one the update is from.
     #
-    # @param update [::Array<Integer>]
+    # @param update [::Array<Integer>, String] Array of bytes or binary string
+    # @param policy [Y::UpdatePolicy, nil] Optional policy the update must
+    #   comply with, see {#validate_update}
     # @return [void]
-    def apply(update)
+    # @raise [Y::DecodeError] If the update can not be decoded
+    # @raise [Y::UpdateRejected] If the update violates the policy
+    # @raise [Y::TransactionError] If the transaction is read-only or no
+    #   longer active
+    def apply(update, policy: nil)
+      violation = policy && validate_update(update, policy)
+      raise violation if violation
+
       ytransaction_apply_update(update)
     end
 
     # Applies the v2 encoded update on this document. This will bring the
     # the document to the same state as the one the update is from.
     #
-    # @param update [::Array<Integer>]
+    # @param update [::Array<Integer>, String] Array of bytes or binary string
+    # @param policy [Y::UpdatePolicy, nil] Optional policy the update must
+    #   comply with, see {#validate_update_v2}
     # @return [void]
-    def apply_v2(update)
+    # @raise [Y::DecodeError] If the update can not be decoded
+    # @raise [Y::UpdateRejected] If the update violates the policy
+    # @raise [Y::TransactionError] If the transaction is read-only or no
+    #   longer active
+    def apply_v2(update, policy: nil)
+      violation = policy && validate_update_v2(update, policy)
+      raise violation if violation
+
       ytransaction_apply_update_v2(update)
     end
 
-    # Commits transaction
+    # Commits transaction, this is a no-op for read-only transactions
+    #
+    # Observers are called while committing, the first error raised by one of
+    # them is raised once all of them have been called.
     #
     # @return [void]
     def commit
@@ -98,31 +119,85 @@ module Y
       xml_text
     end
 
+    # Whether this transaction can only be used to read the document, see
+    # {Y::Doc#read}
+    #
+    # @return [true, false]
+    def read_only?
+      ytransaction_read_only
+    end
+
+    # Captures the current state of the document
+    #
+    # @return [Y::Snapshot]
+    def snapshot
+      ytransaction_snapshot
+    end
+
     # Return a state vector for this transaction
     #
-    # @return [::Array<Integer>]
-    def state
-      ytransaction_state_vector
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String]
+    def state(binary: false)
+      state = ytransaction_state_vector
+      binary ? state : state.bytes
     end
 
     # Returns a v2 state vector for this transaction
     #
-    # @return [::Array<Integer>]
-    def state_v2
-      ytransaction_state_vector_v2
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String]
+    def state_v2(binary: false)
+      state = ytransaction_state_vector_v2
+      binary ? state : state.bytes
+    end
+
+    # Checks whether the encoded update complies with a policy, without
+    # applying it
+    #
+    # The update is summarized into the clients it contains and the root
+    # types it modifies, with the kind of content inserted directly into each
+    # root type, e.g.
+    # `{ clients: [1234], roots: { "my text" => [{ kind: :string,
+    # key: false }] } }, unresolved: false }`. Root types that are only
+    # modified by deletions or changes to nested types are listed without
+    # content. `unresolved` is true if the update contains blocks or
+    # deletions that depend on content neither the update nor the document
+    # contain.
+    #
+    # @param update [::Array<Integer>, String] Array of bytes or binary string
+    # @param policy [Y::UpdatePolicy]
+    # @return [Y::UpdateRejected, nil] The violation, or nil if the update
+    #   complies with the policy
+    def validate_update(update, policy)
+      policy.violation(update) { ytransaction_update_summary(update) }
+    end
+
+    # Checks whether the v2 encoded update complies with a policy, without
+    # applying it
+    #
+    # @see #validate_update
+    # @param update [::Array<Integer>, String] Array of bytes or binary string
+    # @param policy [Y::UpdatePolicy]
+    # @return [Y::UpdateRejected, nil] The violation, or nil if the update
+    #   complies with the policy
+    def validate_update_v2(update, policy)
+      policy.violation(update) { ytransaction_update_summary_v2(update) }
     end
 
     # @!method ytransaction_apply_update(update)
     #   Apply the encoded update within current transaction
     #
-    # @param update [::Array<Integer>]
+    # @param update [::Array<Integer>, String]
     # @return [void]
     # @!visibility private
 
     # @!method ytransaction_apply_update_v2(update)
     #   Apply the v2 encoded update within current transaction
     #
-    # @param update [::Array<Integer>]
+    # @param update [::Array<Integer>, String]
     # @return [void]
     # @!visibility private
 
@@ -176,14 +251,38 @@ module Y
     # @return [Y::XMLElement] XMLElement structure
     # @!visibility private
 
+    # @!method ytransaction_read_only
+    #
+    # @return [true, false]
+    # @!visibility private
+
+    # @!method ytransaction_snapshot
+    #
+    # @return [Y::Snapshot]
+    # @!visibility private
+
     # @!method ytransaction_state_vector
     #
-    # @return [Array<Integer>]
+    # @return [String]
     # @!visibility private
 
     # @!method ytransaction_state_vector_v2
     #
-    # @return [Array<Integer>]
+    # @return [String]
+    # @!visibility private
+
+    # @!method ytransaction_update_summary(update)
+    #   Summarizes the clients and root types of an update
+    #
+    # @param update [::Array<Integer>, String]
+    # @return [Hash{Symbol => Object}]
+    # @!visibility private
+
+    # @!method ytransaction_update_summary_v2(update)
+    #   Summarizes the clients and root types of a v2 update
+    #
+    # @param update [::Array<Integer>, String]
+    # @return [Hash{Symbol => Object}]
     # @!visibility private
   end
 end
@@ -0,0 +1,284 @@
+# frozen_string_literal: true
+
+module Y
+  # An undo manager tracks changes of one or more shared types and allows to
+  # revert (undo) and re-apply (redo) them.
+  #
+  # Changes are not tracked per transaction, but grouped together when they
+  # happen within the capture timeout (500ms by default). Call
+  # {#stop_capturing} to explicitly start a new group of changes.
+  #
+  # By default, only changes made in transactions without an origin are
+  # tracked. If a list of tracked origins is provided, only changes made in
+  # transactions with one of those origins are tracked (see {Y::Doc#transact}).
+  #
+  # @example Undo and redo changes to a text
+  #   doc = Y::Doc.new
+  #   text = doc.get_text("my text")
+  #
+  #   manager = Y::UndoManager.new(doc, text)
+  #
+  #   text << "Hello, World!"
+  #   manager.undo
+  #   text.to_s # ""
+  #
+  #   manager.redo
+  #   text.to_s # "Hello, World!"
+  #
+  # @example Only track changes made by the local user
+  #   doc = Y::Doc.new
+  #   text = doc.get_text("my text")
+  #
+  #   manager = Y::UndoManager.new(doc, text, tracked_origins: [:local])
+  #
+  #   doc.transact(origin: :local) { text << "Hello" }
+  #   doc.transact(origin: :remote) { text << ", World!" }
+  #
+  #   manager.undo
+  #   text.to_s # ", World!"
+  class UndoManager
+    # @!attribute [r] document
+    #
+    # @return [Y::Doc] The document this undo manager belongs to
+    attr_accessor :document
+
+    # Create a new undo manager for the given shared types
+    #
+    # @param document [Y::Doc]
+    # @param scope [::Array<Y::Text, Y::Array, Y::Map, Y::XMLElement,
+    #   Y::XMLFragment, Y::XMLText>] Shared types to track, all of them must
+    #   belong to the given document
+    # @param capture_timeout [Integer, nil] Changes within this timespan (in
+    #   milliseconds) are grouped together, defaults to 500ms
+    # @param tracked_origins [::Array<String, Symbol, Integer>] Track only
+    #   changes made in transactions with one of these origins
+    # @return [Y::UndoManager]
+    def self.new(document, *scope, capture_timeout: nil, tracked_origins: [])
+      manager = yundo_manager_new(document, scope, capture_timeout,
+                                  tracked_origins)
+      manager.document = document
+      manager
+    end
+
+    private_class_method :yundo_manager_new
+
+    # Add another shared type to the list of tracked types
+    #
+    # @param type [Y::Text, Y::Array, Y::Map, Y::XMLElement, Y::XMLFragment,
+    #   Y::XMLText]
+    # @return [void]
+    def add_scope(type)
+      yundo_manager_expand_scope(type)
+    end
+
+    # Removes all captured changes from the undo and redo stack
+    #
+    # @return [void]
+    def clear
+      yundo_manager_clear
+    end
+
+    # Start tracking changes made in transactions with the given origin
+    #
+    # @param origin [String, Symbol, Integer]
+    # @return [void]
+    def include_origin(origin)
+      yundo_manager_include_origin(origin)
+    end
+
+    # Stop tracking changes made in transactions with the given origin
+    #
+    # @param origin [String, Symbol, Integer]
+    # @return [void]
+    def exclude_origin(origin)
+      yundo_manager_exclude_origin(origin)
+    end
+
+    # Attach a listener that is called when a new stack item is added
+    #
+    # The listener receives a Hash with the `kind` (`:undo` or `:redo`) and
+    # the `origin` of the transaction that caused the change.
+    #
+    # @param callback [Proc]
+    # @param block [Block]
+    # @return [Integer] The subscription ID
+    def on_item_added(callback = nil, &block)
+      return yundo_manager_observe_item_added(callback) unless callback.nil?
+
+      yundo_manager_observe_item_added(block.to_proc) unless block.nil?
+    end
+
+    # Attach a listener that is called when a stack item is popped, as a
+    # result of {#undo} or {#redo}
+    #
+    # @param callback [Proc]
+    # @param block [Block]
+    # @return [Integer] The subscription ID
+    def on_item_popped(callback = nil, &block)
+      return yundo_manager_observe_item_popped(callback) unless callback.nil?
+
+      yundo_manager_observe_item_popped(block.to_proc) unless block.nil?
+    end
+
+    # Attach a listener that is called when an existing stack item is extended
+    # by changes within the capture timeout
+    #
+    # @param callback [Proc]
+    # @param block [Block]
+    # @return [Integer] The subscription ID
+    def on_item_updated(callback = nil, &block)
+      return yundo_manager_observe_item_updated(callback) unless callback.nil?
+
+      yundo_manager_observe_item_updated(block.to_proc) unless block.nil?
+    end
+
+    # Detach an item added listener
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    def off_item_added(subscription_id)
+      yundo_manager_unobserve_item_added(subscription_id)
+    end
+
+    # Detach an item popped listener
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    def off_item_popped(subscription_id)
+      yundo_manager_unobserve_item_popped(subscription_id)
+    end
+
+    # Detach an item updated listener
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    def off_item_updated(subscription_id)
+      yundo_manager_unobserve_item_updated(subscription_id)
+    end
+
+    # Re-applies the last undone change
+    #
+    # @return [true, false] True if a change has been re-applied
+    def redo
+      yundo_manager_redo
+    end
+
+    # @return [true, false] True if there is at least one change to redo
+    def redo?
+      yundo_manager_can_redo
+    end
+
+    # Stops grouping changes, the next change will be a separate stack item
+    #
+    # @return [void]
+    def stop_capturing
+      yundo_manager_stop_capturing
+    end
+
+    # Reverts the last change
+    #
+    # @return [true, false] True if a change has been reverted
+    def undo
+      yundo_manager_undo
+    end
+
+    # @return [true, false] True if there is at least one change to undo
+    def undo?
+      yundo_manager_can_undo
+    end
+
+    # @!method yundo_manager_new(document, scope, timeout, origins)
+    #   Creates a new undo manager
+    #
+    # @param document [Y::Doc]
+    # @param scope [::Array<Object>]
+    # @param timeout [Integer, nil]
+    # @param origins [::Array<String, Symbol, Integer>]
+    # @return [Y::UndoManager]
+    # @!visibility private
+
+    # @!method yundo_manager_can_redo
+    #
+    # @return [true, false]
+    # @!visibility private
+
+    # @!method yundo_manager_can_undo
+    #
+    # @return [true, false]
+    # @!visibility private
+
+    # @!method yundo_manager_clear
+    #
+    # @return [void]
+    # @!visibility private
+
+    # @!method yundo_manager_exclude_origin(origin)
+    #
+    # @param origin [String, Symbol, Integer]
+    # @return [void]
+    # @!visibility private
+
+    # @!method yundo_manager_expand_scope(type)
+    #
+    # @param type [Object]
+    # @return [void]
+    # @!visibility private
+
+    # @!method yundo_manager_include_origin(origin)
+    #
+    # @param origin [String, Symbol, Integer]
+    # @return [void]
+    # @!visibility private
+
+    # @!method yundo_manager_observe_item_added(proc)
+    #
+    # @param proc [Proc]
+    # @return [Integer]
+    # @!visibility private
+
+    # @!method yundo_manager_observe_item_popped(proc)
+    #
+    # @param proc [Proc]
+    # @return [Integer]
+    # @!visibility private
+
+    # @!method yundo_manager_observe_item_updated(proc)
+    #
+    # @param proc [Proc]
+    # @return [Integer]
+    # @!visibility private
+
+    # @!method yundo_manager_redo
+    #
+    # @return [true, false]
+    # @!visibility private
+
+    # @!method yundo_manager_stop_capturing
+    #
+    # @return [void]
+    # @!visibility private
+
+    # @!method yundo_manager_undo
+    #
+    # @return [true, false]
+    # @!visibility private
+
+    # @!method yundo_manager_unobserve_item_added(subscription_id)
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    # @!visibility private
+
+    # @!method yundo_manager_unobserve_item_popped(subscription_id)
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    # @!visibility private
+
+    # @!method yundo_manager_unobserve_item_updated(subscription_id)
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    # @!visibility private
+  end
+end
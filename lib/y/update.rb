@@ -0,0 +1,268 @@
+# frozen_string_literal: true
+
+module Y
+  # Functions that operate on binary encoded updates without a document.
+  #
+  # These are useful for servers that store and forward updates without
+  # loading them into a document, e.g. to compact a log of updates into a
+  # single one, to answer a sync request from stored updates only, or to
+  # inspect what a client has sent.
+  #
+  # All functions accept updates and state vectors as arrays of bytes or
+  # binary strings, and return arrays of bytes unless `binary: true` is given.
+  # Large updates are processed without holding the GVL. Malformed updates
+  # raise a {Y::DecodeError}.
+  #
+  # @example Merge stored updates and send a client only what it misses
+  #   update = Y::Update.merge([update1, update2, update3])
+  #   Y::Update.diff(update, client_state)
+  module Update
+    ZERO_STATE = [0].freeze
+    private_constant :ZERO_STATE
+
+    ZERO_STATE_V2 = [0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0].freeze
+    private_constant :ZERO_STATE_V2
+
+    # Compacts an update into a minimal one with the same content
+    #
+    # The update is integrated into an empty document that collects the
+    # garbage of deleted content. Blocks that can not be integrated because
+    # they depend on content missing from the update are kept as they are.
+    #
+    # @example Compact a log of stored updates
+    #   update = Y::Update.compact(Y::Update.merge(updates))
+    #
+    # @param update [::Array<Integer>, String] Binary encoded update
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded update
+    def self.compact(update, binary: false)
+      compacted = yupdate_compact_v1(update)
+      binary ? compacted : compacted.bytes
+    end
+
+    # Compacts a v2 update into a minimal one with the same content
+    #
+    # @see .compact
+    # @param update [::Array<Integer>, String] Binary encoded v2 update
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded v2 update
+    def self.compact_v2(update, binary: false)
+      compacted = yupdate_compact_v2(update)
+      binary ? compacted : compacted.bytes
+    end
+
+    # Decodes an update into a description of its content
+    #
+    # Blocks are grouped by client and carry their clock range, the kind of
+    # content, and the parent and key they belong to. The parent is either the
+    # name of a root type, the ID of a nested type, or `nil` when the block is
+    # positioned next to another block and inherits its parent from it.
+    #
+    # Clock ranges are exclusive. Text content is counted in UTF-16 code
+    # units, like in Yjs.
+    #
+    # @example Inspect an update
+    #   doc = Y::Doc.new
+    #   text = doc.get_text("my text")
+    #   text << "Hello"
+    #
+    #   Y::Update.decode(doc.diff)
+    #   # {
+    #   #   blocks: {
+    #   #     1234 => [{ clock: 0...5, kind: :string, parent: "my text",
+    #   #                key: nil }]
+    #   #   },
+    #   #   delete_set: {}
+    #   # }
+    #
+    # @param update [::Array<Integer>, String] Binary encoded update
+    # @return [Hash{Symbol => Hash}] Blocks and deleted clock ranges per client
+    def self.decode(update)
+      yupdate_decode_v1(update)
+    end
+
+    # Decodes a v2 update into a description of its content
+    #
+    # @see .decode
+    # @param update [::Array<Integer>, String] Binary encoded v2 update
+    # @return [Hash{Symbol => Hash}] Blocks and deleted clock ranges per client
+    def self.decode_v2(update)
+      yupdate_decode_v2(update)
+    end
+
+    # Merges many updates into a single one
+    #
+    # @param updates [::Array<::Array<Integer>, String>] Updates to merge
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded update
+    def self.merge(updates, binary: false)
+      update = yupdate_merge_v1(updates)
+      binary ? update : update.bytes
+    end
+
+    # Merges many v2 updates into a single one
+    #
+    # @param updates [::Array<::Array<Integer>, String>] Updates to merge
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded update
+    def self.merge_v2(updates, binary: false)
+      update = yupdate_merge_v2(updates)
+      binary ? update : update.bytes
+    end
+
+    # Computes the part of an update that is missing from the given state
+    #
+    # @param update [::Array<Integer>, String] Binary encoded update
+    # @param state [::Array<Integer>, String] The state to create the diff
+    #   against
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded update
+    def self.diff(update, state = ZERO_STATE, binary: false)
+      diff = yupdate_diff_v1(update, state)
+      binary ? diff : diff.bytes
+    end
+
+    # Computes the part of a v2 update that is missing from the given v2 state
+    #
+    # @param update [::Array<Integer>, String] Binary encoded update
+    # @param state [::Array<Integer>, String] The state to create the diff
+    #   against
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded update
+    def self.diff_v2(update, state = ZERO_STATE_V2, binary: false)
+      diff = yupdate_diff_v2(update, state)
+      binary ? diff : diff.bytes
+    end
+
+    # Extracts the state vector of an update
+    #
+    # @param update [::Array<Integer>, String] Binary encoded update
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded state vector
+    def self.state(update, binary: false)
+      state = yupdate_state_vector_v1(update)
+      binary ? state : state.bytes
+    end
+
+    # Extracts the v2 state vector of a v2 update
+    #
+    # @param update [::Array<Integer>, String] Binary encoded update
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded state vector
+    def self.state_v2(update, binary: false)
+      state = yupdate_state_vector_v2(update)
+      binary ? state : state.bytes
+    end
+
+    # Converts a v1 update into the v2 encoding
+    #
+    # @param update [::Array<Integer>, String] Binary encoded update
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded v2 update
+    def self.v1_to_v2(update, binary: false)
+      converted = yupdate_v1_to_v2(update)
+      binary ? converted : converted.bytes
+    end
+
+    # Converts a v2 update into the v1 encoding
+    #
+    # @param update [::Array<Integer>, String] Binary encoded v2 update
+    # @param binary [true, false] Return an ASCII-8BIT string instead of an
+    #   array of bytes
+    # @return [::Array<Integer>, String] Binary encoded update
+    def self.v2_to_v1(update, binary: false)
+      converted = yupdate_v2_to_v1(update)
+      binary ? converted : converted.bytes
+    end
+
+    private_class_method :yupdate_compact_v1, :yupdate_compact_v2,
+                         :yupdate_decode_v1, :yupdate_decode_v2,
+                         :yupdate_diff_v1, :yupdate_diff_v2,
+                         :yupdate_merge_v1, :yupdate_merge_v2,
+                         :yupdate_state_vector_v1, :yupdate_state_vector_v2,
+                         :yupdate_v1_to_v2, :yupdate_v2_to_v1
+
+    # @!method self.yupdate_compact_v1(update)
+    #
+    # @param update [Array<Integer>, String]
+    # @return [String]
+    # @!visibility private
+
+    # @!method self.yupdate_compact_v2(update)
+    #
+    # @param update [Array<Integer>, String]
+    # @return [String]
+    # @!visibility private
+
+    # @!method self.yupdate_decode_v1(update)
+    #
+    # @param update [Array<Integer>, String]
+    # @return [Hash{Symbol => Hash}]
+    # @!visibility private
+
+    # @!method self.yupdate_decode_v2(update)
+    #
+    # @param update [Array<Integer>, String]
+    # @return [Hash{Symbol => Hash}]
+    # @!visibility private
+
+    # @!method self.yupdate_diff_v1(update, state)
+    #
+    # @param update [Array<Integer>, String]
+    # @param state [Array<Integer>, String]
+    # @return [String]
+    # @!visibility private
+
+    # @!method self.yupdate_diff_v2(update, state)
+    #
+    # @param update [Array<Integer>, String]
+    # @param state [Array<Integer>, String]
+    # @return [String]
+    # @!visibility private
+
+    # @!method self.yupdate_merge_v1(updates)
+    #
+    # @param updates [Array<Array<Integer>, String>]
+    # @return [String]
+    # @!visibility private
+
+    # @!method self.yupdate_merge_v2(updates)
+    #
+    # @param updates [Array<Array<Integer>, String>]
+    # @return [String]
+    # @!visibility private
+
+    # @!method self.yupdate_state_vector_v1(update)
+    #
+    # @param update [Array<Integer>, String]
+    # @return [String]
+    # @!visibility private
+
+    # @!method self.yupdate_state_vector_v2(update)
+    #
+    # @param update [Array<Integer>, String]
+    # @return [String]
+    # @!visibility private
+
+    # @!method self.yupdate_v1_to_v2(update)
+    #
+    # @param update [Array<Integer>, String]
+    # @return [String]
+    # @!visibility private
+
+    # @!method self.yupdate_v2_to_v1(update)
+    #
+    # @param update [Array<Integer>, String]
+    # @return [String]
+    # @!visibility private
+  end
+end
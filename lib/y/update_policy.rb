@@ -0,0 +1,197 @@
+# frozen_string_literal: true
+
+require_relative "error"
+
+module Y
+  # Raised when an update violates an {UpdatePolicy}
+  class UpdateRejected < Error
+    # @return [Symbol] The violated rule, one of `:size`, `:client`, `:root`,
+    #   `:type` or `:unresolved`
+    attr_reader :reason
+
+    # @return [String, nil] The root type the update was rejected for
+    attr_reader :root
+
+    # @return [Integer, nil] The client the update was rejected for
+    attr_reader :client
+
+    # @param message [String]
+    # @param reason [Symbol]
+    # @param root [String, nil]
+    # @param client [Integer, nil]
+    def initialize(message, reason:, root: nil, client: nil)
+      super(message)
+
+      @reason = reason
+      @root = root
+      @client = client
+    end
+  end
+
+  # A policy describes which updates a peer is allowed to apply to a document.
+  #
+  # Updates are checked before they are integrated, so a rejected update
+  # leaves the document untouched. All rules are optional.
+  #
+  # The root types an update modifies are resolved from the update itself and
+  # from the document it is applied to, as only the first block of a sequence
+  # of insertions names its parent. Resolving them encodes the document if the
+  # update refers to existing content, which takes time proportional to the
+  # size of the document. Blocks and deletions that refer to content neither
+  # the update nor the document contain can not be resolved, so updates that
+  # contain them are rejected by policies restricting root types or their
+  # types. Peers are expected to send the content they depend on first.
+  #
+  # @example Only allow a client to edit the text of a document
+  #   policy = Y::UpdatePolicy.new(
+  #     roots: ["content"],
+  #     types: { "content" => :text },
+  #     max_size: 64 * 1024,
+  #     clients: [client_id]
+  #   )
+  #
+  #   begin
+  #     doc.sync(update, policy: policy)
+  #   rescue Y::UpdateRejected => e
+  #     logger.warn("rejected update: #{e.message}")
+  #   end
+  class UpdatePolicy
+    # Content allowed in the sequence of a root type, by type
+    SEQUENCE_CONTENT = {
+      array: %i[any json binary doc type],
+      map: [],
+      text: %i[string format embed type],
+      xml_element: %i[type],
+      xml_fragment: %i[type],
+      xml_text: %i[string format embed type]
+    }.freeze
+    private_constant :SEQUENCE_CONTENT
+
+    # Types that store entries (or attributes) by key
+    KEYED_TYPES = %i[map xml_element xml_text].freeze
+    private_constant :KEYED_TYPES
+
+    # @return [::Array<String>, nil] Names of root types that may be modified
+    attr_reader :roots
+
+    # @return [Hash{String => Symbol}] Expected type by root type name
+    attr_reader :types
+
+    # @return [Integer, nil] Maximum size of an update in bytes
+    attr_reader :max_size
+
+    # @return [::Array<Integer>, nil] Clients that may send updates
+    attr_reader :clients
+
+    # @param roots [::Array<String>, nil] Names of root types that may be
+    #   modified, any root type if `nil`
+    # @param types [Hash{String => Symbol}] Expected type by root type name,
+    #   one of `:array`, `:map`, `:text`, `:xml_element`, `:xml_fragment` or
+    #   `:xml_text`
+    # @param max_size [Integer, nil] Maximum size of an update in bytes
+    # @param clients [::Array<Integer>, nil] Clients that may send updates,
+    #   e.g. the client ID assigned to a connection, any client if `nil`
+    # @raise [ArgumentError] If a type is not supported
+    def initialize(roots: nil, types: {}, max_size: nil, clients: nil)
+      types.each_value do |type|
+        next if SEQUENCE_CONTENT.key?(type)
+
+        raise ArgumentError, "unsupported type: #{type.inspect}"
+      end
+
+      @roots = roots&.map(&:to_s)
+      @types = types.to_h { |root, type| [root.to_s, type] }
+      @max_size = max_size
+      @clients = clients
+    end
+
+    # Checks an update against this policy. The summary of the update is only
+    # created if the size of the update is acceptable.
+    #
+    # @param update [::Array<Integer>, String] Binary encoded update
+    # @yieldreturn [Hash] Summary of the update, see
+    #   {Y::Transaction#validate_update}
+    # @return [Y::UpdateRejected, nil] The violation, if any
+    # @!visibility private
+    def violation(update)
+      size_violation(update) || summary_violation(yield)
+    end
+
+    private
+
+    def size_violation(update)
+      size = update.is_a?(String) ? update.bytesize : update.size
+      return if max_size.nil? || size <= max_size
+
+      UpdateRejected.new(
+        "update of #{size} bytes exceeds maximum size of #{max_size} bytes",
+        reason: :size
+      )
+    end
+
+    def summary_violation(summary)
+      client_violation(summary[:clients]) ||
+        unresolved_violation(summary[:unresolved]) ||
+        summary[:roots].lazy.filter_map do |root, content|
+          root_violation(root) || type_violation(root, content)
+        end.first
+    end
+
+    def client_violation(update_clients)
+      return if clients.nil?
+
+      client = update_clients.find { |id| !clients.include?(id) }
+      return if client.nil?
+
+      UpdateRejected.new(
+        "client #{client} may not send updates",
+        reason: :client,
+        client: client
+      )
+    end
+
+    # the root type of unresolved blocks is unknown, so they could modify any
+    # root type
+    def unresolved_violation(unresolved)
+      return if !unresolved || (roots.nil? && types.empty?)
+
+      UpdateRejected.new(
+        "update depends on content the document does not contain",
+        reason: :unresolved
+      )
+    end
+
+    def root_violation(root)
+      return if roots.nil? || roots.include?(root)
+
+      UpdateRejected.new(
+        "root type #{root.inspect} may not be modified",
+        reason: :root,
+        root: root
+      )
+    end
+
+    def type_violation(root, content)
+      type = types[root]
+      return if type.nil?
+
+      entry = content.find { |item| !allowed?(type, item) }
+      return if entry.nil?
+
+      kind = entry[:key] ? "map entries" : "#{entry[:kind]} content"
+      UpdateRejected.new(
+        "root type #{root.inspect} of type #{type} does not accept #{kind}",
+        reason: :type,
+        root: root
+      )
+    end
+
+    def allowed?(type, entry)
+      if entry[:key]
+        KEYED_TYPES.include?(type)
+      else
+        SEQUENCE_CONTENT[type].include?(entry[:kind])
+      end
+    end
+  end
+end
@@ -109,6 +109,9 @@ module Y
     #   xml_element = doc.get_xml_element("my xml element")
     #   xml_element.attach { |changes| … }
     #
+    # @example Receive the origin of the transaction via Block
+    #   xml_element.attach { |changes, origin| origin.local? }
+    #
     # @param callback [Proc]
     # @param block [Block]
     # @return [Integer] The subscription ID
@@ -118,6 +121,35 @@ module Y
       yxml_element_observe(block.to_proc) unless block.nil?
     end
 
+    # Attach a listener to changes of this element and all of its
+    # descendants
+    #
+    # See {Y::Map#observe_deep} for the structure of the events. Listeners
+    # accepting a second argument receive the {Y::Origin} of the transaction.
+    #
+    # @example Listen to attribute changes of child elements
+    #   xml_element.observe_deep do |events|
+    #     events.each { |event| pp event[:keys] unless event[:path].empty? }
+    #   end
+    #
+    # @param callback [Proc]
+    # @param block [Block]
+    # @return [Integer] The subscription ID
+    def observe_deep(callback = nil, &block)
+      listener = callback || block&.to_proc
+      return if listener.nil?
+
+      yxml_element_observe_deep(listener, document)
+    end
+
+    # Detach a listener attached with {#observe_deep}
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    def unobserve_deep(subscription_id)
+      yxml_element_unobserve_deep(subscription_id)
+    end
+
     # Retrieve parent element
     #
     # @return [Y::XMLElement, nil]
@@ -396,6 +428,13 @@ module Y
     # @param callback [Proc]
     # @return [Integer] The subscription ID
 
+    # @!method yxml_element_observe_deep(proc, document)
+    #   Observe changes of this type and all nested types
+    #
+    # @param proc [Proc]
+    # @param document [Y::Doc]
+    # @return [Integer]
+
     # @!method yxml_element_parent()
     #
     # @return [Y::XMLElement, nil]
@@ -461,6 +500,12 @@ module Y
     #
     # @param subscription_id [Integer]
     # @return [void]
+
+    # @!method yxml_element_unobserve_deep(subscription_id)
+    #   Detach deep listener
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
   end
 
   # A XMLText
@@ -611,6 +656,30 @@ module Y
       node
     end
 
+    # Attach a listener to changes of this text node, including changes of
+    # its attributes and embedded shared types
+    #
+    # See {Y::Map#observe_deep} for the structure of the events. Listeners
+    # accepting a second argument receive the {Y::Origin} of the transaction.
+    #
+    # @param callback [Proc]
+    # @param block [Block]
+    # @return [Integer] The subscription ID
+    def observe_deep(callback = nil, &block)
+      listener = callback || block&.to_proc
+      return if listener.nil?
+
+      yxml_text_observe_deep(listener, document)
+    end
+
+    # Detach a listener attached with {#observe_deep}
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    def unobserve_deep(subscription_id)
+      yxml_text_unobserve_deep(subscription_id)
+    end
+
     # Return parent XMLElement
     #
     # @return [Y::XMLElement, nil]
@@ -716,6 +785,22 @@ module Y
 
     # rubocop:enable Metrics/AbcSize, Metrics/CyclomaticComplexity, Metrics/MethodLength
 
+    # Creates a sticky index at the given position
+    #
+    # In contrast to a plain index, a sticky index keeps pointing to the same
+    # position, even when content is inserted or removed before it, e.g. by
+    # remote peers. Use {Y::StickyIndex#offset} to resolve it to an index.
+    #
+    # @param index [Integer]
+    # @param assoc [Symbol] Associate the index with the element `:after`
+    #   (default) or `:before` the given position
+    # @return [Y::StickyIndex, nil] Returns nil if the index is out of bounds
+    def sticky_index(index, assoc: :after)
+      document.current_transaction do |tx|
+        yxml_text_sticky_index(tx, index, assoc)
+      end
+    end
+
     # Returns string representation of XMLText
     #
     # @return [String]
@@ -857,6 +942,13 @@ module Y
     # @param callback [Proc]
     # @return [Integer] A subscription ID
 
+    # @!method yxml_text_observe_deep(proc, document)
+    #   Observe changes of this type and all nested types
+    #
+    # @param proc [Proc]
+    # @param document [Y::Doc]
+    # @return [Integer]
+
     # @!method yxml_text_parent
     #
     # @return [Y::XMLElement, nil]
@@ -879,6 +971,14 @@ module Y
     # @param length [Integer]
     # @return [void]
 
+    # @!method yxml_text_sticky_index(tx, index, assoc)
+    #   Creates a sticky index at the given position
+    #
+    # @param tx [Y::Transaction]
+    # @param index [Integer]
+    # @param assoc [Symbol]
+    # @return [Y::StickyIndex, nil]
+
     # @!method yxml_text_to_s(tx)
     #
     # @param tx [Y::Transaction]
@@ -888,6 +988,12 @@ module Y
     #
     # @param subscription_id [Integer]
     # @return [void]
+
+    # @!method yxml_text_unobserve_deep(subscription_id)
+    #   Detach deep listener
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
   end
 
   # @!visibility private
@@ -958,6 +1064,30 @@ module Y
 
     alias size length
 
+    # Attach a listener to changes of this fragment and all of its
+    # descendants
+    #
+    # See {Y::Map#observe_deep} for the structure of the events. Listeners
+    # accepting a second argument receive the {Y::Origin} of the transaction.
+    #
+    # @param callback [Proc]
+    # @param block [Block]
+    # @return [Integer] The subscription ID
+    def observe_deep(callback = nil, &block)
+      listener = callback || block&.to_proc
+      return if listener.nil?
+
+      yxml_fragment_observe_deep(listener, document)
+    end
+
+    # Detach a listener attached with {#observe_deep}
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
+    def unobserve_deep(subscription_id)
+      yxml_fragment_unobserve_deep(subscription_id)
+    end
+
     # Retrieve parent element
     #
     # @return [Y::XMLElement, Y::XMLFragment, Y::XMLText, nil]
@@ -1099,6 +1229,13 @@ module Y
     # @param tx [Y::Transaction]
     # @return [Integer]
 
+    # @!method yxml_fragment_observe_deep(proc, document)
+    #   Observe changes of this type and all nested types
+    #
+    # @param proc [Proc]
+    # @param document [Y::Doc]
+    # @return [Integer]
+
     # @!method yxml_fragment_parent
     #
     # @return [Y::XMLElement, Y::XMLFragment, Y::XMLText, nil]
@@ -1135,6 +1272,12 @@ module Y
     #
     # @param tx [Y::Transaction]
     # @return [String]
+
+    # @!method yxml_fragment_unobserve_deep(subscription_id)
+    #   Detach deep listener
+    #
+    # @param subscription_id [Integer]
+    # @return [void]
   end
 
   # rubocop:enable Metrics/ClassLength
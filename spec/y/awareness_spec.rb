@@ -16,6 +16,15 @@ RSpec.describe Y::Awareness do
     expect(remote_state).to eq(state)
   end
 
+  it "applies a binary update" do
+    local_awareness.local_state = state
+
+    remote_awareness = described_class.new
+    remote_awareness.sync(local_awareness.diff(binary: true))
+
+    expect(remote_awareness.clients[local_id]).to eq(state)
+  end
+
   it "cleans local state" do
     local_awareness.local_state = state
     local_awareness.clean_local_state
@@ -84,6 +93,115 @@ RSpec.describe Y::Awareness do
     expect(event).to be_instance_of(Y::AwarenessEvent)
   end
 
+  it "raises errors of listeners once the state changed" do
+    local_awareness.attach { |_event| raise ArgumentError, "listener failed" }
+
+    expect { local_awareness.local_state = state }
+      .to raise_error(ArgumentError, "listener failed")
+  end
+
+  it "stops notifying detached listeners" do
+    events = []
+    subscription = local_awareness.attach { |event| events << event }
+    local_awareness.detach(subscription)
+    local_awareness.local_state = state
+
+    expect(events).to be_empty
+  end
+
+  it "shares the client id of its document" do
+    doc = Y::Doc.new
+
+    expect(described_class.new(doc).client_id).to eq(doc.client_id)
+  end
+
+  it "exposes its document" do
+    doc = Y::Doc.new
+
+    expect(described_class.new(doc).doc).to be(doc)
+  end
+
+  it "creates a document by default" do
+    expect(local_awareness.doc.client_id).to eq(local_id)
+  end
+
+  it "returns the clock of a client" do
+    local_awareness.local_state = state
+    local_awareness.local_state = state
+
+    expect(local_awareness.meta(local_id)[:clock]).to eq(2)
+  end
+
+  it "returns the time of the last update of a client" do
+    local_awareness.local_state = state
+
+    expect(local_awareness.meta(local_id)[:last_updated]).to be_a(Time)
+  end
+
+  it "records the time of the last update with the given clock" do
+    awareness = described_class.new(clock: -> { Time.at(42) })
+    awareness.local_state = state
+
+    expect(awareness.meta(awareness.client_id)[:last_updated])
+      .to eq(Time.at(42))
+  end
+
+  it "returns no meta for unknown clients" do
+    expect(local_awareness.meta(1)).to be_nil
+  end
+
+  context "when removing outdated clients" do
+    let(:clock) { Struct.new(:now).new(Time.at(0)) }
+    let(:local_awareness) { described_class.new(clock: -> { clock.now }) }
+    let(:remote_awareness) do
+      remote_awareness = described_class.new
+      remote_awareness.local_state = { name: "User B" }
+      remote_awareness
+    end
+
+    before do
+      local_awareness.local_state = state
+      local_awareness.sync(remote_awareness.diff)
+    end
+
+    it "removes remote clients that have not been updated in time" do
+      clock.now += 30
+      removed = local_awareness.remove_outdated(30)
+
+      expect(removed).to eq([remote_awareness.client_id])
+    end
+
+    it "keeps clients that have been updated recently" do
+      clock.now += 29
+
+      expect(local_awareness.remove_outdated(30)).to be_empty
+    end
+
+    it "keeps the local client" do
+      clock.now += 30
+      local_awareness.remove_outdated(30)
+
+      expect(local_awareness.local_state).to eq(state)
+    end
+
+    it "notifies observers about the removed clients" do
+      removed = []
+      local_awareness.attach { |event| removed.concat(event.removed) }
+      clock.now += 30
+      local_awareness.remove_outdated(30)
+
+      expect(removed).to eq([remote_awareness.client_id])
+    end
+
+    it "forgets when removed clients were updated" do
+      clock.now += 30
+      local_awareness.remove_outdated(30)
+
+      expect(local_awareness.meta(remote_awareness.client_id)[:last_updated])
+        .to be_nil
+    end
+  end
+
   context "when syncing multiple client states" do
     it "merges state of all clients" do
       client_a = described_class.new
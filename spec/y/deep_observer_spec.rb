@@ -0,0 +1,78 @@
+# frozen_string_literal: true
+
+RSpec.describe "Deep observers" do
+  # pages: { home: [{ title: "Home" }] } created by client 1
+  let(:initial) do
+    [1, 3, 1, 0, 39, 1, 5, 112, 97, 103, 101, 115, 4, 104, 111, 109, 101, 0,
+     7, 0, 1, 0, 1, 40, 0, 1, 1, 5, 116, 105, 116, 108, 101, 1, 119, 4, 72,
+     111, 109, 101, 0]
+  end
+  # sets the title of the nested map to "Start"
+  let(:update) do
+    [1, 1, 1, 3, 168, 1, 2, 1, 119, 5, 83, 116, 97, 114, 116, 1, 1, 1, 2, 1]
+  end
+  let(:doc) { Y::Doc.new }
+
+  it "reports path of nested map changes" do
+    doc.sync(initial)
+    events = nil
+    doc.get_map("pages").observe_deep { |e| events = e }
+
+    doc.sync(update)
+
+    expect(events.map { |e| e[:path] }).to eq([["home", 0]])
+  end
+
+  it "reports changed keys of nested maps" do
+    doc.sync(initial)
+    events = nil
+    doc.get_map("pages").observe_deep { |e| events = e }
+
+    doc.sync(update)
+
+    expect(events.first[:keys]).to eq([{ updated: { title: %w[Home Start] } }])
+  end
+
+  it "associates event targets with the document" do
+    doc.sync(initial)
+    target = nil
+    doc.get_map("pages").observe_deep { |e| target = e.first[:target] }
+
+    doc.sync(update)
+
+    expect(target.to_h).to eq({ "title" => "Start" })
+  end
+
+  it "reports changes of child elements" do
+    xml_element = doc.get_xml_element("my xml")
+    child = xml_element << "child"
+    events = nil
+    xml_element.observe_deep { |e| events = e }
+
+    child.attr_name = "Hello"
+
+    expect(events.map { |e| [e[:path], e[:keys]] })
+      .to eq([[[0], [{ inserted: { name: "Hello" } }]]])
+  end
+
+  it "passes transaction origin" do
+    xml_element = doc.get_xml_element("my xml")
+    origin = nil
+    xml_element.observe_deep { |_events, o| origin = o }
+
+    doc.transact(origin: :editor) { xml_element << "child" }
+
+    expect(origin.value).to eq(:editor)
+  end
+
+  it "stops calling removed deep listeners" do
+    text = doc.get_text("my text")
+    events = nil
+    subscription_id = text.observe_deep { |e| events = e }
+
+    text.unobserve_deep(subscription_id)
+    text << "Hello"
+
+    expect(events).to be_nil
+  end
+end
@@ -148,6 +148,58 @@ RSpec.describe Y::Doc do
     # rubocop:enable RSpec/MultipleExpectations
   end
 
+  context "when compacting documents" do
+    let(:doc) { described_class.new(skip_gc: true) }
+
+    before do
+      text = doc.get_text("my text")
+      text << ("Hello" * 100)
+      text.slice!(0, 495)
+    end
+
+    it "reports the size before and after compaction" do
+      result = doc.compact
+
+      expect(result[:size_after]).to be < result[:size_before]
+    end
+
+    it "returns an update with the live content" do
+      remote = described_class.new
+      remote.restore(doc.compact[:update])
+
+      expect(remote.get_text("my text").to_s).to eq("Hello")
+    end
+  end
+
+  context "when collecting statistics" do
+    let(:doc) { described_class.new(42, skip_gc: true) }
+
+    before do
+      text = doc.get_text("my text")
+      text << "Hello, World!"
+      text.slice!(0, 7)
+      doc.get_map("my map")[:key] = 1
+    end
+
+    it "counts blocks and tombstones per client" do
+      expect(doc.stats[:clients])
+        .to eq(42 => { clock: 14, blocks: 3, deleted: 1, gc: 0 })
+    end
+
+    it "counts deleted ranges" do
+      expect(doc.stats[:delete_set]).to eq(42 => 1)
+    end
+
+    it "returns the length of root types" do
+      expect(doc.stats[:roots]).to eq("my text" => 6, "my map" => 1)
+    end
+
+    it "returns the encoded size of the state" do
+      expect(doc.stats.values_at(:size_v1, :size_v2))
+        .to eq([doc.full_diff.size, doc.diff_v2.size])
+    end
+  end
+
   context "when listening to updates" do
     it "attaching to updates return a subscription_id" do
       doc = described_class.new
@@ -169,6 +221,377 @@ RSpec.describe Y::Doc do
 
       expect(count).to eq(2)
     end
+
+    it "stops calling detached listeners" do
+      doc = described_class.new
+      count = 0
+      subscription_id = doc.attach { |_update| count += 1 }
+
+      doc.detach(subscription_id)
+      doc.get_text("my text") << "1"
+
+      expect(count).to eq(0)
+    end
+
+    it "yields v2 encoded updates" do
+      doc = described_class.new
+      updates = []
+      doc.attach_v2 { |update| updates << update }
+      doc.get_text("my text") << "Hello"
+
+      remote = described_class.new
+      remote.sync_v2(updates.first)
+
+      expect(remote.get_text("my text").to_s).to eq("Hello")
+    end
+
+    it "raises errors of listeners once the transaction is committed" do
+      doc = described_class.new
+      doc.attach { |_update| raise ArgumentError, "listener failed" }
+      text = doc.get_text("my text")
+
+      expect { text << "Hello" }
+        .to raise_error(ArgumentError, "listener failed")
+    end
+
+    it "keeps the document usable after a listener failed" do
+      doc = described_class.new
+      text = doc.get_text("my text")
+      subscription_id = doc.attach { |_update| raise ArgumentError }
+      begin
+        text << "Hello"
+      rescue ArgumentError
+        doc.detach(subscription_id)
+      end
+
+      text << ", World!"
+
+      expect(text.to_s).to eq("Hello, World!")
+    end
+
+    it "lists attached listeners" do
+      doc = described_class.new
+      update_id = doc.attach { |_update| }
+      update_v2_id = doc.attach_v2 { |_update| }
+
+      expect(doc.listeners).to eq(
+        { update: [update_id], update_v2: [update_v2_id] }
+      )
+    end
+
+    it "detaches all listeners" do
+      doc = described_class.new
+      count = 0
+      doc.attach { |_update| count += 1 }
+      doc.attach_v2 { |_update| count += 1 }
+
+      doc.detach_all
+      doc.get_text("my text") << "1"
+
+      expect([count, doc.listeners]).to eq([0, {}])
+    end
+  end
+
+  context "when listening to transactions" do
+    it "calls listener once per transaction with changed root types" do
+      doc = described_class.new
+      text = doc.get_text("my text")
+      map = doc.get_map("my map")
+      changed = []
+      doc.observe_after_transaction { |event| changed << event[:changed].sort }
+
+      doc.transact do
+        text << "Hello"
+        map[:hello] = "world"
+      end
+
+      expect(changed).to eq([["my map", "my text"]])
+    end
+
+    it "passes origin to transaction listener" do
+      doc = described_class.new
+      text = doc.get_text("my text")
+      origin = nil
+      doc.observe_after_transaction { |_event, o| origin = o }
+
+      doc.transact(origin: :importer) { text << "Hello" }
+
+      expect(origin.value).to eq(:importer)
+    end
+
+    it "passes state vector from before the transaction" do
+      doc = described_class.new
+      doc.get_text("my text") << "Hello"
+      state = doc.state
+      before_state = nil
+      doc.observe_transaction_cleanup { |e| before_state = e[:before_state] }
+
+      doc.get_text("my text") << ", World!"
+
+      expect(before_state).to eq(state)
+    end
+
+    it "passes state vector after the transaction as binary string" do
+      doc = described_class.new
+      after_state = nil
+      doc.observe_after_transaction(binary: true) do |event|
+        after_state = event[:after_state]
+      end
+
+      doc.get_text("my text") << "Hello"
+
+      expect(after_state).to eq(doc.state(binary: true))
+    end
+
+    it "skips transactions that do not change the document" do
+      doc = described_class.new
+      text = doc.get_text("my text")
+      count = 0
+      doc.observe_after_transaction { count += 1 }
+      doc.observe_transaction_cleanup { count += 1 }
+
+      text.to_s
+      doc.state
+
+      expect(count).to eq(0)
+    end
+
+    it "reports deleted clock ranges" do
+      doc = described_class.new
+      text = doc.get_text("my text")
+      text << "Hello"
+      delete_set = nil
+      doc.observe_transaction_cleanup { |e| delete_set = e[:delete_set] }
+
+      text.slice!(0, 2)
+
+      expect(delete_set).to eq({ doc.client_id => [0...2] })
+    end
+
+    it "stops calling removed transaction listeners" do
+      doc = described_class.new
+      count = 0
+      subscription_id = doc.observe_after_transaction { |_event| count += 1 }
+
+      doc.unobserve_after_transaction(subscription_id)
+      doc.get_text("my text") << "Hello"
+
+      expect(count).to eq(0)
+    end
+  end
+
+  context "when reading documents" do
+    let(:doc) { described_class.new }
+    let!(:text) { doc.get_text("my text", "Hello") }
+
+    it "reads types within read-only transaction" do
+      result = doc.read { |tx| [tx.read_only?, text.to_s] }
+
+      expect(result).to eq([true, "Hello"])
+    end
+
+    it "fails to modify document within read-only transaction" do
+      expect { doc.read { text << ", World!" } }
+        .to raise_error(Y::TransactionError, /read-only/)
+    end
+
+    it "allows multiple readers at the same time" do
+      result = doc.read { Thread.new { doc.read { text.to_s } }.value }
+
+      expect(result).to eq("Hello")
+    end
+
+    it "fails to create read-write transaction while reading" do
+      expect { doc.read { Thread.new { doc.transact { nil } }.join } }
+        .to raise_error(Y::TransactionError)
+    end
+
+    it "fails to get root types while another thread reads" do
+      reading = Thread::Queue.new
+      done = Thread::Queue.new
+      reader = Thread.new { doc.read { [reading << true, done.pop] } }
+
+      reading.pop
+      expect { doc.get_map("my map") }.to raise_error(Y::TransactionError)
+    ensure
+      done << true
+      reader.join
+    end
+
+    it "reuses active read-write transaction" do
+      expect(doc.transact { doc.read(&:read_only?) }).to be(false)
+    end
+  end
+
+  context "when nesting operations in a transaction" do
+    let(:doc) { described_class.new }
+    let(:update) do
+      remote = described_class.new
+      remote.get_text("my text") << "Hello"
+      remote.diff
+    end
+
+    it "joins the active transaction without an origin" do
+      text = doc.get_text("my text")
+      doc.transact(origin: :importer) { text << "Hello" }
+
+      expect(text.to_s).to eq("Hello")
+    end
+
+    it "joins a remote transaction with the same origin" do
+      doc.transact(origin: :peer, local: false) do
+        doc.sync(update, origin: :peer)
+      end
+
+      expect(doc.get_text("my text").to_s).to eq("Hello")
+    end
+
+    it "syncs within a local transaction" do
+      doc.transact { doc.sync(update) }
+
+      expect(doc.get_text("my text").to_s).to eq("Hello")
+    end
+
+    it "restores within a local transaction" do
+      doc.transact { doc.restore(update) }
+
+      expect(doc.get_text("my text").to_s).to eq("Hello")
+    end
+
+    it "fails to sync within a transaction with another origin" do
+      expect do
+        doc.transact(origin: :a, local: false) { doc.sync(update, origin: :b) }
+      end.to raise_error(Y::TransactionError, /origin :a/)
+    end
+  end
+
+  context "when using binary strings" do
+    let(:local) { described_class.new }
+    let(:remote) { described_class.new }
+
+    before { local.get_text("my text") << "Hello" }
+
+    it "returns diff as binary string" do
+      diff = local.diff(binary: true)
+
+      expect([diff.encoding, diff.bytes]).to eq([Encoding::BINARY, local.diff])
+    end
+
+    it "syncs binary diff created from binary state" do
+      remote.sync(local.diff(remote.state(binary: true), binary: true))
+
+      expect(remote.get_text("my text").to_s).to eq("Hello")
+    end
+
+    it "syncs binary v2 diff" do
+      remote.sync_v2(local.diff_v2(remote.state_v2(binary: true), binary: true))
+
+      expect(remote.get_text("my text").to_s).to eq("Hello")
+    end
+
+    it "yields binary updates" do
+      updates = []
+      local.attach(binary: true) { |update| updates << update }
+
+      local.get_text("my text") << ", World!"
+
+      expect(updates.map(&:encoding)).to eq([Encoding::BINARY])
+    end
+  end
+
+  context "when enumerating root types" do
+    let(:doc) { described_class.new }
+    let(:remote) { described_class.new }
+
+    before do
+      doc.get_text("my text") << "Hello"
+      doc.get_map("my map")[:count] = 1
+      doc.get_array("my array") << 1
+      remote.sync(doc.diff)
+    end
+
+    it "returns root types with their kind" do
+      roots = doc.roots.transform_values { |root| root[:kind] }
+
+      expect(roots).to eq(
+        "my text" => :text, "my map" => :map, "my array" => :array
+      )
+    end
+
+    it "infers the kind of root types received from other clients" do
+      roots = remote.roots.transform_values { |root| root[:kind] }
+
+      expect(roots).to eq(
+        "my text" => :text, "my map" => :map, "my array" => :array
+      )
+    end
+
+    it "returns live references" do
+      expect(remote.roots["my text"][:value].to_s).to eq("Hello")
+    end
+
+    it "converts the document into plain data" do
+      expect(remote.to_h).to eq(
+        "my text" => "Hello", "my map" => { "count" => 1 }, "my array" => [1]
+      )
+    end
+
+    it "converts the document into JSON" do
+      expect(JSON.parse(doc.to_json)).to eq(doc.to_h)
+    end
+  end
+
+  context "when receiving updates out of order" do
+    let(:doc) { described_class.new }
+    let(:remote) { described_class.new(42) }
+    let(:remote_text) { remote.get_text("my text") }
+
+    def updates
+      remote_text << "Hello"
+      first = remote.diff
+      state = remote.state
+      remote_text << ", World!"
+      [first, remote.diff(state)]
+    end
+
+    it "describes pending and missing clock ranges" do
+      doc.sync(updates.last)
+
+      expect(doc.pending).to eq(
+        structs: { 42 => [5...13] }, missing: { 42 => [0...5] }, delete_set: {}
+      )
+    end
+
+    it "integrates pending updates once the missing update arrives" do
+      first, second = updates
+      doc.sync(second)
+      doc.sync(first)
+
+      expect(doc.get_text("my text").to_s).to eq("Hello, World!")
+    end
+
+    it "has nothing pending after the missing update arrived" do
+      first, second = updates
+      doc.sync(second)
+      doc.sync(first)
+
+      expect(doc.pending?).to be(false)
+    end
+
+    it "describes pending deletions" do
+      remote_text << "Hello"
+      state = remote.state
+      remote_text.slice!(1, 2)
+      doc.sync(remote.diff(state))
+
+      expect(doc.pending).to include(delete_set: { 42 => [1...3] })
+    end
+
+    it "has nothing pending after updates arrived in order" do
+      updates.each { |update| doc.sync(update) }
+
+      expect(doc.pending).to be_nil
+    end
   end
 
   context "when using v2 encoding" do
@@ -186,4 +609,51 @@ RSpec.describe Y::Doc do
       expect(remote_text.to_s).to eq(local_text.to_s)
     end
   end
+
+  it "creates document with options" do
+    doc = described_class.new(guid: "my-guid", collection_id: "my-collection")
+
+    expect([doc.guid, doc.collection_id]).to eq(%w[my-guid my-collection])
+  end
+
+  it "creates document with client id" do
+    doc = described_class.new(42)
+
+    expect(doc.client_id).to eq(42)
+  end
+
+  it "disables garbage collection" do
+    expect(described_class.new(gc: false).skip_gc?).to be(true)
+  end
+
+  it "enables garbage collection by default" do
+    expect(described_class.new.gc?).to be(true)
+  end
+
+  it "fails when gc and skip_gc contradict each other" do
+    expect { described_class.new(gc: true, skip_gc: true) }
+      .to raise_error(ArgumentError)
+  end
+
+  it "creates document with load options" do
+    doc = described_class.new(auto_load: true, should_load: false)
+
+    expect([doc.auto_load?, doc.should_load?]).to eq([true, false])
+  end
+
+  it "uses utf16 offsets by default" do
+    expect(described_class.new.offset_kind).to eq(:utf16)
+  end
+
+  it "counts text length in bytes" do
+    doc = described_class.new(offset_kind: :bytes)
+    text = doc.get_text("my text", "Grüße")
+
+    expect(text.length).to eq(7)
+  end
+
+  it "fails for unsupported offset kinds" do
+    expect { described_class.new(offset_kind: :utf32) }
+      .to raise_error(ArgumentError)
+  end
 end
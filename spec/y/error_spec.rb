@@ -0,0 +1,89 @@
+# frozen_string_literal: true
+
+RSpec.describe Y::Error do
+  let(:doc) { Y::Doc.new }
+
+  it "is the superclass of all specific errors" do
+    errors = [Y::DecodeError, Y::IndexError, Y::TypeError,
+              Y::TransactionError, Y::UpdateRejected]
+
+    expect(errors).to all(be < described_class)
+  end
+
+  it "raises a decode error with the reason for malformed updates" do
+    expect { doc.sync([1, 2, 3]) }
+      .to raise_error(Y::DecodeError, /cannot decode update: .+/)
+  end
+
+  it "raises a decode error for malformed state vectors" do
+    expect { doc.diff([255]) }.to raise_error(Y::DecodeError)
+  end
+
+  it "raises an index error when reading beyond the end of an array" do
+    doc.get_array("my array") << 1
+
+    expect { doc.get_array("my array")[1] }
+      .to raise_error(Y::IndexError, /index 1 out of bounds for length 1/)
+  end
+
+  it "raises an index error when inserting beyond the end of an array" do
+    expect { doc.get_array("my array")[3] = 1 }
+      .to raise_error(Y::IndexError)
+  end
+
+  it "raises an index error when removing beyond the end of a text" do
+    doc.get_text("my text") << "Hello"
+
+    expect { doc.get_text("my text").slice!(3, 5) }
+      .to raise_error(Y::IndexError, /range 3...8 out of bounds/)
+  end
+
+  it "raises an index error when inserting beyond the end of a text" do
+    expect { doc.get_text("my text").insert(1, "Hello") }
+      .to raise_error(Y::IndexError)
+  end
+
+  it "raises an index error when formatting beyond the end of a XML text" do
+    doc.get_xml_text("my xml text") << "Hello"
+
+    expect { doc.get_xml_text("my xml text").format(3, 5, { bold: true }) }
+      .to raise_error(Y::IndexError, /range 3...8 out of bounds/)
+  end
+
+  it "raises a type error for integers that do not fit into 64 bits" do
+    expect { doc.get_map("my map")[:big] = 2**64 }
+      .to raise_error(Y::TypeError, /64-bit/)
+  end
+
+  it "raises a type error for hash keys that are neither strings or symbols" do
+    expect { doc.get_array("my array") << { 1 => "one" } }
+      .to raise_error(Y::TypeError)
+  end
+
+  it "raises a type error for values that can not be stored" do
+    expect { doc.get_map("my map")[:time] = Time.now }
+      .to raise_error(Y::TypeError, "cannot store value of class Time")
+  end
+
+  it "raises a type error for client ids that are not integers" do
+    expect { Y::Doc.new("client") }.to raise_error(Y::TypeError)
+  end
+
+  it "raises a transaction error for transactions that are no longer active" do
+    transaction = doc.transact { |tx| tx }
+
+    expect { transaction.get_text("my text") }
+      .to raise_error(Y::TransactionError, /no longer active/)
+  end
+
+  it "does not modify the document when a value can not be stored" do
+    array = doc.get_array("my array")
+    begin
+      array << [1, 2**64]
+    rescue Y::TypeError
+      nil
+    end
+
+    expect(array.size).to eq(0)
+  end
+end
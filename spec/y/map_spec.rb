@@ -191,6 +191,16 @@ RSpec.describe Y::Map do
       expect(remote_map).to have_key(:my_array)
       expect(remote_map).to have_key(:my_hash)
     end
+
+    it "reads XML fragments nested by other peers" do
+      # yrs peers can nest XML fragments, e.g. { fragment: <><p/></> }
+      update = [1, 2, 1, 0, 39, 1, 3, 109, 97, 112, 8, 102, 114, 97, 103,
+                109, 101, 110, 116, 4, 7, 0, 1, 0, 3, 1, 112, 0]
+      doc = Y::Doc.new
+      doc.sync(update)
+
+      expect(doc.get_map("map")[:fragment]).to be_a(Y::XMLFragment)
+    end
   end
 
   # rubocop:disable RSpec/ExampleLength
@@ -0,0 +1,56 @@
+# frozen_string_literal: true
+
+RSpec.describe Y::Origin do
+  let(:doc) { Y::Doc.new }
+  let(:text) { doc.get_text("my text") }
+
+  it "passes transaction origin to text observer" do
+    origin = nil
+    text.attach { |_delta, o| origin = o }
+
+    doc.transact(origin: "client-1") { text << "Hello" }
+
+    expect([origin.value, origin.local?]).to eq(["client-1", true])
+  end
+
+  it "passes origin of synced updates to document observer" do
+    remote = Y::Doc.new
+    remote.get_text("my text") << "Hello"
+    origin = nil
+    doc.attach { |_update, o| origin = o }
+
+    doc.sync(remote.diff(doc.state), origin: 42)
+
+    expect([origin.value, origin.remote?]).to eq([42, true])
+  end
+
+  it "marks synced updates without origin as remote" do
+    remote = Y::Doc.new
+    remote.get_array("my array") << 1
+    origin = nil
+    doc.get_array("my array").attach { |_changes, o| origin = o }
+
+    doc.sync(remote.diff(doc.state))
+
+    expect([origin.value, origin.local?]).to eq([nil, false])
+  end
+
+  it "supports remote transactions" do
+    map = doc.get_map("my map")
+    origin = nil
+    map.attach(nil) { |_changes, o| origin = o }
+
+    doc.transact(origin: :server, local: false) { map[:hello] = "world" }
+
+    expect([origin.value, origin.remote?]).to eq([:server, true])
+  end
+
+  it "keeps calling single argument lambdas" do
+    changes = nil
+    text.attach(->(delta) { changes = delta })
+
+    doc.transact(origin: :local) { text << "Hello" }
+
+    expect(changes).to eq({ insert: "Hello" })
+  end
+end
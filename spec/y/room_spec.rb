@@ -0,0 +1,119 @@
+# frozen_string_literal: true
+
+RSpec.describe Y::Room do
+  let(:room) { described_class.new }
+  let(:sent) { Hash.new { |messages, id| messages[id] = [] } }
+
+  let(:update) do
+    remote = Y::Doc.new
+    remote.get_text("my text") << "Hello"
+    Y::Sync::Message.update(remote.diff).encode
+  end
+
+  def connect(*ids)
+    ids.each do |id|
+      room.connect(id) do |message|
+        sent[id] << Y::Sync::Message.decode(message)
+      end
+    end
+  end
+
+  it "starts the sync handshake with new connections" do
+    connect(:a)
+
+    expect(sent[:a].map(&:type)).to eq(%i[sync_step1 awareness])
+  end
+
+  it "replies to the connection a message was received from" do
+    connect(:a, :b)
+    room.receive(:a, Y::Sync::Message.sync_step1(Y::Doc.new.state).encode)
+
+    expect(sent[:a].last.type).to eq(:sync_step2)
+  end
+
+  it "sends received updates to all other connections" do
+    connect(:a, :b, :c)
+    room.receive(:a, update)
+
+    expect(sent.transform_values { |messages| messages.last.type })
+      .to eq({ a: :awareness, b: :update, c: :update })
+  end
+
+  it "sends local changes to all connections" do
+    connect(:a, :b)
+    room.doc.get_text("my text") << "Hello"
+
+    expect(sent.values.map { |messages| messages.last.type })
+      .to eq(%i[update update])
+  end
+
+  it "sends received awareness updates to all other connections" do
+    connect(:a, :b)
+    awareness = Y::Awareness.new
+    awareness.local_state = { name: "User A" }
+    room.receive(:a, Y::Sync::Message.awareness(awareness.diff).encode)
+
+    expect(sent[:b].last.type).to eq(:awareness)
+  end
+
+  it "removes the awareness states of disconnected clients" do
+    connect(:a)
+    awareness = Y::Awareness.new
+    awareness.local_state = { name: "User A" }
+    room.receive(:a, Y::Sync::Message.awareness(awareness.diff).encode)
+    room.disconnect(:a)
+
+    expect(room.awareness.clients).not_to include(awareness.client_id)
+  end
+
+  it "disconnects connections that can not be sent to" do
+    closed = false
+    connect(:a)
+    room.connect(:b) { raise IOError, "closed" if closed }
+    closed = true
+    room.receive(:a, update)
+
+    expect(room.connections).to eq([:a])
+  end
+
+  it "rejects connections with an ID that is already connected" do
+    connect(:a)
+
+    expect { connect(:a) }.to raise_error(ArgumentError)
+  end
+
+  it "stops sending changes once closed" do
+    connect(:a)
+    room.close
+    room.doc.get_text("my text") << "Hello"
+    room.awareness.local_state = { name: "Server" }
+
+    expect(sent[:a].map(&:type)).to eq(%i[sync_step1 awareness])
+  end
+
+  it "disconnects all connections when closed" do
+    connect(:a, :b)
+    room.close
+
+    expect(room.connections).to be_empty
+  end
+
+  context "with an awareness that has a clock" do
+    let(:clock) { Struct.new(:now).new(Time.at(0)) }
+    let(:doc) { Y::Doc.new }
+    let(:room) do
+      described_class.new(doc, Y::Awareness.new(doc, clock: -> { clock.now }))
+    end
+
+    it "sends the removal of outdated clients to all connections" do
+      connect(:a, :b)
+      awareness = Y::Awareness.new
+      awareness.local_state = { name: "User A" }
+      room.receive(:a, Y::Sync::Message.awareness(awareness.diff).encode)
+      clock.now += 30
+      room.remove_outdated(30)
+
+      expect(sent[:a].map(&:type)).to eq(%i[sync_step1 awareness awareness])
+    end
+  end
+end
@@ -0,0 +1,59 @@
+# frozen_string_literal: true
+
+RSpec.describe Y::Snapshot do
+  let(:doc) { Y::Doc.new(skip_gc: true) }
+  let(:text) { doc.get_text("my text") }
+
+  it "restores document state at the time of the snapshot" do
+    text << "Hello"
+    snapshot = doc.snapshot
+    text << ", World!"
+
+    past = Y::Doc.new
+    past.sync(doc.encode_state_from_snapshot(snapshot))
+
+    expect(past.get_text("my text").to_s).to eq("Hello")
+  end
+
+  it "restores deleted content" do
+    text << "Hello, World!"
+    snapshot = doc.snapshot
+    text.slice!(5, 8)
+
+    past = Y::Doc.new
+    past.sync_v2(doc.encode_state_from_snapshot_v2(snapshot))
+
+    expect(past.get_text("my text").to_s).to eq("Hello, World!")
+  end
+
+  it "encodes and decodes snapshot" do
+    text << "Hello"
+    snapshot = doc.snapshot
+
+    expect(described_class.decode(snapshot.encode)).to eq(snapshot)
+  end
+
+  it "encodes and decodes v2 snapshot" do
+    text << "Hello"
+    snapshot = doc.snapshot
+
+    expect(described_class.decode_v2(snapshot.encode_v2)).to eq(snapshot)
+  end
+
+  it "differs from snapshot of a later state" do
+    text << "Hello"
+    snapshot = doc.snapshot
+    text << ", World!"
+
+    expect(doc.snapshot).not_to eq(snapshot)
+  end
+
+  it "fails to restore snapshot when garbage collection is enabled" do
+    gc_doc = Y::Doc.new
+    gc_doc.get_text("my text") << "Hello"
+    snapshot = gc_doc.snapshot
+
+    expect { gc_doc.encode_state_from_snapshot(snapshot) }
+      .to raise_error(Y::Error)
+  end
+end
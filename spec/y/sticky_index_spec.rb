@@ -0,0 +1,61 @@
+# frozen_string_literal: true
+
+RSpec.describe Y::StickyIndex do
+  let(:doc) { Y::Doc.new }
+  let(:text) { doc.get_text("my text", "Hello, World!") }
+
+  it "keeps position when content is inserted before it" do
+    index = text.sticky_index(7)
+    text.insert(0, ">> ")
+
+    expect(index.offset(doc)).to eq(10)
+  end
+
+  it "keeps position when remote content is synced" do
+    index = text.sticky_index(7)
+    remote = Y::Doc.new
+    remote.sync(doc.diff)
+    remote.get_text("my text").insert(0, ">> ")
+
+    doc.sync(remote.diff(doc.state))
+
+    expect(index.offset(doc)).to eq(10)
+  end
+
+  it "keeps position within an array" do
+    arr = doc.get_array("my array", [1, 2, 3])
+    index = arr.sticky_index(1, assoc: :before)
+    arr.unshift(0)
+
+    expect([index.offset(doc), index.assoc]).to eq([2, :before])
+  end
+
+  it "returns nil when index is out of bounds" do
+    expect(text.sticky_index(100)).to be_nil
+  end
+
+  it "raises for unknown association" do
+    expect { text.sticky_index(0, assoc: :middle) }
+      .to raise_error(ArgumentError)
+  end
+
+  it "encodes and decodes sticky index" do
+    index = text.sticky_index(7)
+
+    expect(described_class.decode(index.encode)).to eq(index)
+  end
+
+  it "encodes and decodes sticky index as JSON" do
+    index = text.sticky_index(7)
+
+    expect(described_class.from_json(index.to_json)).to eq(index)
+  end
+
+  it "resolves sticky index in empty type" do
+    empty = doc.get_text("empty")
+    index = empty.sticky_index(0)
+    empty << "Hello"
+
+    expect(index.offset(doc)).to eq(5)
+  end
+end
@@ -0,0 +1,84 @@
+# frozen_string_literal: true
+
+RSpec.describe "Subdocuments" do
+  let(:doc) { Y::Doc.new }
+  let(:map) { doc.get_map("pages") }
+
+  it "inserts a document into a map" do
+    subdoc = Y::Doc.new
+    map[:home] = subdoc
+
+    expect(map[:home].guid).to eq(subdoc.guid)
+  end
+
+  it "inserts a document into an array" do
+    arr = doc.get_array("pages")
+    subdoc = Y::Doc.new
+    arr << subdoc
+
+    expect(arr[0].guid).to eq(subdoc.guid)
+  end
+
+  it "concatenates documents and values to an array" do
+    arr = doc.get_array("pages")
+    subdoc = Y::Doc.new
+    arr.concat([1, subdoc, 2])
+
+    expect([arr[0], arr[1].guid, arr[2]]).to eq([1, subdoc.guid, 2])
+  end
+
+  it "reads subdocument as live document" do
+    map[:home] = Y::Doc.new
+    map[:home].get_text("content") << "Hello, World!"
+
+    expect(map[:home].get_text("content").to_s).to eq("Hello, World!")
+  end
+
+  it "fails to insert the same document twice" do
+    subdoc = Y::Doc.new
+    map[:home] = subdoc
+
+    expect { map[:about] = subdoc }.to raise_error(Y::Error)
+  end
+
+  it "lists subdocuments" do
+    subdoc = Y::Doc.new
+    map[:home] = subdoc
+
+    expect(doc.subdocs.map(&:guid)).to eq([subdoc.guid])
+  end
+
+  it "syncs subdocument reference to remote document" do
+    subdoc = Y::Doc.new
+    map[:home] = subdoc
+
+    remote = Y::Doc.new
+    remote.sync(doc.diff)
+
+    expect(remote.get_map("pages")[:home].guid).to eq(subdoc.guid)
+  end
+
+  it "observes added subdocuments" do
+    event = nil
+    doc.observe_subdocs { |e| event = e }
+
+    subdoc = Y::Doc.new
+    map[:home] = subdoc
+
+    expect(event[:added].map(&:guid)).to eq([subdoc.guid])
+  end
+
+  it "observes removed subdocuments" do
+    map[:home] = Y::Doc.new
+    event = nil
+    doc.observe_subdocs { |e| event = e }
+
+    map.delete(:home)
+
+    expect(event[:removed].size).to eq(1)
+  end
+
+  it "fails to load a document that is not a subdocument" do
+    expect { doc.load }.to raise_error(Y::Error)
+  end
+end
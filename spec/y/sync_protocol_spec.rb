@@ -0,0 +1,91 @@
+# frozen_string_literal: true
+
+RSpec.describe Y::Sync::Protocol do
+  let(:doc) { Y::Doc.new }
+  let(:protocol) { described_class.new(doc) }
+
+  let(:remote) do
+    remote = Y::Doc.new
+    remote.get_text("my text") << "Hello"
+    remote
+  end
+
+  it "starts with the state of the document" do
+    first = Y::Sync::Message.decode(protocol.start.first)
+
+    expect(first).to eq(Y::Sync::Message.sync_step1(doc.state))
+  end
+
+  it "answers the first sync step with the missing updates" do
+    doc.get_text("my text") << "Hello"
+    step1 = Y::Sync::Message.sync_step1(remote.state).encode
+    reply = Y::Sync::Message.decode(protocol.handle(step1).first)
+
+    expect(reply.type).to eq(:sync_step2)
+  end
+
+  it "does not reply to updates" do
+    update = Y::Sync::Message.update(remote.diff).encode
+
+    expect(protocol.handle(update)).to be_empty
+  end
+
+  it "applies the update of the second sync step" do
+    protocol.handle(Y::Sync::Message.sync_step2(remote.diff).encode)
+
+    expect(doc.get_text("my text").to_s).to eq("Hello")
+  end
+
+  it "applies updates with the policy of the protocol" do
+    policy = Y::UpdatePolicy.new(roots: ["other"])
+    protocol = described_class.new(doc, policy: policy)
+    update = Y::Sync::Message.update(remote.diff).encode
+
+    expect { protocol.handle(update) }.to raise_error(Y::UpdateRejected)
+  end
+
+  it "answers an awareness query with the awareness state" do
+    protocol.awareness.local_state = { name: "User A" }
+    query = Y::Sync::Message.query_awareness.encode
+    reply = Y::Sync::Message.decode(protocol.handle(query).first)
+
+    expect(reply.type).to eq(:awareness)
+  end
+
+  it "applies awareness updates" do
+    awareness = Y::Awareness.new
+    awareness.local_state = { name: "User B" }
+    protocol.handle(Y::Sync::Message.awareness(awareness.diff).encode)
+
+    expect(protocol.awareness.clients).to include(awareness.client_id)
+  end
+
+  it "raises when the peer denies the permission" do
+    denied = Y::Sync::Message.auth("read only").encode
+
+    expect { protocol.handle(denied) }
+      .to raise_error(Y::Sync::PermissionDenied, /read only/)
+  end
+
+  it "passes auth messages to the handler" do
+    protocol.on_auth { |message| Y::Sync::Message.auth(message.reason) }
+    denied = Y::Sync::Message.auth("read only").encode
+
+    expect(protocol.handle(denied)).to eq([denied])
+  end
+
+  it "replies with the messages of the custom handler" do
+    pong = Y::Sync::Message.custom(5, "pong")
+    protocol.on_custom { pong }
+    ping = Y::Sync::Message.custom(4, "ping").encode
+
+    expect(protocol.handle(ping)).to eq([pong.encode])
+  end
+
+  it "raises for custom messages without handler" do
+    ping = Y::Sync::Message.custom(4, "ping").encode
+
+    expect { protocol.handle(ping) }
+      .to raise_error(Y::Sync::UnsupportedMessage)
+  end
+end
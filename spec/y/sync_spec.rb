@@ -0,0 +1,78 @@
+# frozen_string_literal: true
+
+RSpec.describe Y::Sync::Message do
+  let(:doc) do
+    doc = Y::Doc.new
+    doc.get_text("my text") << "Hello"
+    doc
+  end
+
+  it "encodes the first sync step with the y-protocols framing" do
+    message = described_class.sync_step1(Y::Doc.new.state)
+
+    expect(message.encode).to eq([0, 0, 1, 0])
+  end
+
+  it "encodes an update with the y-protocols framing" do
+    update = doc.diff
+
+    expect(described_class.update(update).encode)
+      .to eq([0, 2, update.size, *update])
+  end
+
+  it "encodes a denied auth message with its reason" do
+    expect(described_class.auth("nope").encode)
+      .to eq([2, 0, 4, 110, 111, 112, 101])
+  end
+
+  it "encodes an awareness query" do
+    expect(described_class.query_awareness.encode).to eq([3])
+  end
+
+  it "encodes a message as binary string" do
+    expect(described_class.auth.encode(binary: true)).to eq("\x02\x01".b)
+  end
+
+  it "decodes the second sync step" do
+    message = described_class.decode([0, 1, 1, 0])
+
+    expect([message.type, message.payload]).to eq([:sync_step2, [0]])
+  end
+
+  it "decodes a custom message" do
+    message = described_class.decode([42, 2, 1, 2])
+
+    expect([message.type, message.tag, message.payload])
+      .to eq([:custom, 42, [1, 2]])
+  end
+
+  it "decodes the reason of a denied auth message" do
+    encoded = described_class.auth("nope").encode(binary: true)
+
+    expect(described_class.decode(encoded).reason).to eq("nope")
+  end
+
+  it "round trips awareness messages" do
+    awareness = Y::Awareness.new
+    awareness.local_state = { name: "User A" }
+    message = described_class.awareness(awareness.diff)
+
+    expect(described_class.decode(message.encode)).to eq(message)
+  end
+
+  it "applies the update of a decoded update message" do
+    encoded = described_class.update(doc.diff).encode
+    remote = Y::Doc.new
+    remote.sync(described_class.decode(encoded).payload)
+
+    expect(remote.get_text("my text").to_s).to eq("Hello")
+  end
+
+  it "raises a decode error for malformed messages" do
+    expect { described_class.decode([0, 9]) }.to raise_error(Y::DecodeError)
+  end
+
+  it "rejects custom tags that are used by the protocol" do
+    expect { described_class.custom(1, [1]) }.to raise_error(ArgumentError)
+  end
+end
@@ -0,0 +1,99 @@
+# frozen_string_literal: true
+
+RSpec.describe Y::UndoManager do
+  let(:doc) { Y::Doc.new }
+  let(:text) { doc.get_text("my text") }
+
+  it "undoes changes" do
+    manager = described_class.new(doc, text)
+    text << "Hello, World!"
+
+    manager.undo
+
+    expect(text.to_s).to eq("")
+  end
+
+  it "redoes undone changes" do
+    manager = described_class.new(doc, text)
+    text << "Hello, World!"
+
+    manager.undo
+    manager.redo
+
+    expect(text.to_s).to eq("Hello, World!")
+  end
+
+  it "groups changes within capture timeout" do
+    manager = described_class.new(doc, text)
+    text << "Hello"
+    text << ", World!"
+
+    manager.undo
+
+    expect(text.to_s).to eq("")
+  end
+
+  it "stops capturing changes" do
+    manager = described_class.new(doc, text)
+    text << "Hello"
+    manager.stop_capturing
+    text << ", World!"
+
+    manager.undo
+
+    expect(text.to_s).to eq("Hello")
+  end
+
+  it "tracks multiple shared types" do
+    arr = doc.get_array("my array")
+    manager = described_class.new(doc, text, arr)
+    doc.transact do
+      text << "Hello"
+      arr << 1
+    end
+
+    manager.undo
+
+    expect([text.to_s, arr.to_a]).to eq(["", []])
+  end
+
+  it "tracks changes of given origins only" do
+    manager = described_class.new(doc, text, tracked_origins: [:local])
+    doc.transact(origin: :local) { text << "Hello" }
+    doc.transact(origin: :remote) { text << ", World!" }
+
+    manager.undo
+
+    expect(text.to_s).to eq(", World!")
+  end
+
+  it "clears undo stack" do
+    manager = described_class.new(doc, text)
+    text << "Hello, World!"
+
+    manager.clear
+
+    expect(manager.undo?).to be(false)
+  end
+
+  it "calls listener when item is added" do
+    manager = described_class.new(doc, text)
+    event = nil
+    manager.on_item_added { |e| event = e }
+
+    text << "Hello, World!"
+
+    expect(event).to include(kind: :undo, origin: nil)
+  end
+
+  it "calls listener when item is popped" do
+    manager = described_class.new(doc, text)
+    event = nil
+    manager.on_item_popped { |e| event = e }
+    text << "Hello, World!"
+
+    manager.undo
+
+    expect(event[:kind]).to eq(:undo)
+  end
+end
@@ -0,0 +1,115 @@
+# frozen_string_literal: true
+
+RSpec.describe Y::UpdatePolicy do
+  let(:remote) { Y::Doc.new(42) }
+  let(:doc) { Y::Doc.new }
+
+  it "accepts updates that comply with the policy" do
+    remote.get_text("content") << "Hello"
+    policy = described_class.new(roots: ["content"], types: { content: :text })
+
+    doc.sync(remote.diff, policy: policy)
+
+    expect(doc.get_text("content").to_s).to eq("Hello")
+  end
+
+  it "rejects updates that exceed the maximum size" do
+    remote.get_text("content") << ("a" * 100)
+    policy = described_class.new(max_size: 64)
+
+    expect(doc.validate_update(remote.diff, policy).reason).to eq(:size)
+  end
+
+  it "rejects updates from other clients" do
+    remote.get_text("content") << "Hello"
+    policy = described_class.new(clients: [1])
+    violation = doc.validate_update(remote.diff, policy)
+
+    expect([violation.reason, violation.client]).to eq([:client, 42])
+  end
+
+  it "rejects updates to other root types" do
+    remote.get_map("meta")[:owner] = "mallory"
+    policy = described_class.new(roots: ["content"])
+    violation = doc.validate_update(remote.diff, policy)
+
+    expect([violation.reason, violation.root]).to eq([:root, "meta"])
+  end
+
+  it "rejects content that does not match the type of a root type" do
+    remote.get_map("content")[:key] = "value"
+    policy = described_class.new(types: { "content" => :text })
+    violation = doc.validate_update(remote.diff, policy)
+
+    expect([violation.reason, violation.root]).to eq([:type, "content"])
+  end
+
+  it "resolves root types of blocks positioned next to existing content" do
+    remote.get_text("content") << "Hello"
+    doc.sync(remote.diff)
+    state = doc.state
+    remote.get_text("content") << ", World!"
+
+    policy = described_class.new(roots: ["content"])
+    expect(doc.validate_update(remote.diff(state), policy)).to be_nil
+  end
+
+  it "attributes deletions to the root type they are made in" do
+    remote.get_text("content") << "Hello"
+    remote.get_map("meta")[:owner] = "me"
+    doc.sync(remote.diff)
+    remote.get_map("meta").delete(:owner)
+
+    policy = described_class.new(roots: ["content"])
+    expect(doc.validate_update(remote.diff, policy).root).to eq("meta")
+  end
+
+  it "raises for rejected updates" do
+    remote.get_map("meta")[:owner] = "mallory"
+    policy = described_class.new(roots: ["content"])
+
+    expect { doc.sync(remote.diff, policy: policy) }
+      .to raise_error(Y::UpdateRejected, /"meta" may not be modified/)
+  end
+
+  it "does not apply rejected updates", :aggregate_failures do
+    remote.get_map("meta")[:owner] = "mallory"
+    policy = described_class.new(roots: ["content"])
+
+    expect { doc.sync(remote.diff, policy: policy) }
+      .to raise_error(Y::UpdateRejected)
+    expect(doc.get_map("meta").size).to eq(0)
+  end
+
+  it "rejects updates that depend on content the document does not contain" do
+    remote.get_text("meta") << "Hello"
+    state = remote.state
+    remote.get_text("meta") << ", World!"
+    policy = described_class.new(roots: ["content"])
+    violation = doc.validate_update(remote.diff(state), policy)
+
+    expect(violation.reason).to eq(:unresolved)
+  end
+
+  it "rejects deletions of content the document does not contain" do
+    remote.get_text("meta") << "Hello"
+    state = remote.state
+    remote.get_text("meta").slice!(0, 2)
+    policy = described_class.new(roots: ["content"])
+    violation = doc.validate_update(remote.diff(state), policy)
+
+    expect(violation.reason).to eq(:unresolved)
+  end
+
+  it "validates v2 updates" do
+    remote.get_map("meta")[:owner] = "mallory"
+    policy = described_class.new(roots: ["content"])
+
+    expect(doc.validate_update_v2(remote.diff_v2, policy).reason).to eq(:root)
+  end
+
+  it "raises for unsupported types" do
+    expect { described_class.new(types: { "content" => :list }) }
+      .to raise_error(ArgumentError, /unsupported type/)
+  end
+end
@@ -0,0 +1,146 @@
+# frozen_string_literal: true
+
+RSpec.describe Y::Update do
+  let(:doc) { Y::Doc.new }
+  let(:text) { doc.get_text("my text") }
+
+  def restore(update)
+    remote = Y::Doc.new
+    remote.sync(update)
+    remote.get_text("my text").to_s
+  end
+
+  it "merges updates" do
+    text << "Hello"
+    first = doc.diff
+    state = doc.state
+    text << ", World!"
+
+    update = described_class.merge([first, doc.diff(state)])
+
+    expect(restore(update)).to eq("Hello, World!")
+  end
+
+  it "merges v2 updates" do
+    text << "Hello"
+    first = doc.diff_v2
+    state = doc.state_v2
+    text << ", World!"
+
+    update = described_class.merge_v2([first, doc.diff_v2(state)])
+
+    expect(restore(described_class.v2_to_v1(update))).to eq("Hello, World!")
+  end
+
+  it "computes the diff against a state vector" do
+    text << "Hello"
+    remote = Y::Doc.new
+    remote.sync(doc.diff)
+    text << ", World!"
+
+    remote.sync(described_class.diff(doc.diff, remote.state))
+
+    expect(remote.get_text("my text").to_s).to eq("Hello, World!")
+  end
+
+  it "extracts the state vector from an update" do
+    text << "Hello"
+
+    expect(described_class.state(doc.diff)).to eq(doc.state)
+  end
+
+  it "extracts the v2 state vector from a v2 update" do
+    text << "Hello"
+
+    expect(described_class.state_v2(doc.diff_v2)).to eq(doc.state_v2)
+  end
+
+  it "converts between v1 and v2 updates" do
+    text << "Hello"
+    update = described_class.v1_to_v2(doc.diff)
+
+    expect(restore(described_class.v2_to_v1(update))).to eq("Hello")
+  end
+
+  it "returns binary strings" do
+    text << "Hello"
+    update = described_class.merge([doc.diff(binary: true)], binary: true)
+
+    expect(update.encoding).to eq(Encoding::BINARY)
+  end
+
+  context "when compacting updates" do
+    let(:doc) { Y::Doc.new(skip_gc: true) }
+
+    before do
+      text << ("Hello" * 100)
+      text.slice!(0, 495)
+    end
+
+    it "removes deleted content" do
+      expect(described_class.compact(doc.diff).size).to be < doc.diff.size
+    end
+
+    it "preserves live content" do
+      expect(restore(described_class.compact(doc.diff))).to eq("Hello")
+    end
+
+    it "compacts v2 updates" do
+      update = described_class.compact_v2(doc.diff_v2)
+
+      expect(restore(described_class.v2_to_v1(update))).to eq("Hello")
+    end
+
+    it "keeps blocks with missing dependencies" do
+      remote = Y::Doc.new
+      remote.sync(doc.diff)
+      state = doc.state
+      text << ", World!"
+      remote.sync(described_class.compact(doc.diff(state)))
+
+      expect(remote.get_text("my text").to_s).to eq("Hello, World!")
+    end
+  end
+
+  context "when decoding updates" do
+    let(:doc) { Y::Doc.new(42) }
+    let(:map) { doc.get_map("my map") }
+
+    it "describes blocks per client" do
+      text << "Hello"
+      map[:key] = 1
+
+      expect(described_class.decode(doc.diff)[:blocks]).to eq(
+        42 => [
+          { clock: 0...5, kind: :string, parent: "my text", key: nil },
+          { clock: 5...6, kind: :any, parent: "my map", key: "key" }
+        ]
+      )
+    end
+
+    it "describes the delete set" do
+      text << "Hello"
+      text.slice!(1, 2)
+
+      expect(described_class.decode(doc.diff)[:delete_set])
+        .to eq(42 => [1...3])
+    end
+
+    it "decodes v2 updates" do
+      text << "Hello"
+
+      expect(described_class.decode_v2(doc.diff_v2))
+        .to eq(described_class.decode(doc.diff))
+    end
+
+    it "raises for malformed updates" do
+      expect { described_class.decode([1, 2, 3]) }
+        .to raise_error(Y::DecodeError, /cannot decode update/)
+    end
+  end
+
+  it "raises when merging malformed updates" do
+    expect { described_class.merge([[1, 2, 3]]) }
+      .to raise_error(Y::DecodeError, /cannot merge updates/)
+  end
+end